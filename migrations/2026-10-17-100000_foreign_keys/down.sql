@@ -0,0 +1,36 @@
+-- This file should undo anything in `up.sql`
+CREATE TABLE ingredient_macros_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ingredient_id INTEGER NOT NULL,
+    proteins FLOAT NOT NULL,
+    carbs FLOAT NOT NULL,
+    fats FLOAT NOT NULL,
+    alcohols FLOAT NOT NULL,
+    calories FLOAT NOT NULL
+);
+INSERT INTO ingredient_macros_old (id, ingredient_id, proteins, carbs, fats, alcohols, calories)
+    SELECT id, ingredient_id, proteins, carbs, fats, alcohols, calories FROM ingredient_macros;
+DROP TABLE ingredient_macros;
+ALTER TABLE ingredient_macros_old RENAME TO ingredient_macros;
+
+CREATE TABLE meal_ingredients_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    meal_id INTEGER NOT NULL,
+    ingredient_id INTEGER NOT NULL,
+    mass INTEGER NOT NULL
+);
+INSERT INTO meal_ingredients_old (id, meal_id, ingredient_id, mass)
+    SELECT id, meal_id, ingredient_id, mass FROM meal_ingredients;
+DROP TABLE meal_ingredients;
+ALTER TABLE meal_ingredients_old RENAME TO meal_ingredients;
+
+CREATE TABLE recipe_ingredients_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipe_id INTEGER NOT NULL,
+    ingredient_id INTEGER NOT NULL,
+    mass INTEGER NOT NULL
+);
+INSERT INTO recipe_ingredients_old (id, recipe_id, ingredient_id, mass)
+    SELECT id, recipe_id, ingredient_id, mass FROM recipe_ingredients;
+DROP TABLE recipe_ingredients;
+ALTER TABLE recipe_ingredients_old RENAME TO recipe_ingredients;
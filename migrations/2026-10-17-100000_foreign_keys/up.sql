@@ -0,0 +1,45 @@
+-- SQLite can not add constraints to existing tables, so the referencing tables are rebuilt.
+-- Rows pointing at nonexistent items are dropped, they would violate the new constraints.
+CREATE TABLE ingredient_macros_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    proteins FLOAT NOT NULL,
+    carbs FLOAT NOT NULL,
+    fats FLOAT NOT NULL,
+    alcohols FLOAT NOT NULL,
+    calories FLOAT NOT NULL
+);
+INSERT INTO ingredient_macros_new (id, ingredient_id, proteins, carbs, fats, alcohols, calories)
+    SELECT id, ingredient_id, proteins, carbs, fats, alcohols, calories FROM ingredient_macros
+    WHERE ingredient_id IN (SELECT id FROM ingredients);
+DROP TABLE ingredient_macros;
+ALTER TABLE ingredient_macros_new RENAME TO ingredient_macros;
+CREATE INDEX ingredient_macros_ingredient_id ON ingredient_macros (ingredient_id);
+
+CREATE TABLE meal_ingredients_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    meal_id INTEGER NOT NULL REFERENCES meals (id) ON DELETE CASCADE,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    mass INTEGER NOT NULL
+);
+INSERT INTO meal_ingredients_new (id, meal_id, ingredient_id, mass)
+    SELECT id, meal_id, ingredient_id, mass FROM meal_ingredients
+    WHERE meal_id IN (SELECT id FROM meals) AND ingredient_id IN (SELECT id FROM ingredients);
+DROP TABLE meal_ingredients;
+ALTER TABLE meal_ingredients_new RENAME TO meal_ingredients;
+CREATE INDEX meal_ingredients_meal_id ON meal_ingredients (meal_id);
+CREATE INDEX meal_ingredients_ingredient_id ON meal_ingredients (ingredient_id);
+
+CREATE TABLE recipe_ingredients_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipe_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    mass INTEGER NOT NULL
+);
+INSERT INTO recipe_ingredients_new (id, recipe_id, ingredient_id, mass)
+    SELECT id, recipe_id, ingredient_id, mass FROM recipe_ingredients
+    WHERE recipe_id IN (SELECT id FROM recipes) AND ingredient_id IN (SELECT id FROM ingredients);
+DROP TABLE recipe_ingredients;
+ALTER TABLE recipe_ingredients_new RENAME TO recipe_ingredients;
+CREATE INDEX recipe_ingredients_recipe_id ON recipe_ingredients (recipe_id);
+CREATE INDEX recipe_ingredients_ingredient_id ON recipe_ingredients (ingredient_id);
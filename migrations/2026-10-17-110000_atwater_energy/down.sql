@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+UPDATE ingredient_macros SET calories = proteins * 4 + carbs * 4 + fats * 4 + alcohols * 4;
+ALTER TABLE ingredient_macros DROP COLUMN polyols;
+ALTER TABLE ingredient_macros DROP COLUMN fiber;
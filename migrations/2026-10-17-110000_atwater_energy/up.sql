@@ -0,0 +1,6 @@
+-- Fiber and polyols are stored separately from carbs, they contribute less energy
+ALTER TABLE ingredient_macros ADD COLUMN fiber FLOAT NOT NULL DEFAULT 0;
+ALTER TABLE ingredient_macros ADD COLUMN polyols FLOAT NOT NULL DEFAULT 0;
+
+-- Calories were computed with 4 kcal/g for all macros, recompute them with the Atwater factors
+UPDATE ingredient_macros SET calories = proteins * 4 + carbs * 4 + fats * 9 + alcohols * 7;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE recipes DROP COLUMN yield_mass;
+ALTER TABLE recipes DROP COLUMN servings;
@@ -0,0 +1,4 @@
+-- Number of servings a recipe yields and optionally the mass of the finished dish, which differs
+-- from the sum of its raw ingredients when water is lost or absorbed during cooking
+ALTER TABLE recipes ADD COLUMN servings INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE recipes ADD COLUMN yield_mass INTEGER;
@@ -0,0 +1,9 @@
+-- Recipes used as component of another recipe, the mass refers to the finished component recipe
+CREATE TABLE recipe_components (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipe_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    component_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    mass INTEGER NOT NULL
+);
+CREATE INDEX recipe_components_recipe_id ON recipe_components (recipe_id);
+CREATE INDEX recipe_components_component_id ON recipe_components (component_id);
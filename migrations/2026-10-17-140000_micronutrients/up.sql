@@ -0,0 +1,28 @@
+-- Nutrients besides the macros, identified by a stable code. Amounts are given in `unit` per
+-- 100 g of an ingredient. Fiber and polyols stay in ingredient_macros as they contribute energy.
+CREATE TABLE micronutrients (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    code TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    unit TEXT NOT NULL
+);
+INSERT INTO micronutrients (code, name, unit) VALUES
+    ('sugars', 'Sugars', 'g'),
+    ('saturates', 'Saturated fat', 'g'),
+    ('salt', 'Salt', 'g'),
+    ('sodium', 'Sodium', 'mg'),
+    ('vitamin_a', 'Vitamin A', 'µg'),
+    ('vitamin_c', 'Vitamin C', 'mg'),
+    ('vitamin_d', 'Vitamin D', 'µg'),
+    ('calcium', 'Calcium', 'mg'),
+    ('iron', 'Iron', 'mg'),
+    ('potassium', 'Potassium', 'mg');
+
+CREATE TABLE ingredient_micros (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    micronutrient_id INTEGER NOT NULL REFERENCES micronutrients (id) ON DELETE CASCADE,
+    amount FLOAT NOT NULL,
+    UNIQUE (ingredient_id, micronutrient_id)
+);
+CREATE INDEX ingredient_micros_micronutrient_id ON ingredient_micros (micronutrient_id);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE recipe_ingredients DROP COLUMN unit;
+ALTER TABLE recipe_ingredients DROP COLUMN quantity;
+ALTER TABLE meal_ingredients DROP COLUMN unit;
+ALTER TABLE meal_ingredients DROP COLUMN quantity;
+DROP TABLE ingredient_portions;
+ALTER TABLE ingredients DROP COLUMN density;
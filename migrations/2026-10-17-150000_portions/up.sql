@@ -0,0 +1,17 @@
+-- Density in g/ml to convert volumes to masses
+ALTER TABLE ingredients ADD COLUMN density FLOAT;
+
+-- Named portions of an ingredient, e.g. one egg or one slice, with their mass in gram
+CREATE TABLE ingredient_portions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    mass FLOAT NOT NULL,
+    UNIQUE (ingredient_id, name)
+);
+
+-- Quantity and unit as entered, the mass in gram is derived from them
+ALTER TABLE meal_ingredients ADD COLUMN quantity FLOAT;
+ALTER TABLE meal_ingredients ADD COLUMN unit TEXT;
+ALTER TABLE recipe_ingredients ADD COLUMN quantity FLOAT;
+ALTER TABLE recipe_ingredients ADD COLUMN unit TEXT;
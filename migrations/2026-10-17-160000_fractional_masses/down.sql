@@ -0,0 +1,50 @@
+-- This file should undo anything in `up.sql`
+CREATE TABLE meal_ingredients_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    meal_id INTEGER NOT NULL REFERENCES meals (id) ON DELETE CASCADE,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    mass INTEGER NOT NULL,
+    quantity FLOAT,
+    unit TEXT
+);
+INSERT INTO meal_ingredients_new (id, meal_id, ingredient_id, mass, quantity, unit)
+    SELECT id, meal_id, ingredient_id, CAST(ROUND(mass) AS INTEGER), quantity, unit
+    FROM meal_ingredients;
+DROP TABLE meal_ingredients;
+ALTER TABLE meal_ingredients_new RENAME TO meal_ingredients;
+CREATE INDEX meal_ingredients_meal_id ON meal_ingredients (meal_id);
+CREATE INDEX meal_ingredients_ingredient_id ON meal_ingredients (ingredient_id);
+
+CREATE TABLE recipe_ingredients_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipe_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    mass INTEGER NOT NULL,
+    quantity FLOAT,
+    unit TEXT
+);
+INSERT INTO recipe_ingredients_new (id, recipe_id, ingredient_id, mass, quantity, unit)
+    SELECT id, recipe_id, ingredient_id, CAST(ROUND(mass) AS INTEGER), quantity, unit
+    FROM recipe_ingredients;
+DROP TABLE recipe_ingredients;
+ALTER TABLE recipe_ingredients_new RENAME TO recipe_ingredients;
+CREATE INDEX recipe_ingredients_recipe_id ON recipe_ingredients (recipe_id);
+CREATE INDEX recipe_ingredients_ingredient_id ON recipe_ingredients (ingredient_id);
+
+CREATE TABLE recipe_components_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipe_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    component_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    mass INTEGER NOT NULL
+);
+INSERT INTO recipe_components_new (id, recipe_id, component_id, mass)
+    SELECT id, recipe_id, component_id, CAST(ROUND(mass) AS INTEGER) FROM recipe_components;
+DROP TABLE recipe_components;
+ALTER TABLE recipe_components_new RENAME TO recipe_components;
+CREATE INDEX recipe_components_recipe_id ON recipe_components (recipe_id);
+CREATE INDEX recipe_components_component_id ON recipe_components (component_id);
+
+ALTER TABLE recipes ADD COLUMN yield_mass_new INTEGER;
+UPDATE recipes SET yield_mass_new = CAST(ROUND(yield_mass) AS INTEGER);
+ALTER TABLE recipes DROP COLUMN yield_mass;
+ALTER TABLE recipes RENAME COLUMN yield_mass_new TO yield_mass;
@@ -0,0 +1,50 @@
+-- Store masses as real numbers to allow fractions of a gram, negative masses are rejected
+CREATE TABLE meal_ingredients_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    meal_id INTEGER NOT NULL REFERENCES meals (id) ON DELETE CASCADE,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    mass FLOAT NOT NULL CHECK (mass >= 0),
+    quantity FLOAT,
+    unit TEXT
+);
+INSERT INTO meal_ingredients_new (id, meal_id, ingredient_id, mass, quantity, unit)
+    SELECT id, meal_id, ingredient_id, MAX(mass, 0), quantity, unit FROM meal_ingredients;
+DROP TABLE meal_ingredients;
+ALTER TABLE meal_ingredients_new RENAME TO meal_ingredients;
+CREATE INDEX meal_ingredients_meal_id ON meal_ingredients (meal_id);
+CREATE INDEX meal_ingredients_ingredient_id ON meal_ingredients (ingredient_id);
+
+CREATE TABLE recipe_ingredients_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipe_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    mass FLOAT NOT NULL CHECK (mass >= 0),
+    quantity FLOAT,
+    unit TEXT
+);
+INSERT INTO recipe_ingredients_new (id, recipe_id, ingredient_id, mass, quantity, unit)
+    SELECT id, recipe_id, ingredient_id, MAX(mass, 0), quantity, unit FROM recipe_ingredients;
+DROP TABLE recipe_ingredients;
+ALTER TABLE recipe_ingredients_new RENAME TO recipe_ingredients;
+CREATE INDEX recipe_ingredients_recipe_id ON recipe_ingredients (recipe_id);
+CREATE INDEX recipe_ingredients_ingredient_id ON recipe_ingredients (ingredient_id);
+
+CREATE TABLE recipe_components_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipe_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    component_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    mass FLOAT NOT NULL CHECK (mass >= 0)
+);
+INSERT INTO recipe_components_new (id, recipe_id, component_id, mass)
+    SELECT id, recipe_id, component_id, MAX(mass, 0) FROM recipe_components;
+DROP TABLE recipe_components;
+ALTER TABLE recipe_components_new RENAME TO recipe_components;
+CREATE INDEX recipe_components_recipe_id ON recipe_components (recipe_id);
+CREATE INDEX recipe_components_component_id ON recipe_components (component_id);
+
+-- yield_mass is the last column of recipes, so it is replaced without rebuilding the table, which
+-- is referenced by other tables
+ALTER TABLE recipes ADD COLUMN yield_mass_new FLOAT;
+UPDATE recipes SET yield_mass_new = yield_mass;
+ALTER TABLE recipes DROP COLUMN yield_mass;
+ALTER TABLE recipes RENAME COLUMN yield_mass_new TO yield_mass;
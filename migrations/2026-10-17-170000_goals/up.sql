@@ -0,0 +1,15 @@
+-- Daily nutrition targets, optionally restricted to a weekday (0 = monday) and a date range
+CREATE TABLE goals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    weekday INTEGER CHECK (weekday BETWEEN 0 AND 6),
+    start_date DATE,
+    end_date DATE,
+    calories FLOAT,
+    proteins FLOAT,
+    carbs FLOAT,
+    fats FLOAT,
+    alcohols FLOAT,
+    fiber FLOAT,
+    polyols FLOAT
+);
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX goals_profile_id;
+DROP INDEX meals_profile_id;
+DROP INDEX recipes_profile_id;
+DROP INDEX ingredients_profile_id;
+ALTER TABLE goals DROP COLUMN profile_id;
+ALTER TABLE meals DROP COLUMN profile_id;
+ALTER TABLE recipes DROP COLUMN profile_id;
+ALTER TABLE ingredients DROP COLUMN profile_id;
+DROP TABLE profiles;
@@ -0,0 +1,16 @@
+-- Profiles of the people sharing a database. Meals and goals belong to a profile, recipes and
+-- ingredients may be private to a profile. Rows without a profile are shared.
+CREATE TABLE profiles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE
+);
+
+ALTER TABLE ingredients ADD COLUMN profile_id INTEGER REFERENCES profiles (id) ON DELETE SET NULL;
+ALTER TABLE recipes ADD COLUMN profile_id INTEGER REFERENCES profiles (id) ON DELETE SET NULL;
+ALTER TABLE meals ADD COLUMN profile_id INTEGER REFERENCES profiles (id) ON DELETE CASCADE;
+ALTER TABLE goals ADD COLUMN profile_id INTEGER REFERENCES profiles (id) ON DELETE CASCADE;
+
+CREATE INDEX ingredients_profile_id ON ingredients (profile_id);
+CREATE INDEX recipes_profile_id ON recipes (profile_id);
+CREATE INDEX meals_profile_id ON meals (profile_id);
+CREATE INDEX goals_profile_id ON goals (profile_id);
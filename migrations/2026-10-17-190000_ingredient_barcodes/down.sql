@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX ingredients_barcode;
+ALTER TABLE ingredients DROP COLUMN barcode;
@@ -0,0 +1,4 @@
+-- EAN-8 or EAN-13 code printed on the package of an ingredient, UPC-A codes are stored as EAN-13
+ALTER TABLE ingredients ADD COLUMN barcode TEXT;
+
+CREATE UNIQUE INDEX ingredients_barcode ON ingredients (barcode);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX ingredients_source;
+ALTER TABLE ingredients DROP COLUMN source_id;
+ALTER TABLE ingredients DROP COLUMN source;
@@ -0,0 +1,6 @@
+-- Record an imported ingredient was created from, e.g. source 'fdc' with the FoodData Central id,
+-- so a later import of the same source updates the ingredient instead of duplicating it
+ALTER TABLE ingredients ADD COLUMN source TEXT;
+ALTER TABLE ingredients ADD COLUMN source_id TEXT;
+
+CREATE UNIQUE INDEX ingredients_source ON ingredients (source, source_id);
@@ -3,11 +3,19 @@ use std::env::args;
 #[derive(Debug, PartialEq)]
 enum CmdPattern {
     Unknown,
+    Dump,
+    Goal,
+    Import,
     Ingredient,
     IngredientMacro,
+    IngredientMicro,
+    IngredientPortion,
+    Micronutrient,
     Meal,
     MealIngredient,
+    Profile,
     Recipe,
+    RecipeComponent,
     RecipeIngredient,
     Help,
 }
@@ -19,29 +27,70 @@ enum SubCmdPattern {
     Read,
     Update,
     Delete,
+    List,
+    Search,
+    Recompute,
+    Nutrients,
+    Report,
+    AddRecipe,
+    Progress,
+    Barcode,
+    Ingredients,
+    Fdc,
+    Off,
+    Restore,
     Help
 }
 
 fn usage() {
-    println!("Usage: {} <cmd> <subcmd> ", args().nth(0).unwrap());
+    println!("Usage: {} <cmd> <subcmd> ", args().next().unwrap());
     println!("Where cmd holds the table to insert to:");
     println!("\t* ingredient\t* ingredient_macro");
     println!("\t* meal\t\t* meal_ingredient");
     println!("\t* recipe\t* recipe_ingredient");
+    println!("\t* recipe_component\t* micronutrient");
+    println!("\t* ingredient_micro\t* portion");
+    println!("\t* goal\t\t* profile");
+    println!("\t* import\t* dump");
     println!("\t* help");
     println!("And cmd is one of");
     println!("\t* create\t* read");
     println!("\t* update\t* delete");
+    println!("\t* list [limit] [offset]");
+    println!("\t* search <query> [prefix|substring|fuzzy] [limit] [profile] (ingredient only)");
+    println!("\t* barcode <ean or upc> (ingredient only)");
+    println!("\t* recompute [atwater|eu] (ingredient_macro only)");
+    println!("\t* nutrients <id> (meal and recipe only)");
+    println!("\t* report <start> <end> [day|week|month] [profile] (meal only)");
+    println!("\t* add_recipe <meal id> <recipe id> <amount> [servings|g] (meal only)");
+    println!("\t* progress <start> [end] [profile] (goal only)");
+    println!("\t* ingredients <file> [--dry-run] [--delimiter=;] [field=column]... (import only)");
+    println!("\t* fdc <json file or csv directory> [--dry-run] (import only)");
+    println!("\t* off <csv or jsonl file> [--dry-run] [--country=germany] [--language=de] \
+        (import only)");
+    println!("\t* create <file> (dump only), writes all tables to a JSON file");
+    println!("\t* restore <file> (dump only), adds the rows of a dump with new ids");
+    println!("Meals, goals, recipes and ingredients take the name of their profile as last");
+    println!("optional argument, use - to skip optional arguments.");
+    println!("Masses of meal and recipe ingredients may be given as quantity, e.g. 2 cups");
     println!("For each command a help method is available, which gives a deeper understanding.");
 }
 
 fn decode_cmd(cmd_str: &str) -> CmdPattern {
     match cmd_str {
+        "dump" => CmdPattern::Dump,
+        "goal" => CmdPattern::Goal,
+        "import" => CmdPattern::Import,
         "ingredient" => CmdPattern::Ingredient,
         "ingredient_macro" => CmdPattern::IngredientMacro,
+        "ingredient_micro" => CmdPattern::IngredientMicro,
+        "micronutrient" => CmdPattern::Micronutrient,
+        "portion" => CmdPattern::IngredientPortion,
         "meal" => CmdPattern::Meal,
         "meal_ingredient" => CmdPattern::MealIngredient,
+        "profile" => CmdPattern::Profile,
         "recipe" => CmdPattern::Recipe,
+        "recipe_component" => CmdPattern::RecipeComponent,
         "recipe_ingredient" => CmdPattern::RecipeIngredient,
         "help" => CmdPattern::Help,
         _ => CmdPattern::Unknown
@@ -54,6 +103,18 @@ fn decode_subcmd(subcmd_str: &str) -> SubCmdPattern {
         "read" => SubCmdPattern::Read,
         "update" => SubCmdPattern::Update,
         "delete" => SubCmdPattern::Delete,
+        "list" => SubCmdPattern::List,
+        "search" => SubCmdPattern::Search,
+        "recompute" => SubCmdPattern::Recompute,
+        "nutrients" => SubCmdPattern::Nutrients,
+        "report" => SubCmdPattern::Report,
+        "add_recipe" => SubCmdPattern::AddRecipe,
+        "progress" => SubCmdPattern::Progress,
+        "barcode" => SubCmdPattern::Barcode,
+        "ingredients" => SubCmdPattern::Ingredients,
+        "fdc" => SubCmdPattern::Fdc,
+        "off" => SubCmdPattern::Off,
+        "restore" => SubCmdPattern::Restore,
         "help" => SubCmdPattern::Help,
         _ => SubCmdPattern::Unknown
     }
@@ -76,6 +137,27 @@ fn decode_i32(pos: usize) -> i32 {
     }
 }
 
+fn decode_i32_or(pos: usize, default: i32) -> i32 {
+    decode_optional_i32(pos).unwrap_or(default)
+}
+
+fn decode_optional_i32(pos: usize) -> Option<i32> {
+    args().nth(pos).filter(|value| value != "-").map(|value| match value.parse::<i32>() {
+        Ok(value) => value,
+        Err(e) => panic!("Could not parse number: {}", e)
+    })
+}
+
+fn decode_i64_or(pos: usize, default: i64) -> i64 {
+    match args().nth(pos) {
+        Some(value) => match value.parse::<i64>() {
+            Ok(value) => value,
+            Err(e) => panic!("Could not parse number: {}", e)
+        },
+        None => default
+    }
+}
+
 fn decode_f32(pos: usize) -> f32 {
     match args().nth(pos) {
         Some(id) => {
@@ -103,9 +185,100 @@ fn decode_string(pos: usize) -> String {
     }
 }
 
+fn decode_optional_string(pos: usize) -> Option<String> {
+    args().nth(pos).filter(|value| value != "-")
+}
+
+fn decode_search_mode(pos: usize) -> SearchMode {
+    match args().nth(pos).as_deref() {
+        Some("prefix") => SearchMode::Prefix,
+        Some("substring") => SearchMode::Substring,
+        Some("fuzzy") | Some("-") | None => SearchMode::Fuzzy,
+        Some(mode) => panic!("Unknown search mode: {}", mode)
+    }
+}
+
+fn decode_energy_model(pos: usize) -> EnergyModel {
+    match args().nth(pos).as_deref() {
+        Some("atwater") | None => EnergyModel::ATWATER,
+        Some("eu") => EnergyModel::ATWATER_WITH_FIBER,
+        Some(model) => panic!("Unknown energy model: {}", model)
+    }
+}
+
+fn decode_optional_f32(pos: usize) -> Option<f32> {
+    args().nth(pos).filter(|value| value != "-").map(|value| match value.parse::<f32>() {
+        Ok(value) => value,
+        Err(e) => panic!("Could not parse number: {}", e)
+    })
+}
+
+/**
+ * Decode all arguments starting at `pos` as a quantity, e.g. `2 cups`. A plain number is a mass
+ * in gram and returned as `Err`.
+ */
+fn decode_quantity(pos: usize) -> Result<Quantity, f32> {
+    let text = args().skip(pos).collect::<Vec<String>>().join(" ");
+    if let Ok(mass) = text.parse::<f32>() {
+        return Err(mass)
+    }
+    match Quantity::parse(&text) {
+        Ok(quantity) => Ok(quantity),
+        Err(e) => panic!("Could not parse quantity: {}", e)
+    }
+}
+
+fn format_mass(mass: f32, quantity: Option<Quantity>) -> String {
+    match quantity {
+        Some(quantity) => format!("{} g ({})", mass, quantity),
+        None => format!("{} g", mass)
+    }
+}
+
+fn decode_f32_or(pos: usize, default: f32) -> f32 {
+    match args().nth(pos) {
+        Some(value) => match value.parse::<f32>() {
+            Ok(value) => value,
+            Err(e) => panic!("Could not parse number: {}", e)
+        },
+        None => default
+    }
+}
+
+fn decode_period(pos: usize) -> Period {
+    match args().nth(pos).as_deref() {
+        Some("day") | Some("-") | None => Period::Day,
+        Some("week") => Period::Week,
+        Some("month") => Period::Month,
+        Some(period) => panic!("Unknown period: {}", period)
+    }
+}
+
+fn decode_portion(pos: usize) -> RecipePortion {
+    let amount = decode_f32(pos);
+    match args().nth(pos + 1).as_deref() {
+        Some("servings") | None => RecipePortion::Servings(amount),
+        Some("g") => RecipePortion::Mass(amount),
+        Some(unit) => panic!("Unknown unit: {}", unit)
+    }
+}
+
+fn print_nutrients(indent: &str, nutrients: &Nutrients) {
+    println!("{}proteins: {:.1} g", indent, nutrients.proteins);
+    println!("{}carbs: {:.1} g", indent, nutrients.carbs);
+    println!("{}fats: {:.1} g", indent, nutrients.fats);
+    println!("{}alcohols: {:.1} g", indent, nutrients.alcohols);
+    println!("{}fiber: {:.1} g", indent, nutrients.fiber);
+    println!("{}polyols: {:.1} g", indent, nutrients.polyols);
+    println!("{}calories: {:.0} kcal", indent, nutrients.calories);
+    for (code, amount) in nutrients.micros.iter() {
+        println!("{}{}: {:.2}", indent, code, amount);
+    }
+}
+
 fn decode_date(pos: usize) -> chrono::NaiveDate {
     match args().nth(pos) {
-        Some(s) => match chrono::NaiveDate::parse_from_str(&s, "%Y-%M-%D") {
+        Some(s) => match chrono::NaiveDate::parse_from_str(&s, "%Y-%m-%d") {
             Ok(date) => date,
             Err(e) => panic!("Could not parse date: {}", e)
         }
@@ -116,6 +289,90 @@ fn decode_date(pos: usize) -> chrono::NaiveDate {
     }
 }
 
+fn decode_optional_date(pos: usize) -> Option<chrono::NaiveDate> {
+    match args().nth(pos).as_deref() {
+        Some("-") | None => None,
+        Some(_) => Some(decode_date(pos))
+    }
+}
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+fn decode_weekday(pos: usize) -> Option<i32> {
+    match args().nth(pos).as_deref() {
+        Some("-") | None => None,
+        Some(day) => match WEEKDAYS.iter().position(|name| *name == day) {
+            Some(day) => Some(day as i32),
+            None => panic!("Unknown weekday: {}", day)
+        }
+    }
+}
+
+fn format_scope(goal: &Goal) -> String {
+    let weekday = goal.weekday.and_then(|day| WEEKDAYS.get(day as usize)).unwrap_or(&"daily");
+    match (goal.start_date, goal.end_date) {
+        (None, None) => weekday.to_string(),
+        (start, end) => format!("{} {} - {}", weekday,
+            start.map(|date| date.to_string()).unwrap_or_default(),
+            end.map(|date| date.to_string()).unwrap_or_default())
+    }
+}
+
+/**
+ * Print the outcome of an import, listing every record which is not stored as new ingredient.
+ */
+fn print_import(report: Result<ImportReport, NutriplanError>, dry_run: bool) {
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            println!("Failure: {}", e);
+            return
+        }
+    };
+    for row in report.rows.iter() {
+        match &row.status {
+            RowStatus::New => (),
+            RowStatus::Updated(id) => println!(
+                "line {}: {} updates ingredient {}", row.line, row.name, id),
+            RowStatus::Duplicate(Some(id)) => println!(
+                "line {}: {} duplicates ingredient {}", row.line, row.name, id),
+            RowStatus::Duplicate(None) => println!(
+                "line {}: {} duplicates an earlier line", row.line, row.name),
+            RowStatus::Invalid(reason) => println!(
+                "line {}: {}", row.line, reason),
+        }
+    }
+    println!("{} new, {} updated, {} duplicates, {} invalid", report.new_count(),
+        report.updated_count(), report.duplicate_count(), report.invalid_count());
+    if report.skipped > 0 {
+        println!("{} skipped", report.skipped);
+    }
+    if report.committed {
+        println!("Success, imported {} ingredients", report.new_count() + report.updated_count());
+    } else if dry_run {
+        println!("Dry run, nothing imported");
+    } else {
+        println!("Failure: invalid lines, nothing imported");
+    }
+}
+
+/**
+ * Decode the name of a profile at `pos` to its id, no profile is given by `-` or by omitting it.
+ */
+fn decode_profile(db: &NutriplanDb, pos: usize) -> Result<Option<i32>, NutriplanError> {
+    match args().nth(pos).as_deref() {
+        Some("-") | None => Ok(None),
+        Some(name) => match db.profiles().find_by_name(name)? {
+            Some(item) => Ok(item.id),
+            None => Err(NutriplanError::InvalidInput(format!("unknown profile {}", name)))
+        }
+    }
+}
+
+fn format_target(target: Option<f32>) -> String {
+    target.map(|target| target.to_string()).unwrap_or_else(|| String::from("-"))
+}
+
 fn decode_time(pos: usize) -> chrono::NaiveTime {
     match args().nth(pos) {
         Some(s) => match chrono::NaiveTime::parse_from_str(&s, "%H:%M") {
@@ -129,24 +386,55 @@ fn decode_time(pos: usize) -> chrono::NaiveTime {
     }
 }
 
-use nutriplan_db_sqlite::INutriplanDbIngredient;
-use nutriplan_db_sqlite::INutriplanDbIngredientMacro;
-use nutriplan_db_sqlite::INutriplanDbMeal;
-use nutriplan_db_sqlite::INutriplanDbMealIngredient;
-use nutriplan_db_sqlite::INutriplanDbRecipe;
-use nutriplan_db_sqlite::INutriplanDbRecipeIngredient;
-use nutriplan_db_sqlite::controller::database::ingredient::NewIngredient;
-use nutriplan_db_sqlite::controller::database::ingredient::Ingredient;
-use nutriplan_db_sqlite::controller::database::ingredient_macro::NewIngredientMacro;
-use nutriplan_db_sqlite::controller::database::ingredient_macro::IngredientMacro;
-use nutriplan_db_sqlite::controller::database::meal::NewMeal;
-use nutriplan_db_sqlite::controller::database::meal::Meal;
-use nutriplan_db_sqlite::controller::database::meal_ingredient::NewMealIngredient;
-use nutriplan_db_sqlite::controller::database::meal_ingredient::MealIngredient;
-use nutriplan_db_sqlite::controller::database::recipe::NewRecipe;
-use nutriplan_db_sqlite::controller::database::recipe::Recipe;
-use nutriplan_db_sqlite::controller::database::recipe_ingredient::NewRecipeIngredient;
-use nutriplan_db_sqlite::controller::database::recipe_ingredient::RecipeIngredient;
+use nutriplan_db::NutriplanDb;
+use nutriplan_db::INutriplanDbDump;
+use nutriplan_db::INutriplanDbGoal;
+use nutriplan_db::INutriplanDbImport;
+use nutriplan_db::NutriplanError;
+use nutriplan_db::INutriplanDbIngredient;
+use nutriplan_db::INutriplanDbIngredientMacro;
+use nutriplan_db::INutriplanDbIngredientMicro;
+use nutriplan_db::INutriplanDbIngredientPortion;
+use nutriplan_db::INutriplanDbMicronutrient;
+use nutriplan_db::INutriplanDbMeal;
+use nutriplan_db::INutriplanDbMealIngredient;
+use nutriplan_db::INutriplanDbProfile;
+use nutriplan_db::INutriplanDbRecipe;
+use nutriplan_db::INutriplanDbRecipeComponent;
+use nutriplan_db::INutriplanDbRecipeIngredient;
+use nutriplan_db::controller::database::goal::NewGoal;
+use nutriplan_db::controller::database::goal::Goal;
+use nutriplan_db::controller::import::{ImportReport, RowStatus};
+use nutriplan_db::controller::import::csv::CsvFormat;
+use nutriplan_db::controller::import::off::OffFilter;
+use nutriplan_db::controller::database::ingredient::NewIngredient;
+use nutriplan_db::controller::database::ingredient::Ingredient;
+use nutriplan_db::controller::database::ingredient::SearchMode;
+use nutriplan_db::controller::database::ingredient_macro::NewIngredientMacro;
+use nutriplan_db::controller::database::ingredient_macro::IngredientMacro;
+use nutriplan_db::controller::database::ingredient_micro::NewIngredientMicro;
+use nutriplan_db::controller::database::ingredient_micro::IngredientMicro;
+use nutriplan_db::controller::database::ingredient_portion::NewIngredientPortion;
+use nutriplan_db::controller::database::ingredient_portion::IngredientPortion;
+use nutriplan_db::controller::database::micronutrient::NewMicronutrient;
+use nutriplan_db::controller::database::micronutrient::Micronutrient;
+use nutriplan_db::controller::database::profile::NewProfile;
+use nutriplan_db::controller::database::profile::Profile;
+use nutriplan_db::controller::nutrition::{EnergyModel, Nutrients};
+use nutriplan_db::controller::report::Period;
+use nutriplan_db::controller::units::Quantity;
+use nutriplan_db::INutriplanDbReport;
+use nutriplan_db::controller::database::meal::NewMeal;
+use nutriplan_db::controller::database::meal::Meal;
+use nutriplan_db::controller::database::meal::RecipePortion;
+use nutriplan_db::controller::database::meal_ingredient::NewMealIngredient;
+use nutriplan_db::controller::database::meal_ingredient::MealIngredient;
+use nutriplan_db::controller::database::recipe::NewRecipe;
+use nutriplan_db::controller::database::recipe::Recipe;
+use nutriplan_db::controller::database::recipe_component::NewRecipeComponent;
+use nutriplan_db::controller::database::recipe_component::RecipeComponent;
+use nutriplan_db::controller::database::recipe_ingredient::NewRecipeIngredient;
+use nutriplan_db::controller::database::recipe_ingredient::RecipeIngredient;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cmd = match args().nth(1) {
@@ -161,8 +449,254 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         usage()
     }
     match cmd {
+        CmdPattern::Goal => {
+            let nutriplan_db = NutriplanDb::new("nutriplan.db")?;
+            let db = nutriplan_db.goals();
+            match subcmd {
+                SubCmdPattern::Create => {
+                    let name = decode_string(3);
+                    let item = NewGoal {
+                        weekday: decode_weekday(8),
+                        start_date: decode_optional_date(9),
+                        end_date: decode_optional_date(10),
+                        ..NewGoal::new(&name, decode_f32(4), decode_f32(5), decode_f32(6),
+                            decode_f32(7))
+                    };
+                    let item = decode_profile(&nutriplan_db, 11)
+                        .map(|profile_id| NewGoal { profile_id, ..item });
+                    match item.and_then(|item| db.create(item)) {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Read => {
+                    let id = decode_i32(3);
+                    let item = match db.read(id) {
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    println!("Found item with id {}", id);
+                    println!("\tname: {}", item.name);
+                    println!("\tapplies: {}", format_scope(&item));
+                    println!("\tcalories: {} kcal", format_target(item.calories));
+                    println!("\tproteins: {} g", format_target(item.proteins));
+                    println!("\tcarbs: {} g", format_target(item.carbs));
+                    println!("\tfats: {} g", format_target(item.fats));
+                    println!("\talcohols: {} g", format_target(item.alcohols));
+                    println!("\tfiber: {} g", format_target(item.fiber));
+                    println!("\tpolyols: {} g", format_target(item.polyols));
+                    if let Some(profile_id) = item.profile_id {
+                        println!("\tprofile: {}", profile_id);
+                    }
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}\t{} kcal", item.id.unwrap_or_default(),
+                                item.name, format_scope(&item), format_target(item.calories));
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Update => {
+                    let id = decode_i32(3);
+                    let item = match db.read(id) {
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    let item = Goal {
+                        name: decode_string(4),
+                        weekday: decode_weekday(9),
+                        start_date: decode_optional_date(10),
+                        end_date: decode_optional_date(11),
+                        calories: Some(decode_f32(5)),
+                        proteins: Some(decode_f32(6)),
+                        carbs: Some(decode_f32(7)),
+                        fats: Some(decode_f32(8)),
+                        ..item
+                    };
+                    let item = decode_profile(&nutriplan_db, 12)
+                        .map(|profile_id| Goal { profile_id, ..item });
+                    match item.and_then(|item| db.update(item)) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Delete => {
+                    let id = decode_i32(3);
+                    match db.delete(id) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Progress => {
+                    let start = decode_date(3);
+                    let end = decode_optional_date(4).unwrap_or(start);
+                    let days = match decode_profile(&nutriplan_db, 5)
+                        .and_then(|profile| db.progress(&start, &end, profile))
+                    {
+                        Ok(days) => days,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    for day in days {
+                        match day.goal {
+                            Some(goal) => println!("{} ({})", day.date, goal.name),
+                            None => println!("{} (no goal)", day.date)
+                        }
+                        for progress in day.nutrients {
+                            let status = if progress.exceeded > 0.0 {
+                                format!("{:.1} exceeded", progress.exceeded)
+                            } else {
+                                format!("{:.1} remaining", progress.remaining)
+                            };
+                            let percent = progress.percent
+                                .map(|percent| format!("{:.0} %", percent))
+                                .unwrap_or_else(|| String::from("-"));
+                            println!("\t{}: {:.1} / {:.1} ({}), {}", progress.nutrient,
+                                progress.actual, progress.target, percent, status);
+                        }
+                    }
+                },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
+                SubCmdPattern::Unknown => usage(),
+                SubCmdPattern::Help => usage(),
+            }
+        }
+        CmdPattern::Dump => {
+            let db = NutriplanDb::new("nutriplan.db")?.dumps();
+            match subcmd {
+                SubCmdPattern::Create => {
+                    let file = std::fs::File::create(decode_string(3))?;
+                    match db.dump(std::io::BufWriter::new(file)) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Restore => {
+                    let file = std::fs::File::open(decode_string(3))?;
+                    match db.restore(std::io::BufReader::new(file)) {
+                        Ok(counts) => {
+                            for (table, count) in counts.iter() {
+                                println!("{}: {}", table, count);
+                            }
+                            println!("Success");
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Read => usage(),
+                SubCmdPattern::Update => usage(),
+                SubCmdPattern::Delete => usage(),
+                SubCmdPattern::List => usage(),
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Unknown => usage(),
+                SubCmdPattern::Help => usage(),
+            }
+        }
+        CmdPattern::Import => {
+            let db = NutriplanDb::new("nutriplan.db")?.imports();
+            match subcmd {
+                SubCmdPattern::Ingredients => {
+                    let path = decode_string(3);
+                    let mut format = CsvFormat::default();
+                    let mut dry_run = false;
+                    for arg in args().skip(4) {
+                        match arg.split_once('=') {
+                            _ if arg == "--dry-run" => dry_run = true,
+                            Some(("--delimiter", delimiter)) if delimiter.len() == 1 => {
+                                format.delimiter = delimiter.as_bytes()[0]
+                            },
+                            Some((field, column)) => format.columns.set(field, column)?,
+                            None => panic!("Unknown argument: {}", arg)
+                        }
+                    }
+                    let file = std::fs::File::open(&path)?;
+                    print_import(db.csv(file, &format, dry_run), dry_run);
+                },
+                SubCmdPattern::Fdc => {
+                    let path = std::path::PathBuf::from(decode_string(3));
+                    let dry_run = match args().nth(4).as_deref() {
+                        Some("--dry-run") => true,
+                        Some(arg) => panic!("Unknown argument: {}", arg),
+                        None => false
+                    };
+                    // The CSV download is a directory of files, the JSON download a single file
+                    let report = match path.is_dir() {
+                        true => db.fdc_csv(&path, dry_run),
+                        false => db.fdc_json(std::fs::File::open(&path)?, dry_run)
+                    };
+                    print_import(report, dry_run);
+                },
+                SubCmdPattern::Off => {
+                    let path = std::path::PathBuf::from(decode_string(3));
+                    let mut filter = OffFilter::default();
+                    let mut dry_run = false;
+                    for arg in args().skip(4) {
+                        match arg.split_once('=') {
+                            _ if arg == "--dry-run" => dry_run = true,
+                            Some(("--country", country)) => filter.country = Some(country.into()),
+                            Some(("--language", language)) => {
+                                filter.language = Some(language.into())
+                            },
+                            _ => panic!("Unknown argument: {}", arg)
+                        }
+                    }
+                    let file = std::fs::File::open(&path)?;
+                    let report = match path.extension().and_then(|ext| ext.to_str()) {
+                        Some("jsonl") | Some("json") => db.off_jsonl(file, &filter, dry_run),
+                        _ => db.off_csv(file, &filter, dry_run)
+                    };
+                    print_import(report, dry_run);
+                },
+                SubCmdPattern::Restore => usage(),
+                SubCmdPattern::Create => usage(),
+                SubCmdPattern::Read => usage(),
+                SubCmdPattern::Update => usage(),
+                SubCmdPattern::Delete => usage(),
+                SubCmdPattern::List => usage(),
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Unknown => usage(),
+                SubCmdPattern::Help => usage(),
+            }
+        }
         CmdPattern::Ingredient => {
-            let ingredient_db = nutriplan_db_sqlite::NutriplanSqliteDbIngredient::new("nutriplan.db");
+            let nutriplan_db = NutriplanDb::new("nutriplan.db")?;
+            let ingredient_db = nutriplan_db.ingredients();
             match subcmd {
                 SubCmdPattern::Create => {
                     let name = match args().nth(3) {
@@ -172,46 +706,115 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             return Ok(())
                         }
                     };
-                    let ingredient = NewIngredient::new(&name);
-                    match ingredient_db.create(ingredient) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                    let density = decode_optional_f32(4);
+                    let barcode = decode_optional_string(5);
+                    let ingredient = decode_profile(&nutriplan_db, 6).map(|profile_id| {
+                        NewIngredient {
+                            profile_id,
+                            barcode,
+                            ..NewIngredient::with_density(&name, density)
+                        }
+                    });
+                    match ingredient.and_then(|ingredient| ingredient_db.create(ingredient)) {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
                 SubCmdPattern::Read => {
                     let id = decode_i32(3);
                     let ingredient = match ingredient_db.read(id) {
-                        Some(ingredient) => ingredient,
-                        None => {
-                            println!("No ingredient with id {} found", id);
+                        Ok(ingredient) => ingredient,
+                        Err(e) => {
+                            println!("{}", e);
                             return Ok(())
                         }
                     };
                     println!("Found ingredient with id {}, and name {}", id, ingredient.name);
+                    if let Some(density) = ingredient.density {
+                        println!("\tdensity: {} g/ml", density);
+                    }
+                    if let Some(barcode) = ingredient.barcode {
+                        println!("\tbarcode: {}", barcode);
+                    }
+                    if let Some(profile_id) = ingredient.profile_id {
+                        println!("\tprofile: {}", profile_id);
+                    }
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match ingredient_db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}", item.id.unwrap_or_default(), item.name);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Search => {
+                    let query = decode_string(3);
+                    let mode = decode_search_mode(4);
+                    let limit = decode_i64_or(5, 10) as usize;
+                    let items = decode_profile(&nutriplan_db, 6)
+                        .and_then(|profile| ingredient_db.search(&query, mode, limit, profile));
+                    match items {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}", item.id.unwrap_or_default(), item.name);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
                 },
                 SubCmdPattern::Update => {
                     let id = decode_i32(3);
                     let name = decode_string(4);
-                    let ingredient = Ingredient::new(id, &name);
-                    match ingredient_db.update(ingredient) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                    let density = decode_optional_f32(5);
+                    let barcode = decode_optional_string(6);
+                    // The source of an imported ingredient is kept, so a re-import updates it
+                    let ingredient = decode_profile(&nutriplan_db, 7).and_then(|profile_id| {
+                        ingredient_db.read(id).map(|existing| Ingredient {
+                            name,
+                            density,
+                            barcode,
+                            profile_id,
+                            ..existing
+                        })
+                    });
+                    match ingredient.and_then(|ingredient| ingredient_db.update(ingredient)) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
 
                 },
                 SubCmdPattern::Delete => {
                     let id = decode_i32(3);
                     match ingredient_db.delete(id) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => {
+                    let code = decode_string(3);
+                    match ingredient_db.find_by_barcode(&code) {
+                        Ok(Some(item)) => println!("{}\t{}", item.id.unwrap_or_default(),
+                            item.name),
+                        Ok(None) => println!("No ingredient with barcode {}", code),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
                 SubCmdPattern::Unknown => usage(),
                 SubCmdPattern::Help => usage(),
             }
         }
         CmdPattern::IngredientMacro => {
-            let db = nutriplan_db_sqlite::NutriplanSqliteDbIngredientMacro::new("nutriplan.db");
+            let db = NutriplanDb::new("nutriplan.db")?.ingredient_macros();
             match subcmd {
                 SubCmdPattern::Create => {
                     let iid = decode_i32(3);
@@ -219,18 +822,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     let carbs = decode_f32(5);
                     let fats = decode_f32(6);
                     let alcohols = decode_f32(7);
-                    let item = NewIngredientMacro::new(iid, proteins, carbs, fats, alcohols);
+                    let fiber = decode_f32_or(8, 0.0);
+                    let polyols = decode_f32_or(9, 0.0);
+                    let item = NewIngredientMacro::with_model(iid, proteins, carbs, fats, alcohols,
+                        fiber, polyols, &EnergyModel::default());
                     match db.create(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
                 SubCmdPattern::Read => {
                     let id = decode_i32(3);
                     let item = match db.read(id) {
-                        Some(item) => item,
-                        None => {
-                            println!("No ingredient with id {} found", id);
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
                             return Ok(())
                         }
                     };
@@ -239,6 +845,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("\tcarbs: {}", item.carbs);
                     println!("\tfats: {}", item.fats);
                     println!("\talcohols: {}", item.alcohols);
+                    println!("\tfiber: {}", item.fiber);
+                    println!("\tpolyols: {}", item.polyols);
+                    println!("\tcalories: {}", item.calories);
+                },
+                SubCmdPattern::Recompute => {
+                    let model = decode_energy_model(3);
+                    match db.recompute_calories(&model) {
+                        Ok(rows) => println!("Success, recomputed {} items", rows),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                                item.id.unwrap_or_default(), item.ingredient_id, item.proteins,
+                                item.carbs, item.fats, item.alcohols, item.fiber, item.polyols,
+                                item.calories);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
                 },
                 SubCmdPattern::Update => {
                     let id = decode_i32(3);
@@ -247,43 +876,291 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     let carbs = decode_f32(6);
                     let fats = decode_f32(7);
                     let alcohols = decode_f32(8);
-                    let item = IngredientMacro::new(id, iid, proteins, carbs, fats, alcohols);
-                    match db.update(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                    let item = db.read(id).map(|existing| {
+                        let mut item = IngredientMacro {
+                            ingredient_id: iid,
+                            proteins,
+                            carbs,
+                            fats,
+                            alcohols,
+                            fiber: decode_f32_or(9, existing.fiber),
+                            polyols: decode_f32_or(10, existing.polyols),
+                            ..existing
+                        };
+                        item.apply_model(&EnergyModel::default());
+                        item
+                    });
+                    match item.and_then(|item| db.update(item)) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
 
                 },
                 SubCmdPattern::Delete => {
                     let id = decode_i32(3);
                     match db.delete(id) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
+                SubCmdPattern::Unknown => usage(),
+                SubCmdPattern::Help => usage(),
+            }
+        }
+        CmdPattern::IngredientMicro => {
+            let nutriplan_db = NutriplanDb::new("nutriplan.db")?;
+            let db = nutriplan_db.ingredient_micros();
+            let decode_micronutrient = |pos: usize| -> Result<i32, NutriplanError> {
+                let code = decode_string(pos);
+                match nutriplan_db.micronutrients().find_by_code(&code)? {
+                    Some(item) => Ok(item.id.unwrap_or_default()),
+                    None => Err(NutriplanError::InvalidInput(
+                        format!("unknown micronutrient {}", code)))
+                }
+            };
+            match subcmd {
+                SubCmdPattern::Create => {
+                    let iid = decode_i32(3);
+                    let amount = decode_f32(5);
+                    let item = decode_micronutrient(4)
+                        .map(|mid| NewIngredientMicro::new(iid, mid, amount));
+                    match item.and_then(|item| db.create(item)) {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Read => {
+                    let id = decode_i32(3);
+                    let item = match db.read(id) {
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    println!("Found item with id {}", id);
+                    println!("\tingredient: {}", item.ingredient_id);
+                    println!("\tmicronutrient: {}", item.micronutrient_id);
+                    println!("\tamount: {}", item.amount);
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}\t{}", item.id.unwrap_or_default(),
+                                item.ingredient_id, item.micronutrient_id, item.amount);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Update => {
+                    let id = decode_i32(3);
+                    let iid = decode_i32(4);
+                    let amount = decode_f32(6);
+                    let item = decode_micronutrient(5)
+                        .map(|mid| IngredientMicro::new(id, iid, mid, amount));
+                    match item.and_then(|item| db.update(item)) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Delete => {
+                    let id = decode_i32(3);
+                    match db.delete(id) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
+                SubCmdPattern::Unknown => usage(),
+                SubCmdPattern::Help => usage(),
+            }
+        }
+        CmdPattern::IngredientPortion => {
+            let db = NutriplanDb::new("nutriplan.db")?.ingredient_portions();
+            match subcmd {
+                SubCmdPattern::Create => {
+                    let iid = decode_i32(3);
+                    let name = decode_string(4);
+                    let mass = decode_f32(5);
+                    let item = NewIngredientPortion::new(iid, &name, mass);
+                    match db.create(item) {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Read => {
+                    let id = decode_i32(3);
+                    let item = match db.read(id) {
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    println!("Found item with id {}", id);
+                    println!("\tingredient: {}", item.ingredient_id);
+                    println!("\tname: {}", item.name);
+                    println!("\tmass: {} g", item.mass);
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}\t{}", item.id.unwrap_or_default(),
+                                item.ingredient_id, item.name, item.mass);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Update => {
+                    let id = decode_i32(3);
+                    let iid = decode_i32(4);
+                    let name = decode_string(5);
+                    let mass = decode_f32(6);
+                    let item = IngredientPortion::new(id, iid, &name, mass);
+                    match db.update(item) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Delete => {
+                    let id = decode_i32(3);
+                    match db.delete(id) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
+                SubCmdPattern::Unknown => usage(),
+                SubCmdPattern::Help => usage(),
+            }
+        }
+        CmdPattern::Micronutrient => {
+            let db = NutriplanDb::new("nutriplan.db")?.micronutrients();
+            match subcmd {
+                SubCmdPattern::Create => {
+                    let code = decode_string(3);
+                    let name = decode_string(4);
+                    let unit = decode_string(5);
+                    let item = NewMicronutrient::new(&code, &name, &unit);
+                    match db.create(item) {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Read => {
+                    let id = decode_i32(3);
+                    let item = match db.read(id) {
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    println!("Found item with id {}", id);
+                    println!("\tcode: {}", item.code);
+                    println!("\tname: {}", item.name);
+                    println!("\tunit: {}", item.unit);
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}\t{}", item.id.unwrap_or_default(), item.code,
+                                item.name, item.unit);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Update => {
+                    let id = decode_i32(3);
+                    let code = decode_string(4);
+                    let name = decode_string(5);
+                    let unit = decode_string(6);
+                    let item = Micronutrient::new(id, &code, &name, &unit);
+                    match db.update(item) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Delete => {
+                    let id = decode_i32(3);
+                    match db.delete(id) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
                 SubCmdPattern::Unknown => usage(),
                 SubCmdPattern::Help => usage(),
             }
         }
         CmdPattern::Meal => {
-            let db = nutriplan_db_sqlite::NutriplanSqliteDbMeal::new("nutriplan.db");
+            let nutriplan_db = NutriplanDb::new("nutriplan.db")?;
+            let db = nutriplan_db.meals();
             match subcmd {
                 SubCmdPattern::Create => {
                     let name = decode_string(3);
                     let date = decode_date(4);
                     let time = decode_time(5);
-                    let item = NewMeal::new(&name, &date, &time);
-                    match db.create(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                    let item = decode_profile(&nutriplan_db, 6).map(|profile_id| {
+                        NewMeal { profile_id, ..NewMeal::new(&name, &date, &time) }
+                    });
+                    match item.and_then(|item| db.create(item)) {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
                 SubCmdPattern::Read => {
                     let id = decode_i32(3);
                     let item = match db.read(id) {
-                        Some(item) => item,
-                        None => {
-                            println!("No ingredient with id {} found", id);
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
                             return Ok(())
                         }
                     };
@@ -291,173 +1168,492 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("\tname: {}", item.name);
                     println!("\tdate: {}", item.date);
                     println!("\ttime: {}", item.time);
+                    if let Some(profile_id) = item.profile_id {
+                        println!("\tprofile: {}", profile_id);
+                    }
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}\t{}", item.id.unwrap_or_default(), item.name,
+                                item.date, item.time);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Nutrients => {
+                    let id = decode_i32(3);
+                    let meal = match db.nutrients(id) {
+                        Ok(meal) => meal,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    println!("Nutrients of meal with id {}", id);
+                    print_nutrients("\t", &meal.total);
+                    for item in meal.ingredients {
+                        println!("\t{} ({} g)", item.name, item.mass);
+                        print_nutrients("\t\t", &item.nutrients);
+                    }
+                },
+                SubCmdPattern::Report => {
+                    let start = decode_date(3);
+                    let end = decode_date(4);
+                    let period = decode_period(5);
+                    let summaries = match decode_profile(&nutriplan_db, 6).and_then(|profile| {
+                        nutriplan_db.reports().summarize(&start, &end, period, profile)
+                    }) {
+                        Ok(summaries) => summaries,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    for summary in summaries {
+                        println!("{} - {} ({} days logged)", summary.start, summary.end,
+                            summary.days_logged);
+                        println!("\ttotal:");
+                        print_nutrients("\t\t", &summary.total);
+                        println!("\taverage per logged day:");
+                        print_nutrients("\t\t", &summary.average);
+                        for day in summary.days.iter().filter(|day| !day.meals.is_empty()) {
+                            for meal in day.meals.iter() {
+                                println!("\t{} {} {}: {:.0} kcal", day.date, meal.meal.time,
+                                    meal.meal.name, meal.nutrients.total.calories);
+                            }
+                        }
+                    }
+                },
+                SubCmdPattern::AddRecipe => {
+                    let mid = decode_i32(3);
+                    let rid = decode_i32(4);
+                    let portion = decode_portion(5);
+                    match db.add_recipe(mid, rid, portion) {
+                        Ok(ids) => println!("Success, created {} meal ingredients", ids.len()),
+                        Err(e) => println!("Failure: {}", e)
+                    }
                 },
                 SubCmdPattern::Update => {
                     let id = decode_i32(3);
                     let name = decode_string(4);
                     let date = decode_date(5);
                     let time = decode_time(6);
-                    let item = Meal::new(id, &name, &date, &time);
-                    match db.update(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                    let item = decode_profile(&nutriplan_db, 7).map(|profile_id| {
+                        Meal { profile_id, ..Meal::new(id, &name, &date, &time) }
+                    });
+                    match item.and_then(|item| db.update(item)) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
 
                 },
                 SubCmdPattern::Delete => {
                     let id = decode_i32(3);
                     match db.delete(id) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
                 SubCmdPattern::Unknown => usage(),
                 SubCmdPattern::Help => usage(),
             }
         }
         CmdPattern::MealIngredient => {
-            let db = nutriplan_db_sqlite::NutriplanSqliteDbMealIngredient::new("nutriplan.db");
+            let db = NutriplanDb::new("nutriplan.db")?.meal_ingredients();
             match subcmd {
                 SubCmdPattern::Create => {
                     let mid = decode_i32(3);
                     let iid = decode_i32(4);
-                    let mass = decode_i32(5);
-                    let item = NewMealIngredient::new(mid, iid, mass);
-                    match db.create(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                    let created = match decode_quantity(5) {
+                        Ok(quantity) => db.create_from_quantity(mid, iid, &quantity),
+                        Err(mass) => db.create(NewMealIngredient::new(mid, iid, mass))
+                    };
+                    match created {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
                 SubCmdPattern::Read => {
                     let id = decode_i32(3);
                     let item = match db.read(id) {
-                        Some(item) => item,
-                        None => {
-                            println!("No ingredient with id {} found", id);
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
                             return Ok(())
                         }
                     };
                     println!("Found item with id {}", id);
-                    println!("\tdate: {}", item.meal_id);
-                    println!("\tname: {}", item.ingredient_id);
-                    println!("\ttime: {}", item.mass);
+                    println!("\tmeal: {}", item.meal_id);
+                    println!("\tingredient: {}", item.ingredient_id);
+                    println!("\tmass: {}", format_mass(item.mass, item.quantity()));
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}\t{}", item.id.unwrap_or_default(), item.meal_id,
+                                item.ingredient_id, format_mass(item.mass, item.quantity()));
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
                 },
                 SubCmdPattern::Update => {
                     let id = decode_i32(3);
                     let mid = decode_i32(4);
                     let iid = decode_i32(5);
-                    let mass = decode_i32(6);
+                    let mass = decode_f32(6);
                     let item = MealIngredient::new(id, mid, iid, mass);
                     match db.update(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
 
                 },
                 SubCmdPattern::Delete => {
                     let id = decode_i32(3);
                     match db.delete(id) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
+                SubCmdPattern::Unknown => usage(),
+                SubCmdPattern::Help => usage(),
+            }
+        }
+        CmdPattern::Profile => {
+            let db = NutriplanDb::new("nutriplan.db")?.profiles();
+            match subcmd {
+                SubCmdPattern::Create => {
+                    let name = decode_string(3);
+                    match db.create(NewProfile::new(&name)) {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Read => {
+                    let id = decode_i32(3);
+                    let item = match db.read(id) {
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    println!("Found item with id {}", id);
+                    println!("\tname: {}", item.name);
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}", item.id.unwrap_or_default(), item.name);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Update => {
+                    let id = decode_i32(3);
+                    let name = decode_string(4);
+                    match db.update(Profile::new(id, &name)) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Delete => {
+                    let id = decode_i32(3);
+                    match db.delete(id) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
                 SubCmdPattern::Unknown => usage(),
                 SubCmdPattern::Help => usage(),
             }
         }
         CmdPattern::Recipe => {
-            let db = nutriplan_db_sqlite::NutriplanSqliteDbRecipe::new("nutriplan.db");
+            let nutriplan_db = NutriplanDb::new("nutriplan.db")?;
+            let db = nutriplan_db.recipes();
             match subcmd {
                 SubCmdPattern::Create => {
                     let name = decode_string(3);
                     let description = decode_string(4);
-                    let item = NewRecipe::new(&name, &description);
-                    match db.create(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                    let servings = decode_i32_or(5, 1);
+                    let yield_mass = decode_optional_f32(6);
+                    let item = decode_profile(&nutriplan_db, 7).map(|profile_id| NewRecipe {
+                        profile_id,
+                        ..NewRecipe::with_servings(&name, &description, servings, yield_mass)
+                    });
+                    match item.and_then(|item| db.create(item)) {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
                 SubCmdPattern::Read => {
                     let id = decode_i32(3);
                     let item = match db.read(id) {
-                        Some(item) => item,
-                        None => {
-                            println!("No ingredient with id {} found", id);
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
                             return Ok(())
                         }
                     };
                     println!("Found item with id {}", id);
                     println!("\tname: {}", item.name);
                     println!("\tdescription: {}", item.description);
+                    println!("\tservings: {}", item.servings);
+                    if let Some(yield_mass) = item.yield_mass {
+                        println!("\tyield mass: {} g", yield_mass);
+                    }
+                    if let Some(profile_id) = item.profile_id {
+                        println!("\tprofile: {}", profile_id);
+                    }
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}", item.id.unwrap_or_default(), item.name,
+                                item.description);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
                 },
                 SubCmdPattern::Update => {
                     let id = decode_i32(3);
                     let name = decode_string(4);
                     let description = decode_string(5);
-                    let item = Recipe::new(id, &name, &description);
-                    match db.update(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                    let servings = decode_i32_or(6, 1);
+                    let yield_mass = decode_optional_f32(7);
+                    let item = decode_profile(&nutriplan_db, 8).map(|profile_id| Recipe {
+                        profile_id,
+                        ..Recipe::with_servings(id, &name, &description, servings, yield_mass)
+                    });
+                    match item.and_then(|item| db.update(item)) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
 
                 },
                 SubCmdPattern::Delete => {
                     let id = decode_i32(3);
                     match db.delete(id) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => {
+                    let id = decode_i32(3);
+                    let recipe = match db.nutrients(id) {
+                        Ok(recipe) => recipe,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    println!("Nutrients of recipe with id {} ({} g, {} servings)", id,
+                        recipe.mass, recipe.servings);
+                    println!("\ttotal:");
+                    print_nutrients("\t\t", &recipe.total);
+                    println!("\tper serving:");
+                    print_nutrients("\t\t", &recipe.per_serving);
+                    println!("\tper 100 g:");
+                    print_nutrients("\t\t", &recipe.per_100g);
+                    for item in recipe.components {
+                        println!("\t{} ({} g)", item.name, item.mass);
+                        print_nutrients("\t\t", &item.nutrients);
+                    }
+                },
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
+                SubCmdPattern::Unknown => usage(),
+                SubCmdPattern::Help => usage(),
+            }
+        }
+        CmdPattern::RecipeComponent => {
+            let db = NutriplanDb::new("nutriplan.db")?.recipe_components();
+            match subcmd {
+                SubCmdPattern::Create => {
+                    let rid = decode_i32(3);
+                    let cid = decode_i32(4);
+                    let mass = decode_f32(5);
+                    let item = NewRecipeComponent::new(rid, cid, mass);
+                    match db.create(item) {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Read => {
+                    let id = decode_i32(3);
+                    let item = match db.read(id) {
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
+                            return Ok(())
+                        }
+                    };
+                    println!("Found item with id {}", id);
+                    println!("\trecipe: {}", item.recipe_id);
+                    println!("\tcomponent: {}", item.component_id);
+                    println!("\tmass: {}", item.mass);
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}\t{}", item.id.unwrap_or_default(), item.recipe_id,
+                                item.component_id, item.mass);
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Update => {
+                    let id = decode_i32(3);
+                    let rid = decode_i32(4);
+                    let cid = decode_i32(5);
+                    let mass = decode_f32(6);
+                    let item = RecipeComponent::new(id, rid, cid, mass);
+                    match db.update(item) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Delete => {
+                    let id = decode_i32(3);
+                    match db.delete(id) {
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
+                    }
+                },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
                 SubCmdPattern::Unknown => usage(),
                 SubCmdPattern::Help => usage(),
             }
         }
         CmdPattern::RecipeIngredient => { 
-            let db = nutriplan_db_sqlite::NutriplanSqliteDbRecipeIngredient::new("nutriplan.db");
+            let db = NutriplanDb::new("nutriplan.db")?.recipe_ingredients();
             match subcmd {
                 SubCmdPattern::Create => {
                     let mid = decode_i32(3);
                     let iid = decode_i32(4);
-                    let mass = decode_i32(5);
-                    let item = NewRecipeIngredient::new(mid, iid, mass);
-                    match db.create(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                    let created = match decode_quantity(5) {
+                        Ok(quantity) => db.create_from_quantity(mid, iid, &quantity),
+                        Err(mass) => db.create(NewRecipeIngredient::new(mid, iid, mass))
+                    };
+                    match created {
+                        Ok(id) => println!("Success, created item with id {}", id),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
                 SubCmdPattern::Read => {
                     let id = decode_i32(3);
                     let item = match db.read(id) {
-                        Some(item) => item,
-                        None => {
-                            println!("No ingredient with id {} found", id);
+                        Ok(item) => item,
+                        Err(e) => {
+                            println!("{}", e);
                             return Ok(())
                         }
                     };
                     println!("Found item with id {}", id);
-                    println!("\tdate: {}", item.recipe_id);
-                    println!("\tname: {}", item.ingredient_id);
-                    println!("\ttime: {}", item.mass);
+                    println!("\trecipe: {}", item.recipe_id);
+                    println!("\tingredient: {}", item.ingredient_id);
+                    println!("\tmass: {}", format_mass(item.mass, item.quantity()));
+                },
+                SubCmdPattern::List => {
+                    let limit = decode_i64_or(3, 50);
+                    let offset = decode_i64_or(4, 0);
+                    match db.list(limit, offset) {
+                        Ok(items) => for item in items {
+                            println!("{}\t{}\t{}\t{}", item.id.unwrap_or_default(), item.recipe_id,
+                                item.ingredient_id, format_mass(item.mass, item.quantity()));
+                        },
+                        Err(e) => println!("Failure: {}", e)
+                    }
                 },
                 SubCmdPattern::Update => {
                     let id = decode_i32(3);
                     let rid = decode_i32(4);
                     let iid = decode_i32(5);
-                    let mass = decode_i32(6);
+                    let mass = decode_f32(6);
                     let item = RecipeIngredient::new(id, rid, iid, mass);
                     match db.update(item) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
 
                 },
                 SubCmdPattern::Delete => {
                     let id = decode_i32(3);
                     match db.delete(id) {
-                        true => println!("Success"),
-                        false => println!("Failure")
+                        Ok(_) => println!("Success"),
+                        Err(e) => println!("Failure: {}", e)
                     }
                 },
+                SubCmdPattern::Search => usage(),
+                SubCmdPattern::Recompute => usage(),
+                SubCmdPattern::Nutrients => usage(),
+                SubCmdPattern::Report => usage(),
+                SubCmdPattern::AddRecipe => usage(),
+                SubCmdPattern::Progress => usage(),
+                SubCmdPattern::Barcode => usage(),
+                SubCmdPattern::Ingredients => usage(),
+                SubCmdPattern::Fdc => usage(),
+                SubCmdPattern::Off => usage(),
+                SubCmdPattern::Restore => usage(),
                 SubCmdPattern::Unknown => usage(),
                 SubCmdPattern::Help => usage(),
             }
@@ -1,2 +1,8 @@
 pub mod database;
+pub mod dump;
+pub mod import;
+pub mod nutrition;
+pub mod progress;
+pub mod report;
+pub mod units;
 pub mod util;
@@ -8,14 +8,76 @@ use dotenv::dotenv;
 use log::error;
 use std::env;
 
+use crate::error::NutriplanError;
+
+pub mod goal;
 pub mod ingredient;
 pub mod ingredient_macro;
+pub mod ingredient_micro;
+pub mod ingredient_portion;
 pub mod meal;
 pub mod meal_ingredient;
+pub mod micronutrient;
+pub mod profile;
 pub mod recipe;
+pub mod recipe_component;
 pub mod recipe_ingredient;
 
 pub type ConnMgrPool = PooledConnection<ConnectionManager<SqliteConnection>>;
+pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
+
+no_arg_sql_function!(last_insert_rowid, diesel::sql_types::Integer,
+    "Represents the sqlite last_insert_rowid() function");
+
+/**
+ * Maximum number of ids bound to a single `IN` clause. Older SQLite builds limit a statement to 999
+ * bind variables, so longer lists are queried in chunks.
+ */
+pub(crate) const MAX_BOUND_IDS: usize = 500;
+
+/**
+ * Read the id of the row inserted last on the given connection.
+ */
+pub(crate) fn last_insert_id(conn_mgr: &ConnMgrPool) -> Result<i32, NutriplanError> {
+    use diesel::RunQueryDsl;
+    Ok(diesel::select(last_insert_rowid).get_result::<i32>(conn_mgr)?)
+}
+
+/**
+ * Validate the parameters of a paginated request.
+ */
+pub(crate) fn check_page(limit: i64, offset: i64) -> Result<(), NutriplanError> {
+    if limit < 0 || offset < 0 {
+        return Err(NutriplanError::InvalidInput(
+            format!("limit ({}) and offset ({}) must not be negative", limit, offset)))
+    }
+    Ok(())
+}
+
+/**
+ * Validate a mass in gram, which may be fractional but not negative.
+ */
+pub(crate) fn check_mass(mass: f32) -> Result<(), NutriplanError> {
+    if !(mass >= 0.0 && mass.is_finite()) {
+        return Err(NutriplanError::InvalidInput(
+            format!("mass must not be negative, got {}", mass)))
+    }
+    Ok(())
+}
+
+/**
+ * Read an item referenced by another one, failing with a `ForeignKeyViolation` if it is missing.
+ */
+pub(crate) fn read_reference<C: CRUDController>(conn_mgr: &ConnMgrPool, item_id: i32)
+    -> Result<C::Item, NutriplanError>
+{
+    match C::read(conn_mgr, item_id) {
+        Err(NutriplanError::NotFound { .. }) => {
+            Err(NutriplanError::missing_reference(C::ENTITY, item_id))
+        },
+        result => result
+    }
+}
 
 /**
  * Controller entity to satisfy basic database requirements. The requirements are **C**reate,
@@ -31,36 +93,59 @@ pub trait CRUDController {
     type NewItem;
     type Item;
 
+    /** Name of the entity, used in error messages */
+    const ENTITY: &'static str;
+
     /**
-     * Create a single item
+     * Create a single item and return the id it got assigned
      */
-    fn create(conn_mgr: &ConnMgrPool, new_item: &Self::NewItem) -> bool;
+    fn create(conn_mgr: &ConnMgrPool, new_item: &Self::NewItem) -> Result<i32, NutriplanError>;
 
     /**
      * Read item from database by its unique id
      */
-    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Option<Self::Item>;
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<Self::Item, NutriplanError>;
+
+    /**
+     * List items ordered by their id, skipping the first `offset` items and returning at most
+     * `limit` items.
+     */
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<Self::Item>, NutriplanError>;
 
     /**
      * Change a item characteristics.
      */
-    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Self::Item) -> bool;
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Self::Item)
+        -> Result<(), NutriplanError>;
 
     /**
      * Delete an item from database
      */
-    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> bool;
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError>;
 
     /**
      * Check if a item with the specific id exists
      */
-    fn check(conn_mgr: &ConnMgrPool, item_id: i32) -> bool {
+    fn check(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<bool, NutriplanError> {
         match Self::read(conn_mgr, item_id) {
-            Some(_) => true,
-            None => false
+            Ok(_) => Ok(true),
+            Err(NutriplanError::NotFound { .. }) => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+
+    /**
+     * Fail with `NutriplanError::NotFound`, if no row was affected by a statement on the item with
+     * the given id.
+     */
+    fn affected(rows: usize, item_id: i32) -> Result<(), NutriplanError> {
+        match rows {
+            0 => Err(NutriplanError::not_found(Self::ENTITY, item_id)),
+            _ => Ok(())
         }
     }
-} 
+}
 
 /**
  * Object containing settings for database access.
@@ -82,15 +167,17 @@ impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error> fo
         -> Result<(), diesel::r2d2::Error>
     {
         (|| {
+            // busy_timeout has to be set first, otherwise concurrent connections of the pool fail
+            // with a locked database while switching to WAL
+            if let Some(d) = self.busy_timeout {
+                conn.batch_execute(&format!("PRAGMA busy_timeout = {}", d.as_millis()))?;
+            }
             if self.enable_wal {
                 conn.batch_execute("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
             }
             if self.enable_foreign_keys {
                 conn.batch_execute("PRAGMA foreign_keys = ON;")?;
             }
-            if let Some(d) = self.busy_timeout {
-                conn.batch_execute(&format!("PRAGMA busy_timeout = {}", d.as_millis()))?;
-            }
             Ok(())
         })()
         .map_err(diesel::r2d2::Error::QueryError)
@@ -128,24 +215,16 @@ pub fn local_conn_string() -> Option<String> {
  *
  * # Returns
  * * Pool containing the connection to given sqlite database on success
- * * None on Error
+ * * `NutriplanError::Connection` on Error
  */
-pub fn connect_database(database_url: &str)
-    -> Option<Pool<ConnectionManager<SqliteConnection>>> 
-{
-    match Pool::builder()
+pub fn connect_database(database_url: &str) -> Result<DbPool, NutriplanError> {
+    Ok(Pool::builder()
         .max_size(16)
         .connection_customizer(Box::new(ConnectionOptions {
             enable_wal: true,
             enable_foreign_keys: true,
             busy_timeout: Some(Duration::from_secs(30))
         }))
-        .build(ConnectionManager::<SqliteConnection>::new(database_url)) {
-            Ok(db_pool) => Some(db_pool),
-            Err(e) => {
-                error!("Could not connect to database: {}", e);
-                None
-            }
-        }
+        .build(ConnectionManager::<SqliteConnection>::new(database_url))?)
 }
 
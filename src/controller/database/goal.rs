@@ -0,0 +1,388 @@
+use chrono::{Datelike, NaiveDate};
+use diesel::{prelude::*, Queryable, Insertable};
+
+use crate::{
+    controller::database::{ConnMgrPool, CRUDController, check_page, last_insert_id},
+    controller::database::profile::check_profile,
+    error::NutriplanError,
+    schema::*
+};
+
+/**
+ * Daily nutrition targets, calories in kcal and macros in gram. Targets which are `None` are not
+ * tracked.
+ *
+ * A goal applies to every day unless it is restricted to a weekday (0 = monday) or to a date
+ * range, both bounds inclusive. Goals without a profile apply to every profile. If several goals
+ * apply to a day, the most specific one is used, see `CRUDGoal::for_date`.
+ */
+#[derive(Insertable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="goals"]
+pub struct NewGoal {
+    pub name: String,
+    pub weekday: Option<i32>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub calories: Option<f32>,
+    pub proteins: Option<f32>,
+    pub carbs: Option<f32>,
+    pub fats: Option<f32>,
+    pub alcohols: Option<f32>,
+    pub fiber: Option<f32>,
+    pub polyols: Option<f32>,
+    pub profile_id: Option<i32>
+}
+
+impl NewGoal {
+    /**
+     * Create a goal for every day tracking calories and the main macros.
+     */
+    pub fn new(name: &str, calories: f32, proteins: f32, carbs: f32, fats: f32) -> Self {
+        NewGoal {
+            name: name.to_owned(),
+            weekday: None,
+            start_date: None,
+            end_date: None,
+            calories: Some(calories),
+            proteins: Some(proteins),
+            carbs: Some(carbs),
+            fats: Some(fats),
+            alcohols: None,
+            fiber: None,
+            polyols: None,
+            profile_id: None
+        }
+    }
+}
+
+#[derive(Queryable, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Goal {
+    pub id: Option<i32>,
+    pub name: String,
+    pub weekday: Option<i32>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub calories: Option<f32>,
+    pub proteins: Option<f32>,
+    pub carbs: Option<f32>,
+    pub fats: Option<f32>,
+    pub alcohols: Option<f32>,
+    pub fiber: Option<f32>,
+    pub polyols: Option<f32>,
+    pub profile_id: Option<i32>
+}
+
+impl Goal {
+    pub fn new(id: i32, name: &str, calories: f32, proteins: f32, carbs: f32, fats: f32) -> Self {
+        Goal {
+            id: Some(id),
+            name: name.to_owned(),
+            weekday: None,
+            start_date: None,
+            end_date: None,
+            calories: Some(calories),
+            proteins: Some(proteins),
+            carbs: Some(carbs),
+            fats: Some(fats),
+            alcohols: None,
+            fiber: None,
+            polyols: None,
+            profile_id: None
+        }
+    }
+
+    /**
+     * Check whether the goal applies to the given day.
+     */
+    pub fn applies_to(&self, date: &NaiveDate) -> bool {
+        self.weekday.is_none_or(|day| day == weekday(date))
+            && self.start_date.is_none_or(|start| start <= *date)
+            && self.end_date.is_none_or(|end| end >= *date)
+    }
+
+    /**
+     * Goals of a profile take precedence over goals for every profile. Then goals restricted to a
+     * date range take precedence over goals restricted to a weekday, which take precedence over
+     * goals for every day. Among equally specific goals the newest one wins.
+     */
+    fn precedence(&self) -> (bool, bool, bool, Option<i32>) {
+        (self.profile_id.is_some(), self.start_date.is_some() || self.end_date.is_some(),
+            self.weekday.is_some(), self.id)
+    }
+}
+
+fn weekday(date: &NaiveDate) -> i32 {
+    date.weekday().num_days_from_monday() as i32
+}
+
+/**
+ * Select the goal applying to a day from a list of goals.
+ */
+pub(crate) fn most_specific<'a>(goals: &'a [Goal], date: &NaiveDate) -> Option<&'a Goal> {
+    goals.iter()
+        .filter(|goal| goal.applies_to(date))
+        .max_by_key(|goal| goal.precedence())
+}
+
+fn check_goal(weekday: Option<i32>, start: Option<NaiveDate>, end: Option<NaiveDate>,
+    targets: &[Option<f32>]) -> Result<(), NutriplanError>
+{
+    if let Some(day) = weekday.filter(|day| !(0..=6).contains(day)) {
+        return Err(NutriplanError::InvalidInput(
+            format!("weekday must be between 0 (monday) and 6 (sunday), got {}", day)))
+    }
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            return Err(NutriplanError::InvalidInput(
+                format!("start {} is after end {}", start, end)))
+        }
+    }
+    if let Some(target) = targets.iter().flatten().find(|t| !(**t >= 0.0 && t.is_finite())) {
+        return Err(NutriplanError::InvalidInput(
+            format!("targets must not be negative, got {}", target)))
+    }
+    Ok(())
+}
+
+pub struct CRUDGoal { }
+
+impl CRUDGoal {
+    /**
+     * Find the goal applying to the given day of a profile, if any. Without a profile only goals
+     * for every profile are considered.
+     */
+    pub fn for_date(conn_mgr: &ConnMgrPool, date: &NaiveDate, profile: Option<i32>)
+        -> Result<Option<Goal>, NutriplanError>
+    {
+        let goals = Self::list_between(conn_mgr, date, date, profile)?;
+        Ok(most_specific(&goals, date).cloned())
+    }
+
+    /**
+     * List all goals which may apply to a day between `start` and `end`, both inclusive, i.e. the
+     * goals of the given profile and those for every profile.
+     */
+    pub fn list_between(conn_mgr: &ConnMgrPool, start: &NaiveDate, end: &NaiveDate,
+        profile: Option<i32>) -> Result<Vec<Goal>, NutriplanError>
+    {
+        use crate::schema::goals::dsl::*;
+
+        let mut query = goals
+            .filter(start_date.is_null().or(start_date.le(end)))
+            .filter(end_date.is_null().or(end_date.ge(start)))
+            .order(id.asc())
+            .into_boxed();
+        query = match profile {
+            Some(pid) => query.filter(profile_id.is_null().or(profile_id.eq(pid))),
+            None => query.filter(profile_id.is_null())
+        };
+        Ok(query.load::<Goal>(conn_mgr)?)
+    }
+
+    pub fn delete_by_profile_id(conn_mgr: &ConnMgrPool, pid: i32) -> Result<(), NutriplanError> {
+        use crate::schema::goals::dsl::*;
+
+        diesel::delete(
+            goals.filter(profile_id.eq(pid)))
+            .execute(conn_mgr)?;
+        Ok(())
+    }
+}
+
+impl CRUDController for CRUDGoal {
+    type NewItem = NewGoal;
+    type Item = Goal;
+
+    const ENTITY: &'static str = "goal";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewGoal) -> Result<i32, NutriplanError> {
+        check_goal(new_item.weekday, new_item.start_date, new_item.end_date, &[
+            new_item.calories, new_item.proteins, new_item.carbs, new_item.fats,
+            new_item.alcohols, new_item.fiber, new_item.polyols])?;
+        check_profile(conn_mgr, new_item.profile_id)?;
+
+        diesel::insert_into(goals::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
+    }
+
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<Goal, NutriplanError> {
+        use crate::schema::goals::dsl::*;
+
+        goals
+            .filter(id.eq(item_id))
+            .first::<Goal>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
+    }
+
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64) -> Result<Vec<Goal>, NutriplanError> {
+        use crate::schema::goals::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(goals
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Goal>(conn_mgr)?)
+    }
+
+    /**
+     * Replace all values of a goal, targets and restrictions set to `None` are cleared.
+     */
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Goal) -> Result<(), NutriplanError> {
+        use crate::schema::goals::dsl::*;
+
+        check_goal(item.weekday, item.start_date, item.end_date, &[
+            item.calories, item.proteins, item.carbs, item.fats, item.alcohols, item.fiber,
+            item.polyols])?;
+        check_profile(conn_mgr, item.profile_id)?;
+        let rows = diesel::update(
+            goals.filter(id.eq(item_id)))
+            .set((
+                name.eq(item.name),
+                weekday.eq(item.weekday),
+                start_date.eq(item.start_date),
+                end_date.eq(item.end_date),
+                calories.eq(item.calories),
+                proteins.eq(item.proteins),
+                carbs.eq(item.carbs),
+                fats.eq(item.fats),
+                alcohols.eq(item.alcohols),
+                fiber.eq(item.fiber),
+                polyols.eq(item.polyols),
+                profile_id.eq(item.profile_id),
+            ))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
+        use crate::schema::goals::dsl::*;
+
+        let rows = diesel::delete(
+            goals.filter(id.eq(item_id)))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn create_creates_item_correct_parameters() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let item = NewGoal {
+                weekday: Some(5),
+                ..NewGoal::new("weekend", 2500.0, 90.0, 300.0, 80.0)
+            };
+            let conn_mgr = setup_conn_mgr();
+            let created_id = CRUDGoal::create(&conn_mgr, &item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM goals WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            let expected = "2|weekend|5|||2500.0|90.0|300.0|80.0||||\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn create_rejects_invalid_weekday_range_and_targets() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = vec![
+                NewGoal { weekday: Some(7), ..NewGoal::new("invalid", 1.0, 1.0, 1.0, 1.0) },
+                NewGoal {
+                    start_date: Some(date(2000, 2, 1)),
+                    end_date: Some(date(2000, 1, 1)),
+                    ..NewGoal::new("invalid", 1.0, 1.0, 1.0, 1.0)
+                },
+                NewGoal::new("invalid", -1.0, 1.0, 1.0, 1.0),
+            ];
+            for item in items {
+                let ret_val = CRUDGoal::create(&conn_mgr, &item);
+                assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))), "{:?}", item);
+            }
+        })
+    }
+
+    #[test]
+    fn update_clears_targets_set_to_none() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let item = Goal { fats: None, ..Goal::new(1, "updated", 1800.0, 120.0, 200.0, 60.0) };
+            CRUDGoal::update(&conn_mgr, 1, item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT * FROM goals WHERE id=1;")
+                .output()
+                .expect("Failed to execute process");
+            let expected = "1|updated||||1800.0|120.0|200.0|||||\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn for_date_prefers_most_specific_goal() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            // 2000-01-01 is a saturday
+            let weekend = NewGoal {
+                weekday: Some(5),
+                ..NewGoal::new("saturday", 1.0, 1.0, 1.0, 1.0)
+            };
+            let holiday = NewGoal {
+                start_date: Some(date(2000, 1, 1)),
+                end_date: Some(date(2000, 1, 2)),
+                ..NewGoal::new("holiday", 1.0, 1.0, 1.0, 1.0)
+            };
+            CRUDGoal::create(&conn_mgr, &weekend).unwrap();
+            CRUDGoal::create(&conn_mgr, &holiday).unwrap();
+            let name = |d| CRUDGoal::for_date(&conn_mgr, &d, None).unwrap().map(|goal| goal.name);
+            assert_eq!(name(date(2000, 1, 1)), Some(String::from("holiday")));
+            assert_eq!(name(date(2000, 1, 8)), Some(String::from("saturday")));
+            assert_eq!(name(date(2000, 1, 3)), Some(String::from("default")));
+        })
+    }
+
+    #[test]
+    fn for_date_prefers_goal_of_profile() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let own = NewGoal { profile_id: Some(1), ..NewGoal::new("own", 1.0, 1.0, 1.0, 1.0) };
+            let own_id = CRUDGoal::create(&conn_mgr, &own).unwrap();
+            let day = date(2000, 1, 1);
+            let goal_id = |profile| CRUDGoal::for_date(&conn_mgr, &day, profile).unwrap()
+                .and_then(|goal| goal.id);
+            assert_eq!(goal_id(Some(1)), Some(own_id));
+            assert_eq!(goal_id(Some(2)), Some(1));
+            assert_eq!(goal_id(None), Some(1));
+        })
+    }
+
+    #[test]
+    fn for_date_without_goal_returns_none() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            CRUDGoal::delete(&conn_mgr, 1).unwrap();
+            assert_eq!(CRUDGoal::for_date(&conn_mgr, &date(2000, 1, 1), None).unwrap(), None);
+        })
+    }
+}
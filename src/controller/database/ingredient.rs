@@ -1,120 +1,431 @@
 use diesel::{
     prelude::*,
+    sql_types::Text,
     Queryable, Insertable,
 };
-use log::error;
+use std::collections::BTreeMap;
 use crate::{
-    controller::database::{ConnMgrPool, CRUDController},
+    controller::database::{ConnMgrPool, CRUDController, check_page, last_insert_id},
     controller::database::ingredient_macro::CRUDIngredientMacro,
+    controller::database::ingredient_micro::CRUDIngredientMicro,
+    controller::database::ingredient_portion::CRUDIngredientPortion,
     controller::database::meal_ingredient::CRUDMealIngredient,
+    controller::database::profile::{check_profile, check_visible},
     controller::database::recipe_ingredient::CRUDRecipeIngredient,
+    controller::util::edit_distance,
+    error::NutriplanError,
     schema::*
 };
 
-#[derive(Insertable)]
+sql_function!(fn lower(x: Text) -> Text);
+
+#[derive(Insertable, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="ingredients"]
 pub struct NewIngredient {
-    pub name: String
+    pub name: String,
+    /** Density in g/ml, used to convert volumes to masses */
+    pub density: Option<f32>,
+    /** Owner of a private ingredient, shared with all profiles if `None` */
+    pub profile_id: Option<i32>,
+    /** EAN-8, EAN-13 or UPC-A code printed on the package */
+    pub barcode: Option<String>,
+    /** Database an imported ingredient was taken from, e.g. `fdc` */
+    pub source: Option<String>,
+    /** Id of the ingredient within its source, used to update it on a later import */
+    pub source_id: Option<String>
 }
 
 impl NewIngredient {
     pub fn new(name: &str) -> Self {
+        NewIngredient::with_density(name, None)
+    }
+
+    pub fn with_density(name: &str, density: Option<f32>) -> Self {
         NewIngredient {
-            name: String::from(name)
+            name: String::from(name),
+            density,
+            profile_id: None,
+            barcode: None,
+            source: None,
+            source_id: None
         }
     }
 }
 
 #[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="ingredients"]
 pub struct Ingredient {
     pub id: Option<i32>,
-    pub name: String
+    pub name: String,
+    /** Density in g/ml, used to convert volumes to masses */
+    pub density: Option<f32>,
+    /** Owner of a private ingredient, shared with all profiles if `None` */
+    pub profile_id: Option<i32>,
+    /** EAN-8, EAN-13 or UPC-A code printed on the package */
+    pub barcode: Option<String>,
+    /** Database an imported ingredient was taken from, e.g. `fdc` */
+    pub source: Option<String>,
+    /** Id of the ingredient within its source, used to update it on a later import */
+    pub source_id: Option<String>
 }
 
 impl Ingredient {
     pub fn new(id: i32, name: &str) -> Self {
+        Ingredient::with_density(id, name, None)
+    }
+
+    pub fn with_density(id: i32, name: &str, density: Option<f32>) -> Self {
         Ingredient {
             id: Some(id),
-            name: String::from(name)
+            name: String::from(name),
+            density,
+            profile_id: None,
+            barcode: None,
+            source: None,
+            source_id: None
         }
     }
 }
 
+/**
+ * Strategy used to match a search query against ingredient names. Matching is case-insensitive for
+ * ASCII letters in all modes.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchMode {
+    /** Name starts with the query */
+    Prefix,
+    /** Name contains the query */
+    Substring,
+    /** Name contains the query or differs from it only by a few typos */
+    Fuzzy,
+}
+
+/**
+ * Validate the check digit of an EAN-8, EAN-13 or UPC-A code and return it in the form it is
+ * stored in. UPC-A codes are a subset of EAN-13, so they are stored with a leading zero and a
+ * package is found whether the scanner reports it as UPC-A or as EAN-13.
+ */
+pub fn normalize_barcode(code: &str) -> Result<String, NutriplanError> {
+    let code = code.trim();
+    let invalid = || NutriplanError::InvalidInput(format!("invalid barcode {}", code));
+    let digits = code.chars()
+        .map(|c| c.to_digit(10))
+        .collect::<Option<Vec<u32>>>()
+        .ok_or_else(invalid)?;
+    if ![8, 12, 13].contains(&digits.len()) {
+        return Err(invalid())
+    }
+    // Weights alternate between 3 and 1 starting with the digit left of the check digit
+    let (check, payload) = digits.split_last().ok_or_else(invalid)?;
+    let sum: u32 = payload.iter()
+        .rev()
+        .enumerate()
+        .map(|(pos, digit)| if pos % 2 == 0 { digit * 3 } else { *digit })
+        .sum();
+    if (10 - sum % 10) % 10 != *check {
+        return Err(invalid())
+    }
+    match digits.len() {
+        12 => Ok(format!("0{}", code)),
+        _ => Ok(code.to_owned())
+    }
+}
+
+/**
+ * Escape the wildcards of `LIKE` in a text, using a backslash as escape character.
+ */
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn normalize_optional_barcode(code: &Option<String>) -> Result<Option<String>, NutriplanError> {
+    code.as_deref().map(normalize_barcode).transpose()
+}
+
 pub struct CRUDIngredient { }
 
 impl CRUDIngredient {
+    /**
+     * Find the ingredient with the given EAN-8, EAN-13 or UPC-A code.
+     */
+    pub fn find_by_barcode(conn_mgr: &ConnMgrPool, code: &str)
+        -> Result<Option<Ingredient>, NutriplanError>
+    {
+        use crate::schema::ingredients::dsl::*;
+
+        let code = normalize_barcode(code)?;
+        Ok(ingredients
+            .filter(barcode.eq(code))
+            .first::<Ingredient>(conn_mgr)
+            .optional()?)
+    }
+
+    /**
+     * Find the ingredient imported from the given source under the given id.
+     */
+    pub fn find_by_source(conn_mgr: &ConnMgrPool, item_source: &str, item_source_id: &str)
+        -> Result<Option<Ingredient>, NutriplanError>
+    {
+        use crate::schema::ingredients::dsl::*;
+
+        Ok(ingredients
+            .filter(source.eq(item_source))
+            .filter(source_id.eq(item_source_id))
+            .first::<Ingredient>(conn_mgr)
+            .optional()?)
+    }
+
+    /**
+     * Search ingredients by name.
+     *
+     * Results are ranked by relevance: exact matches first, followed by names starting with the
+     * query, names with a word starting with the query, names containing the query and finally
+     * names within a small edit distance of the query. Ties are ordered by name length and id.
+     *
+     * # Attributes
+     * * `query` - Text to search for
+     * * `mode` - Strategy used to match the query
+     * * `limit` - Maximum number of ingredients returned
+     * * `profile` - Only search ingredients visible to this profile, all ingredients if `None`
+     */
+    pub fn search(conn_mgr: &ConnMgrPool, query: &str, mode: SearchMode, limit: usize,
+        profile: Option<i32>) -> Result<Vec<Ingredient>, NutriplanError>
+    {
+        use crate::schema::ingredients::dsl::*;
+
+        let query = query.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase();
+        if query.is_empty() {
+            return Err(NutriplanError::InvalidInput(String::from("search query is empty")))
+        }
+        // Only names matching one of the patterns are loaded and ranked
+        let patterns: Vec<String> = match mode {
+            SearchMode::Prefix => vec![format!("{}%", escape_like(&query))],
+            SearchMode::Substring => vec![format!("%{}%", escape_like(&query))],
+            SearchMode::Fuzzy => Self::fuzzy_fragments(&query)
+                .iter()
+                .map(|fragment| format!("%{}%", escape_like(fragment)))
+                .collect()
+        };
+        let mut candidates = BTreeMap::new();
+        for pattern in patterns {
+            let mut matching = ingredients
+                .filter(lower(name).like(pattern).escape('\\'))
+                .into_boxed();
+            if let Some(pid) = profile {
+                matching = matching.filter(profile_id.is_null().or(profile_id.eq(pid)));
+            }
+            for item in matching.load::<Ingredient>(conn_mgr)? {
+                candidates.entry(item.id).or_insert(item);
+            }
+        }
+        let mut hits: Vec<(usize, Ingredient)> = candidates
+            .into_values()
+            .filter_map(|item| Self::rank(&query, &item.name.to_lowercase(), mode)
+                .map(|rank| (rank, item)))
+            .collect();
+        hits.sort_by_key(|(rank, item)| (*rank, item.name.chars().count()));
+        Ok(hits.into_iter().take(limit).map(|(_, item)| item).collect())
+    }
+
+    /**
+     * Rank how well a lowercase name matches a lowercase query. Lower is better, `None` if the
+     * name does not match at all.
+     */
+    fn rank(query: &str, name: &str, mode: SearchMode) -> Option<usize> {
+        if name == query {
+            return Some(0)
+        }
+        if name.starts_with(query) {
+            return Some(1)
+        }
+        if mode == SearchMode::Prefix {
+            return None
+        }
+        if name.split_whitespace().any(|word| word.starts_with(query)) {
+            return Some(2)
+        }
+        if name.contains(query) {
+            return Some(3)
+        }
+        if mode == SearchMode::Substring {
+            return None
+        }
+        // Compare against the whole name and against each run of words as long as the query, so
+        // a misspelled single word still finds a longer name
+        let query_words = query.split_whitespace().count();
+        let words: Vec<&str> = name.split_whitespace().collect();
+        let distance = words
+            .windows(query_words.min(words.len()).max(1))
+            .map(|window| edit_distance(query, &window.join(" ")))
+            .chain(std::iter::once(edit_distance(query, name)))
+            .min()?;
+        match distance <= Self::tolerance(query) {
+            true => Some(4 + distance),
+            false => None
+        }
+    }
+
+    /**
+     * Number of typos a fuzzy search tolerates for the given query.
+     */
+    fn tolerance(query: &str) -> usize {
+        (query.chars().count() / 4).max(1)
+    }
+
+    /**
+     * Split a query into fragments, one of which every name within the fuzzy tolerance of the
+     * query contains unchanged. The query is cut into one piece more than the tolerated number of
+     * typos, and each typo changes at most one piece.
+     */
+    fn fuzzy_fragments(query: &str) -> Vec<String> {
+        let chars: Vec<char> = query.chars().collect();
+        let pieces = (Self::tolerance(query) + 1).min(chars.len());
+        (0..pieces)
+            .map(|i| chars[i * chars.len() / pieces..(i + 1) * chars.len() / pieces]
+                .iter()
+                .collect::<String>())
+            // A name may separate words by other whitespace, so only the longest word of a piece
+            // is matched
+            .filter_map(|piece| piece
+                .split_whitespace()
+                .max_by_key(|word| word.chars().count())
+                .map(String::from))
+            .collect()
+    }
+
+    /**
+     * List the ingredients visible to a profile, i.e. its private and all shared ingredients.
+     */
+    pub fn list_by_profile(conn_mgr: &ConnMgrPool, pid: i32, limit: i64, offset: i64)
+        -> Result<Vec<Ingredient>, NutriplanError>
+    {
+        use crate::schema::ingredients::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(ingredients
+            .filter(profile_id.is_null().or(profile_id.eq(pid)))
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Ingredient>(conn_mgr)?)
+    }
+
+    /**
+     * Validate that all meals and recipes using an ingredient may still do so if it is owned by
+     * the given profile.
+     */
+    fn check_owner(conn_mgr: &ConnMgrPool, item_id: i32, owner: Option<i32>)
+        -> Result<(), NutriplanError>
+    {
+        let meal_owners = meal_ingredients::table
+            .inner_join(meals::table)
+            .filter(meal_ingredients::ingredient_id.eq(item_id))
+            .select(meals::profile_id)
+            .load::<Option<i32>>(conn_mgr)?;
+        let recipe_owners = recipe_ingredients::table
+            .inner_join(recipes::table)
+            .filter(recipe_ingredients::ingredient_id.eq(item_id))
+            .select(recipes::profile_id)
+            .load::<Option<i32>>(conn_mgr)?;
+        for pid in meal_owners.into_iter().chain(recipe_owners) {
+            check_visible(Self::ENTITY, item_id, owner, pid)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Make the private ingredients of a profile shared.
+     */
+    pub fn share_by_profile_id(conn_mgr: &ConnMgrPool, pid: i32) -> Result<(), NutriplanError> {
+        use crate::schema::ingredients::dsl::*;
+
+        diesel::update(
+            ingredients.filter(profile_id.eq(pid)))
+            .set(profile_id.eq(None::<i32>))
+            .execute(conn_mgr)?;
+        Ok(())
+    }
 }
 
 impl CRUDController for CRUDIngredient {
     type NewItem = NewIngredient;
     type Item = Ingredient;
 
-    fn create(conn_mgr: &ConnMgrPool, new_item: &NewIngredient) -> bool {
-        match diesel::insert_into(ingredients::table)
-        .values(new_item)
-        .execute(conn_mgr) {
-            Ok(_) => true,
-            Err(e) => {
-                error!("Could not insert ingredient: {}", e);
-                false
-            }
-        }
+    const ENTITY: &'static str = "ingredient";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewIngredient) -> Result<i32, NutriplanError> {
+        check_profile(conn_mgr, new_item.profile_id)?;
+        let new_item = NewIngredient {
+            barcode: normalize_optional_barcode(&new_item.barcode)?,
+            ..new_item.clone()
+        };
+        diesel::insert_into(ingredients::table)
+            .values(&new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
     }
 
-    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Option<Ingredient> {
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<Ingredient, NutriplanError> {
         use crate::schema::ingredients::dsl::*;
 
-        match ingredients
+        ingredients
             .filter(id.eq(item_id))
-            .load::<Ingredient>(conn_mgr)
-        {
-            Ok(mut entities) => {
-                if entities.len() == 0 {
-                    error!("Could not find item with id: {}", item_id);
-                    None
-                } else {
-                    Some(entities.remove(0))
-                }
-            },
-            Err(e) => {
-                error!("Could not read from database: {}", e);
-                None
-            }
-        }
+            .first::<Ingredient>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
     }
 
-    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Ingredient) -> bool {
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<Ingredient>, NutriplanError>
+    {
         use crate::schema::ingredients::dsl::*;
 
-        match diesel::update(
-            ingredients.filter(id.eq(item_id)))
-            .set(item)
-            .execute(conn_mgr)
-            {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not update item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+        check_page(limit, offset)?;
+        Ok(ingredients
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Ingredient>(conn_mgr)?)
     }
 
-    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> bool {
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Ingredient)
+        -> Result<(), NutriplanError>
+    {
         use crate::schema::ingredients::dsl::*;
 
-        CRUDIngredientMacro::delete_by_ingredient_id(conn_mgr, item_id);
-        CRUDMealIngredient::delete_by_ingredient_id(conn_mgr, item_id);
-        CRUDRecipeIngredient::delete_by_ingredient_id(conn_mgr, item_id);
-        match diesel::delete(
+        check_profile(conn_mgr, item.profile_id)?;
+        Self::check_owner(conn_mgr, item_id, item.profile_id)?;
+        let rows = diesel::update(
             ingredients.filter(id.eq(item_id)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+            .set((
+                name.eq(item.name),
+                density.eq(item.density),
+                profile_id.eq(item.profile_id),
+                barcode.eq(normalize_optional_barcode(&item.barcode)?),
+                source.eq(item.source),
+                source_id.eq(item.source_id),
+            ))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
+        use crate::schema::ingredients::dsl::*;
+
+        conn_mgr.transaction(|| {
+            CRUDIngredientMacro::delete_by_ingredient_id(conn_mgr, item_id)?;
+            CRUDIngredientMicro::delete_by_ingredient_id(conn_mgr, item_id)?;
+            CRUDIngredientPortion::delete_by_ingredient_id(conn_mgr, item_id)?;
+            CRUDMealIngredient::delete_by_ingredient_id(conn_mgr, item_id)?;
+            CRUDRecipeIngredient::delete_by_ingredient_id(conn_mgr, item_id)?;
+            let rows = diesel::delete(
+                ingredients.filter(id.eq(item_id)))
+                .execute(conn_mgr)?;
+            Self::affected(rows, item_id)
+        })
     }
 }
 
@@ -139,7 +450,17 @@ mod test {
             let ingredient = NewIngredient::new("test");
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDIngredient::create(&conn_mgr, &ingredient);
-            assert!(ret_val, "could not create ingredient");
+            assert!(ret_val.is_ok(), "could not create ingredient");
+        })
+    }
+
+    #[test]
+    fn create_returns_id_of_created_item() {
+        run_db_test(|| {
+            let ingredient = NewIngredient::new("test");
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDIngredient::create(&conn_mgr, &ingredient).unwrap();
+            assert_eq!(ret_val, 3);
         })
     }
 
@@ -150,10 +471,10 @@ mod test {
             use std::str;
             let ingredient = NewIngredient::new("created");
             let conn_mgr = setup_conn_mgr();
-            let _ = CRUDIngredient::create(&conn_mgr, &ingredient);
+            let created_id = CRUDIngredient::create(&conn_mgr, &ingredient).unwrap();
             let output = Command::new("sqlite3")
                 .arg("test.db")
-                .arg("SELECT name FROM ingredients WHERE id=(select max(id) from ingredients);")
+                .arg(format!("SELECT name FROM ingredients WHERE id={};", created_id))
                 .output()
                 .expect("Failed to execute process");
             let expected = "created\n";
@@ -171,6 +492,173 @@ mod test {
         })
     }
 
+    #[test]
+    fn read_with_unknown_id_returns_not_found() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDIngredient::read(&conn_mgr, 3);
+            assert!(matches!(ret_val, Err(NutriplanError::NotFound { id: 3, .. })));
+        })
+    }
+
+    #[test]
+    fn list_returns_items_ordered_by_id() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDIngredient::list(&conn_mgr, 10, 0).unwrap();
+            let ids: Vec<Option<i32>> = items.iter().map(|item| item.id).collect();
+            assert_eq!(ids, vec![Some(1), Some(2)]);
+        })
+    }
+
+    #[test]
+    fn list_applies_limit_and_offset() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDIngredient::list(&conn_mgr, 1, 1).unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].name, "test2");
+        })
+    }
+
+    #[test]
+    fn list_with_negative_limit_returns_invalid_input() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDIngredient::list(&conn_mgr, -1, 0);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn search_prefix_is_case_insensitive() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDIngredient::search(&conn_mgr, "TEST", SearchMode::Prefix, 10, None)
+                .unwrap();
+            assert_eq!(items.len(), 2);
+        })
+    }
+
+    #[test]
+    fn search_ranks_prefix_before_substring() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let _ = CRUDIngredient::create(&conn_mgr, &NewIngredient::new("Pineapple"));
+            let _ = CRUDIngredient::create(&conn_mgr, &NewIngredient::new("Apple juice"));
+            let items = CRUDIngredient::search(&conn_mgr, "apple", SearchMode::Substring, 10, None)
+                .unwrap();
+            let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+            assert_eq!(names, vec!["Apple juice", "Pineapple"]);
+        })
+    }
+
+    #[test]
+    fn search_fuzzy_finds_misspelled_name() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let _ = CRUDIngredient::create(&conn_mgr, &NewIngredient::new("Chicken thigh"));
+            let _ = CRUDIngredient::create(&conn_mgr, &NewIngredient::new("Chicken breast"));
+            let items = CRUDIngredient::search(&conn_mgr, "chiken breast", SearchMode::Fuzzy, 10,
+                None)
+                .unwrap();
+            assert_eq!(items[0].name, "Chicken breast");
+        })
+    }
+
+    #[test]
+    fn search_substring_does_not_match_misspelled_name() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let _ = CRUDIngredient::create(&conn_mgr, &NewIngredient::new("Chicken breast"));
+            let items = CRUDIngredient::search(&conn_mgr, "chiken", SearchMode::Substring, 10, None)
+                .unwrap();
+            assert!(items.is_empty());
+        })
+    }
+
+    #[test]
+    fn search_matches_wildcards_literally() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let _ = CRUDIngredient::create(&conn_mgr, &NewIngredient::new("Milk 100%"));
+            let items = CRUDIngredient::search(&conn_mgr, "_%", SearchMode::Substring, 10, None)
+                .unwrap();
+            assert!(items.is_empty());
+            let items = CRUDIngredient::search(&conn_mgr, "0%", SearchMode::Substring, 10, None)
+                .unwrap();
+            assert_eq!(items[0].name, "Milk 100%");
+        })
+    }
+
+    #[test]
+    fn search_with_empty_query_returns_invalid_input() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDIngredient::search(&conn_mgr, " ", SearchMode::Fuzzy, 10, None);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn normalize_barcode_validates_check_digit() {
+        assert_eq!(normalize_barcode("4006381333931").unwrap(), "4006381333931");
+        assert_eq!(normalize_barcode("96385074").unwrap(), "96385074");
+        assert_eq!(normalize_barcode("036000291452").unwrap(), "0036000291452");
+        assert!(matches!(normalize_barcode("4006381333932"), Err(NutriplanError::InvalidInput(_))));
+        assert!(matches!(normalize_barcode("4006381a33931"), Err(NutriplanError::InvalidInput(_))));
+        assert!(matches!(normalize_barcode("123456"), Err(NutriplanError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn create_rejects_invalid_barcode() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ingredient = NewIngredient {
+                barcode: Some(String::from("96385075")),
+                ..NewIngredient::new("created")
+            };
+            let ret_val = CRUDIngredient::create(&conn_mgr, &ingredient);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn create_rejects_duplicate_barcode() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let upc = NewIngredient {
+                barcode: Some(String::from("036000291452")),
+                ..NewIngredient::new("upc")
+            };
+            CRUDIngredient::create(&conn_mgr, &upc).unwrap();
+            let ean = NewIngredient {
+                barcode: Some(String::from("0036000291452")),
+                ..NewIngredient::new("ean")
+            };
+            let ret_val = CRUDIngredient::create(&conn_mgr, &ean);
+            assert!(matches!(ret_val, Err(NutriplanError::ConstraintViolation(_))));
+        })
+    }
+
+    #[test]
+    fn find_by_barcode_accepts_upc_and_ean_form() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ingredient = NewIngredient {
+                barcode: Some(String::from("036000291452")),
+                ..NewIngredient::new("scanned")
+            };
+            let created_id = CRUDIngredient::create(&conn_mgr, &ingredient).unwrap();
+            for code in ["036000291452", "0036000291452"] {
+                let item = CRUDIngredient::find_by_barcode(&conn_mgr, code).unwrap().unwrap();
+                assert_eq!(item.id, Some(created_id));
+            }
+            let unknown = CRUDIngredient::find_by_barcode(&conn_mgr, "4006381333931").unwrap();
+            assert!(unknown.is_none());
+        })
+    }
+
     #[test]
     fn update_with_sane_id_updates_as_expected() {
         run_db_test(|| {
@@ -179,7 +667,7 @@ mod test {
             let ingredient = Ingredient::new(1, "updated");
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDIngredient::update(&conn_mgr, 1, ingredient);
-            let expected = "1|updated\n";
+            let expected = "1|updated|||||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM ingredients WHERE id=1;")
@@ -189,6 +677,60 @@ mod test {
         })
     }
 
+    #[test]
+    fn update_clears_profile_set_to_none() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let item = NewIngredient { profile_id: Some(1), ..NewIngredient::new("private") };
+            let iid = CRUDIngredient::create(&conn_mgr, &item).unwrap();
+            CRUDIngredient::update(&conn_mgr, iid, Ingredient::new(iid, "private")).unwrap();
+            assert_eq!(CRUDIngredient::read(&conn_mgr, iid).unwrap().profile_id, None);
+        })
+    }
+
+    #[test]
+    fn update_rejects_making_ingredient_used_by_others_private() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let item = Ingredient { profile_id: Some(1), ..Ingredient::new(1, "test1") };
+            let ret_val = CRUDIngredient::update(&conn_mgr, 1, item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+            assert_eq!(CRUDIngredient::read(&conn_mgr, 1).unwrap().profile_id, None);
+        })
+    }
+
+    #[test]
+    fn update_clears_barcode_set_to_none() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let item = Ingredient {
+                barcode: Some(String::from("4006381333931")),
+                ..Ingredient::new(1, "test1")
+            };
+            CRUDIngredient::update(&conn_mgr, 1, item).unwrap();
+            CRUDIngredient::update(&conn_mgr, 1, Ingredient::new(1, "test1")).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT * FROM ingredients WHERE id=1;")
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("1|test1|||||\n", str::from_utf8(&output.stdout).unwrap());
+            assert!(CRUDIngredient::find_by_barcode(&conn_mgr, "4006381333931").unwrap().is_none());
+        })
+    }
+
+    #[test]
+    fn update_with_unknown_id_returns_not_found() {
+        run_db_test(|| {
+            let ingredient = Ingredient::new(3, "updated");
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDIngredient::update(&conn_mgr, 3, ingredient);
+            assert!(matches!(ret_val, Err(NutriplanError::NotFound { id: 3, .. })));
+        })
+    }
+
     #[test]
     fn delete_with_sane_id_deletes_as_expected() {
         run_db_test(|| {
@@ -196,7 +738,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDIngredient::delete(&conn_mgr, 1);
-            let expected = "2|test2\n";
+            let expected = "2|test2|||4000417025005||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM ingredients;")
@@ -213,7 +755,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDIngredient::delete(&conn_mgr, 2);
-            let expected = "1|1|1.0|1.0|1.0|1.0|1.0\n";
+            let expected = "1|1|1.0|1.0|1.0|1.0|1.0|0.0|0.0\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM ingredient_macros;")
@@ -230,7 +772,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDIngredient::delete(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM meal_ingredients;")
@@ -247,7 +789,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDIngredient::delete(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM recipe_ingredients;")
@@ -257,12 +799,38 @@ mod test {
         })
     }
 
+    #[test]
+    fn delete_keeps_dependent_entries_if_deleting_ingredient_fails() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            Command::new("sqlite3")
+                .arg("test.db")
+                .arg("CREATE TRIGGER block_delete BEFORE DELETE ON ingredients \
+                    BEGIN SELECT RAISE(ABORT, 'blocked'); END;")
+                .output()
+                .expect("Failed to execute process");
+            assert!(CRUDIngredient::delete(&conn_mgr, 1).is_err());
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT count(*) FROM ingredient_macros WHERE ingredient_id=1; \
+                    SELECT count(*) FROM ingredient_micros WHERE ingredient_id=1; \
+                    SELECT count(*) FROM ingredient_portions WHERE ingredient_id=1; \
+                    SELECT count(*) FROM meal_ingredients WHERE ingredient_id=1; \
+                    SELECT count(*) FROM recipe_ingredients WHERE ingredient_id=1;")
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("1\n1\n1\n1\n1\n", str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
     #[test]
     fn check_returns_true_if_ingredient_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDIngredient::check(&conn_mgr, 1);
-            assert_eq!(inserted, true)
+            let inserted = CRUDIngredient::check(&conn_mgr, 1).unwrap();
+            assert!(inserted)
         })
     }
 
@@ -270,8 +838,8 @@ mod test {
     fn check_returns_false_if_ingredient_not_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDIngredient::check(&conn_mgr, 3);
-            assert_eq!(inserted, false)
+            let inserted = CRUDIngredient::check(&conn_mgr, 3).unwrap();
+            assert!(!inserted)
         })
     }
 }
@@ -2,13 +2,15 @@ use diesel::{
     prelude::*,
     Queryable, Insertable
 };
-use log::error;
 use crate::{
-    controller::database::{ConnMgrPool, CRUDController},
+    controller::database::{ConnMgrPool, CRUDController, check_page, last_insert_id},
+    controller::nutrition::EnergyModel,
+    error::NutriplanError,
     schema::*
 };
 
 #[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="ingredient_macros"]
 pub struct NewIngredientMacro {
     pub ingredient_id: i32,
@@ -17,27 +19,43 @@ pub struct NewIngredientMacro {
     pub fats: f32,
     pub alcohols: f32,
     pub calories: f32,
+    pub fiber: f32,
+    pub polyols: f32,
 }
 
 impl NewIngredientMacro {
+    /**
+     * Create macros without fiber and polyols, calories are computed with the Atwater factors.
+     */
     pub fn new(ingredient_id: i32, proteins: f32, carbs: f32, fats: f32, alcohols: f32) -> Self
     {
-        let mut calories = proteins * 4.0;
-        calories += carbs * 4.0;
-        calories += fats * 4.0;
-        calories += alcohols * 4.0;
+        NewIngredientMacro::with_model(ingredient_id, proteins, carbs, fats, alcohols, 0.0, 0.0,
+            &EnergyModel::default())
+    }
+
+    /**
+     * Create macros including fiber and polyols, calories are computed with the given model.
+     */
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_model(ingredient_id: i32, proteins: f32, carbs: f32, fats: f32, alcohols: f32,
+        fiber: f32, polyols: f32, model: &EnergyModel) -> Self
+    {
+        let calories = model.calories(proteins, carbs, fats, alcohols, fiber, polyols);
         NewIngredientMacro {
             ingredient_id,
             proteins,
             carbs,
             fats,
             alcohols,
-            calories
+            calories,
+            fiber,
+            polyols
         }
     }
 }
 
 #[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="ingredient_macros"]
 pub struct IngredientMacro {
     pub id: Option<i32>,
@@ -47,16 +65,18 @@ pub struct IngredientMacro {
     pub fats: f32,
     pub alcohols: f32,
     pub calories: f32,
+    pub fiber: f32,
+    pub polyols: f32,
 }
 
 impl IngredientMacro {
+    /**
+     * Create macros without fiber and polyols, calories are computed with the Atwater factors.
+     */
     pub fn new(id: i32, ingredient_id: i32, proteins: f32, carbs: f32, fats: f32, alcohols: f32
         ) -> Self
     {
-        let mut calories = proteins * 4.0;
-        calories += carbs * 4.0;
-        calories += fats * 4.0;
-        calories += alcohols * 4.0;
+        let calories = EnergyModel::default().calories(proteins, carbs, fats, alcohols, 0.0, 0.0);
         IngredientMacro {
             id: Some(id),
             ingredient_id,
@@ -64,26 +84,54 @@ impl IngredientMacro {
             carbs,
             fats,
             alcohols,
-            calories
+            calories,
+            fiber: 0.0,
+            polyols: 0.0
         }
     }
+
+    /**
+     * Recompute the calories from the macros with the given model.
+     */
+    pub fn apply_model(&mut self, model: &EnergyModel) {
+        self.calories = model.calories(self.proteins, self.carbs, self.fats, self.alcohols,
+            self.fiber, self.polyols);
+    }
 }
 
 pub struct CRUDIngredientMacro { }
 
 impl CRUDIngredientMacro {
-    pub fn delete_by_ingredient_id(conn_mgr: &ConnMgrPool, item_id: i32) -> bool {
+    pub fn delete_by_ingredient_id(conn_mgr: &ConnMgrPool, item_id: i32)
+        -> Result<(), NutriplanError>
+    {
         use crate::schema::ingredient_macros::dsl::*;
 
-        match diesel::delete(
+        diesel::delete(
             ingredient_macros.filter(ingredient_id.eq(item_id)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+            .execute(conn_mgr)?;
+        Ok(())
+    }
+
+    /**
+     * Recompute the stored calories of all ingredient macros with the given model.
+     *
+     * # Returns
+     * * Number of updated rows
+     */
+    pub fn recompute_calories(conn_mgr: &ConnMgrPool, model: &EnergyModel)
+        -> Result<usize, NutriplanError>
+    {
+        use crate::schema::ingredient_macros::dsl::*;
+
+        Ok(diesel::update(ingredient_macros)
+            .set(calories.eq(proteins * model.proteins
+                + carbs * model.carbs
+                + fats * model.fats
+                + alcohols * model.alcohols
+                + fiber * model.fiber
+                + polyols * model.polyols))
+            .execute(conn_mgr)?)
     }
 }
 
@@ -91,74 +139,64 @@ impl CRUDController for CRUDIngredientMacro {
     type NewItem = NewIngredientMacro;
     type Item = IngredientMacro;
 
-    fn create(conn_mgr: &ConnMgrPool, new_item: &NewIngredientMacro) -> bool {
+    const ENTITY: &'static str = "ingredient macro";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewIngredientMacro)
+        -> Result<i32, NutriplanError>
+    {
         use crate::controller::database::ingredient::CRUDIngredient;
-        let avail = CRUDIngredient::check(conn_mgr, new_item.ingredient_id);
-        match avail {
-            true => (),
-            false => return false
-        }
-        match diesel::insert_into(ingredient_macros::table)
-        .values(new_item)
-        .execute(conn_mgr) {
-            Ok(_) => true,
-            Err(e) => {
-                error!("Could not insert ingredient: {}", e);
-                false
-            }
+        if !CRUDIngredient::check(conn_mgr, new_item.ingredient_id)? {
+            return Err(NutriplanError::missing_reference(
+                CRUDIngredient::ENTITY, new_item.ingredient_id))
         }
+        diesel::insert_into(ingredient_macros::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
     }
 
-    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Option<IngredientMacro> {
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<IngredientMacro, NutriplanError> {
         use crate::schema::ingredient_macros::dsl::*;
 
-        match ingredient_macros
+        ingredient_macros
             .filter(id.eq(item_id))
-            .load::<IngredientMacro>(conn_mgr)
-        {
-            Ok(mut entities) => {
-                if entities.len() == 0 {
-                    error!("Could not find ingredient with id: {}", item_id);
-                    None
-                } else {
-                    Some(entities.remove(0))
-                }
-            },
-            Err(e) => {
-                error!("Could not read from database: {}", e);
-                None
-            }
-        }
+            .first::<IngredientMacro>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
     }
 
-    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: IngredientMacro) -> bool {
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<IngredientMacro>, NutriplanError>
+    {
+        use crate::schema::ingredient_macros::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(ingredient_macros
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<IngredientMacro>(conn_mgr)?)
+    }
+
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: IngredientMacro)
+        -> Result<(), NutriplanError>
+    {
         use crate::schema::ingredient_macros::dsl::*;
 
-        match diesel::update(
+        let rows = diesel::update(
             ingredient_macros.filter(id.eq(item_id)))
             .set(item)
-            .execute(conn_mgr)
-            {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not update item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
     }
 
-    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> bool {
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
         use crate::schema::ingredient_macros::dsl::*;
 
-        match diesel::delete(
+        let rows = diesel::delete(
             ingredient_macros.filter(id.eq(item_id)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
     }
 }
 
@@ -183,7 +221,17 @@ mod test {
             let item = NewIngredientMacro::new(1, 2.0, 3.0, 4.0, 5.0);
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDIngredientMacro::create(&conn_mgr, &item);
-            assert!(ret_val, "could not create share");
+            assert!(ret_val.is_ok(), "could not create share");
+        })
+    }
+
+    #[test]
+    fn create_returns_id_of_created_item() {
+        run_db_test(|| {
+            let item = NewIngredientMacro::new(1, 2.0, 3.0, 4.0, 5.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDIngredientMacro::create(&conn_mgr, &item).unwrap();
+            assert_eq!(ret_val, 3);
         })
     }
 
@@ -197,6 +245,16 @@ mod test {
         })
     }
 
+    #[test]
+    fn list_returns_items_ordered_by_id() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDIngredientMacro::list(&conn_mgr, 10, 0).unwrap();
+            let ids: Vec<Option<i32>> = items.iter().map(|item| item.id).collect();
+            assert_eq!(ids, vec![Some(1), Some(2)]);
+        })
+    }
+
     #[test]
     fn update_with_sane_id_updates_as_expected() {
         run_db_test(|| {
@@ -205,7 +263,7 @@ mod test {
             let item = IngredientMacro::new(1, 1, 4.0, 5.0, 6.0, 7.0);
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDIngredientMacro::update(&conn_mgr, 1, item);
-            let expected = "1|1|4.0|5.0|6.0|7.0|88.0\n";
+            let expected = "1|1|4.0|5.0|6.0|7.0|139.0|0.0|0.0\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM ingredient_macros WHERE id=1;")
@@ -215,6 +273,38 @@ mod test {
         })
     }
 
+    #[test]
+    fn new_uses_atwater_factors() {
+        let item = NewIngredientMacro::new(1, 1.0, 1.0, 1.0, 1.0);
+        assert_eq!(item.calories, 24.0);
+    }
+
+    #[test]
+    fn with_model_counts_fiber_and_polyols() {
+        let item = NewIngredientMacro::with_model(1, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0,
+            &EnergyModel::ATWATER_WITH_FIBER);
+        assert_eq!(item.calories, 28.4);
+    }
+
+    #[test]
+    fn recompute_calories_updates_all_rows() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let rows = CRUDIngredientMacro::recompute_calories(&conn_mgr, &EnergyModel::ATWATER)
+                .unwrap();
+            assert_eq!(rows, 2);
+            let expected = "24.0\n48.0\n";
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT calories FROM ingredient_macros;")
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
     #[test]
     fn delete_with_sane_id_deletes_as_expected() {
         run_db_test(|| {
@@ -222,7 +312,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDIngredientMacro::delete(&conn_mgr, 1);
-            let expected = "2|2|2.0|2.0|2.0|2.0|2.0\n";
+            let expected = "2|2|2.0|2.0|2.0|2.0|2.0|0.0|0.0\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM ingredient_macros;")
@@ -239,7 +329,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDIngredientMacro::delete_by_ingredient_id(&conn_mgr, 1);
-            let expected = "2|2|2.0|2.0|2.0|2.0|2.0\n";
+            let expected = "2|2|2.0|2.0|2.0|2.0|2.0|0.0|0.0\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM ingredient_macros;")
@@ -257,7 +347,7 @@ mod test {
             let item = NewIngredientMacro::new(3, 2.0, 3.0, 4.0, 5.0);
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDIngredientMacro::create(&conn_mgr, &item);
-            let expected = "1|1|1.0|1.0|1.0|1.0|1.0\n2|2|2.0|2.0|2.0|2.0|2.0\n";
+            let expected = "1|1|1.0|1.0|1.0|1.0|1.0|0.0|0.0\n2|2|2.0|2.0|2.0|2.0|2.0|0.0|0.0\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM ingredient_macros;")
@@ -271,8 +361,8 @@ mod test {
     fn check_returns_true_if_ingredient_macro_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDIngredientMacro::check(&conn_mgr, 1);
-            assert_eq!(inserted, true)
+            let inserted = CRUDIngredientMacro::check(&conn_mgr, 1).unwrap();
+            assert!(inserted)
         })
     }
 
@@ -280,8 +370,8 @@ mod test {
     fn check_returns_false_if_ingredient_macro_not_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDIngredientMacro::check(&conn_mgr, 3);
-            assert_eq!(inserted, false)
+            let inserted = CRUDIngredientMacro::check(&conn_mgr, 3).unwrap();
+            assert!(!inserted)
         })
     }
 }
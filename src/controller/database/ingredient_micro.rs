@@ -0,0 +1,229 @@
+use diesel::{prelude::*, Queryable, Insertable};
+
+use crate::{
+    controller::database::{ConnMgrPool, CRUDController, check_page, last_insert_id},
+    controller::database::ingredient::CRUDIngredient,
+    controller::database::micronutrient::CRUDMicronutrient,
+    error::NutriplanError,
+    schema::*
+};
+
+/**
+ * Amount of a micronutrient per 100 g of an ingredient, in the unit of the micronutrient.
+ */
+#[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="ingredient_micros"]
+pub struct NewIngredientMicro {
+    pub ingredient_id: i32,
+    pub micronutrient_id: i32,
+    pub amount: f32
+}
+
+impl NewIngredientMicro {
+    pub fn new(ingredient_id: i32, micronutrient_id: i32, amount: f32) -> Self {
+        NewIngredientMicro { ingredient_id, micronutrient_id, amount }
+    }
+}
+
+#[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="ingredient_micros"]
+pub struct IngredientMicro {
+    pub id: Option<i32>,
+    pub ingredient_id: i32,
+    pub micronutrient_id: i32,
+    pub amount: f32
+}
+
+impl IngredientMicro {
+    pub fn new(id: i32, ingredient_id: i32, micronutrient_id: i32, amount: f32) -> Self {
+        IngredientMicro { id: Some(id), ingredient_id, micronutrient_id, amount }
+    }
+}
+
+pub struct CRUDIngredientMicro { }
+
+impl CRUDIngredientMicro {
+    pub fn delete_by_ingredient_id(conn_mgr: &ConnMgrPool, iid: i32)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::ingredient_micros::dsl::*;
+
+        diesel::delete(
+            ingredient_micros.filter(ingredient_id.eq(iid)))
+            .execute(conn_mgr)?;
+        Ok(())
+    }
+
+    pub fn delete_by_micronutrient_id(conn_mgr: &ConnMgrPool, mid: i32)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::ingredient_micros::dsl::*;
+
+        diesel::delete(
+            ingredient_micros.filter(micronutrient_id.eq(mid)))
+            .execute(conn_mgr)?;
+        Ok(())
+    }
+
+    /**
+     * List all micronutrient amounts stored for an ingredient.
+     */
+    pub fn list_by_ingredient_id(conn_mgr: &ConnMgrPool, iid: i32)
+        -> Result<Vec<IngredientMicro>, NutriplanError>
+    {
+        use crate::schema::ingredient_micros::dsl::*;
+
+        Ok(ingredient_micros
+            .filter(ingredient_id.eq(iid))
+            .order(id.asc())
+            .load::<IngredientMicro>(conn_mgr)?)
+    }
+
+    fn check_references(conn_mgr: &ConnMgrPool, iid: i32, mid: i32) -> Result<(), NutriplanError> {
+        if !CRUDIngredient::check(conn_mgr, iid)? {
+            return Err(NutriplanError::missing_reference(CRUDIngredient::ENTITY, iid))
+        }
+        if !CRUDMicronutrient::check(conn_mgr, mid)? {
+            return Err(NutriplanError::missing_reference(CRUDMicronutrient::ENTITY, mid))
+        }
+        Ok(())
+    }
+}
+
+impl CRUDController for CRUDIngredientMicro {
+    type NewItem = NewIngredientMicro;
+    type Item = IngredientMicro;
+
+    const ENTITY: &'static str = "ingredient micro";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewIngredientMicro)
+        -> Result<i32, NutriplanError>
+    {
+        Self::check_references(conn_mgr, new_item.ingredient_id, new_item.micronutrient_id)?;
+
+        diesel::insert_into(ingredient_micros::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
+    }
+
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<IngredientMicro, NutriplanError> {
+        use crate::schema::ingredient_micros::dsl::*;
+
+        ingredient_micros
+            .filter(id.eq(item_id))
+            .first::<IngredientMicro>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
+    }
+
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<IngredientMicro>, NutriplanError>
+    {
+        use crate::schema::ingredient_micros::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(ingredient_micros
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<IngredientMicro>(conn_mgr)?)
+    }
+
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: IngredientMicro)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::ingredient_micros::dsl::*;
+
+        Self::check_references(conn_mgr, item.ingredient_id, item.micronutrient_id)?;
+        let rows = diesel::update(
+            ingredient_micros.filter(id.eq(item_id)))
+            .set(item)
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
+        use crate::schema::ingredient_micros::dsl::*;
+
+        let rows = diesel::delete(
+            ingredient_micros.filter(id.eq(item_id)))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    #[test]
+    fn create_creates_item_correct_parameters() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let item = NewIngredientMicro::new(1, 2, 0.25);
+            let conn_mgr = setup_conn_mgr();
+            let created_id = CRUDIngredientMicro::create(&conn_mgr, &item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM ingredient_micros WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            let expected = "3|1|2|0.25\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn create_returns_foreign_key_violation_on_unknown_micronutrient() {
+        run_db_test(|| {
+            let item = NewIngredientMicro::new(1, 99, 1.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDIngredientMicro::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
+    #[test]
+    fn create_rejects_second_amount_of_same_micronutrient() {
+        run_db_test(|| {
+            let item = NewIngredientMicro::new(1, 1, 1.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDIngredientMicro::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::ConstraintViolation(_))));
+        })
+    }
+
+    #[test]
+    fn list_by_ingredient_id_returns_only_matching_items() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDIngredientMicro::list_by_ingredient_id(&conn_mgr, 2).unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].micronutrient_id, 4);
+            assert_eq!(items[0].amount, 400.0);
+        })
+    }
+
+    #[test]
+    fn delete_by_ingredient_id_with_sane_id_deletes_as_expected() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            CRUDIngredientMicro::delete_by_ingredient_id(&conn_mgr, 1).unwrap();
+            let expected = "2|2|4|400.0\n";
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT * FROM ingredient_micros;")
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+}
@@ -0,0 +1,253 @@
+use diesel::{prelude::*, Queryable, Insertable};
+
+use crate::{
+    controller::database::{ConnMgrPool, CRUDController, check_page, last_insert_id},
+    controller::database::ingredient::CRUDIngredient,
+    controller::units::Quantity,
+    error::NutriplanError,
+    schema::*
+};
+
+/**
+ * A named portion of an ingredient, e.g. "egg" or "slice", with its mass in gram.
+ */
+#[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="ingredient_portions"]
+pub struct NewIngredientPortion {
+    pub ingredient_id: i32,
+    pub name: String,
+    pub mass: f32
+}
+
+impl NewIngredientPortion {
+    pub fn new(ingredient_id: i32, name: &str, mass: f32) -> Self {
+        NewIngredientPortion { ingredient_id, name: name.to_owned(), mass }
+    }
+}
+
+#[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="ingredient_portions"]
+pub struct IngredientPortion {
+    pub id: Option<i32>,
+    pub ingredient_id: i32,
+    pub name: String,
+    pub mass: f32
+}
+
+impl IngredientPortion {
+    pub fn new(id: i32, ingredient_id: i32, name: &str, mass: f32) -> Self {
+        IngredientPortion { id: Some(id), ingredient_id, name: name.to_owned(), mass }
+    }
+}
+
+fn check_mass(mass: f32) -> Result<(), NutriplanError> {
+    if !(mass > 0.0 && mass.is_finite()) {
+        return Err(NutriplanError::InvalidInput(
+            format!("portion mass must be positive, got {}", mass)))
+    }
+    Ok(())
+}
+
+pub struct CRUDIngredientPortion { }
+
+impl CRUDIngredientPortion {
+    pub fn delete_by_ingredient_id(conn_mgr: &ConnMgrPool, iid: i32)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::ingredient_portions::dsl::*;
+
+        diesel::delete(
+            ingredient_portions.filter(ingredient_id.eq(iid)))
+            .execute(conn_mgr)?;
+        Ok(())
+    }
+
+    /**
+     * List all portions defined for an ingredient.
+     */
+    pub fn list_by_ingredient_id(conn_mgr: &ConnMgrPool, iid: i32)
+        -> Result<Vec<IngredientPortion>, NutriplanError>
+    {
+        use crate::schema::ingredient_portions::dsl::*;
+
+        Ok(ingredient_portions
+            .filter(ingredient_id.eq(iid))
+            .order(id.asc())
+            .load::<IngredientPortion>(conn_mgr)?)
+    }
+
+    /**
+     * Convert a quantity of an ingredient to gram.
+     *
+     * The unit is first looked up in the portions of the ingredient, so that e.g. a "cup" of flour
+     * can be defined by weight. Otherwise mass units are converted directly and volume units by
+     * the density of the ingredient.
+     */
+    pub fn to_grams(conn_mgr: &ConnMgrPool, iid: i32, quantity: &Quantity)
+        -> Result<f32, NutriplanError>
+    {
+        if !CRUDIngredient::check(conn_mgr, iid)? {
+            return Err(NutriplanError::missing_reference(CRUDIngredient::ENTITY, iid))
+        }
+        let portion = Self::list_by_ingredient_id(conn_mgr, iid)?
+            .into_iter()
+            .find(|portion| quantity.unit_matches(&portion.name));
+        if let Some(portion) = portion {
+            return Ok(quantity.amount * portion.mass)
+        }
+        if let Some(size) = quantity.gram_per_unit() {
+            return Ok(quantity.amount * size)
+        }
+        if let Some(size) = quantity.milliliter_per_unit() {
+            return match CRUDIngredient::read(conn_mgr, iid)?.density {
+                Some(density) => Ok(quantity.amount * size * density),
+                None => Err(NutriplanError::InvalidInput(format!(
+                    "ingredient with id {} has no density to convert {}", iid, quantity.unit)))
+            }
+        }
+        Err(NutriplanError::InvalidInput(
+            format!("unknown unit '{}' for ingredient with id {}", quantity.unit, iid)))
+    }
+}
+
+impl CRUDController for CRUDIngredientPortion {
+    type NewItem = NewIngredientPortion;
+    type Item = IngredientPortion;
+
+    const ENTITY: &'static str = "ingredient portion";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewIngredientPortion)
+        -> Result<i32, NutriplanError>
+    {
+        check_mass(new_item.mass)?;
+        if !CRUDIngredient::check(conn_mgr, new_item.ingredient_id)? {
+            return Err(NutriplanError::missing_reference(
+                CRUDIngredient::ENTITY, new_item.ingredient_id))
+        }
+
+        diesel::insert_into(ingredient_portions::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
+    }
+
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<IngredientPortion, NutriplanError> {
+        use crate::schema::ingredient_portions::dsl::*;
+
+        ingredient_portions
+            .filter(id.eq(item_id))
+            .first::<IngredientPortion>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
+    }
+
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<IngredientPortion>, NutriplanError>
+    {
+        use crate::schema::ingredient_portions::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(ingredient_portions
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<IngredientPortion>(conn_mgr)?)
+    }
+
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: IngredientPortion)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::ingredient_portions::dsl::*;
+
+        check_mass(item.mass)?;
+        let rows = diesel::update(
+            ingredient_portions.filter(id.eq(item_id)))
+            .set(item)
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
+        use crate::schema::ingredient_portions::dsl::*;
+
+        let rows = diesel::delete(
+            ingredient_portions.filter(id.eq(item_id)))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    #[test]
+    fn create_creates_item_correct_parameters() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let item = NewIngredientPortion::new(2, "egg", 55.0);
+            let conn_mgr = setup_conn_mgr();
+            let created_id = CRUDIngredientPortion::create(&conn_mgr, &item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM ingredient_portions WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            let expected = "2|2|egg|55.0\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn create_rejects_non_positive_mass() {
+        run_db_test(|| {
+            let item = NewIngredientPortion::new(2, "egg", 0.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDIngredientPortion::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn to_grams_uses_portion_of_ingredient() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let mass = CRUDIngredientPortion::to_grams(&conn_mgr, 1, &Quantity::new(2.0, "slices"))
+                .unwrap();
+            assert_eq!(mass, 60.0);
+            let ret_val = CRUDIngredientPortion::to_grams(&conn_mgr, 2,
+                &Quantity::new(2.0, "slices"));
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn to_grams_converts_mass_units() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let mass = CRUDIngredientPortion::to_grams(&conn_mgr, 2, &Quantity::new(0.5, "kg"))
+                .unwrap();
+            assert_eq!(mass, 500.0);
+        })
+    }
+
+    #[test]
+    fn to_grams_converts_volume_by_density() {
+        run_db_test(|| {
+            use crate::controller::database::ingredient::Ingredient;
+            let conn_mgr = setup_conn_mgr();
+            let cup = Quantity::new(1.0, "cup");
+            let ret_val = CRUDIngredientPortion::to_grams(&conn_mgr, 2, &cup);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+            CRUDIngredient::update(&conn_mgr, 2, Ingredient::with_density(2, "test2", Some(1.03)))
+                .unwrap();
+            let mass = CRUDIngredientPortion::to_grams(&conn_mgr, 2, &cup).unwrap();
+            assert!((mass - 247.2).abs() < 1e-3);
+        })
+    }
+}
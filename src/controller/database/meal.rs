@@ -1,124 +1,314 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveTime};
 use diesel::{
     prelude::*,
     Queryable, Insertable,
 };
-use log::error;
-
 use crate::{
-    controller::database::{ConnMgrPool, CRUDController},
-    controller::database::meal_ingredient::CRUDMealIngredient,
+    controller::database::{ConnMgrPool, CRUDController, MAX_BOUND_IDS, check_page, last_insert_id},
+    controller::database::ingredient::CRUDIngredient,
+    controller::database::meal_ingredient::{CRUDMealIngredient, NewMealIngredient},
+    controller::database::profile::{check_profile, check_visible},
+    controller::database::recipe::CRUDRecipe,
+    controller::nutrition::{MealNutrients, Nutrients, ingredient_nutrients},
+    error::NutriplanError,
     schema::*
 };
 
 #[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="meals"]
 pub struct NewMeal {
     pub name: String,
-    pub date: String,
-    pub time: String
+    pub date: NaiveDate,
+    pub time: NaiveTime,
+    /** Profile which ate the meal */
+    pub profile_id: Option<i32>
 }
 
 impl NewMeal {
-    pub fn new(name: &str, date: &chrono::NaiveDate, time: &chrono::NaiveTime) -> Self {
+    pub fn new(name: &str, date: &NaiveDate, time: &NaiveTime) -> Self {
         NewMeal {
             name: name.to_owned(),
-            date: date.to_string(),
-            time: time.to_string()
+            date: *date,
+            time: *time,
+            profile_id: None
         }
     }
 }
 
 #[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="meals"]
 pub struct Meal {
     pub id: Option<i32>,
     pub name: String,
-    pub date: String,
-    pub time: String
+    pub date: NaiveDate,
+    pub time: NaiveTime,
+    /** Profile which ate the meal */
+    pub profile_id: Option<i32>
 }
 
 impl Meal {
-    pub fn new(id: i32, name: &str, date: &chrono::NaiveDate, time: &chrono::NaiveTime) -> Self {
+    pub fn new(id: i32, name: &str, date: &NaiveDate, time: &NaiveTime) -> Self {
         Meal {
             id: Some(id),
             name: name.to_owned(),
-            date: date.to_string(),
-            time: time.to_string()
+            date: *date,
+            time: *time,
+            profile_id: None
         }
     }
 }
 
+/**
+ * Amount of a recipe eaten in a meal.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecipePortion {
+    /** Number of servings, may be fractional */
+    Servings(f32),
+    /** Mass in gram of the finished dish */
+    Mass(f32),
+}
+
 pub struct CRUDMeal { }
 
-impl CRUDMeal {}
+impl CRUDMeal {
+    /**
+     * Log a portion of a recipe into a meal by adding each recipe ingredient as a meal ingredient
+     * with its mass scaled to the portion. Component recipes are expanded into their ingredients.
+     * Ingredients with a mass of zero are left out.
+     *
+     * A mass portion refers to the finished dish, i.e. the yield mass of the recipe if one is
+     * stored. Returns the ids of the created meal ingredients.
+     */
+    pub fn add_recipe(conn_mgr: &ConnMgrPool, meal_id: i32, recipe_id: i32,
+        portion: RecipePortion) -> Result<Vec<i32>, NutriplanError>
+    {
+        let meal = Self::read(conn_mgr, meal_id)?;
+        let recipe = CRUDRecipe::read(conn_mgr, recipe_id)?;
+        check_visible(CRUDRecipe::ENTITY, recipe_id, recipe.profile_id, meal.profile_id)?;
+        let (recipe_mass, parts) = CRUDRecipe::expand(conn_mgr, recipe_id)?;
+        let factor = match portion {
+            RecipePortion::Servings(servings) if servings > 0.0 => {
+                servings / recipe.servings as f32
+            },
+            RecipePortion::Mass(mass) if mass > 0.0 => {
+                if recipe_mass <= 0.0 {
+                    return Err(NutriplanError::InvalidInput(
+                        format!("recipe with id {} has no mass", recipe_id)))
+                }
+                mass / recipe_mass
+            },
+            _ => return Err(NutriplanError::InvalidInput(
+                format!("portion must be positive, got {:?}", portion)))
+        };
+        conn_mgr.transaction(|| {
+            let mut created = Vec::new();
+            for (iid, mass) in parts {
+                let mass = mass * factor;
+                if mass > 0.0 {
+                    let item = NewMealIngredient::new(meal_id, iid, mass);
+                    created.push(CRUDMealIngredient::create(conn_mgr, &item)?);
+                }
+            }
+            Ok(created)
+        })
+    }
+
+    /**
+     * Validate that all ingredients of a meal are visible to the given profile.
+     */
+    fn check_owner(conn_mgr: &ConnMgrPool, item_id: i32, owner: Option<i32>)
+        -> Result<(), NutriplanError>
+    {
+        for (iid, pid) in meal_ingredients::table
+            .inner_join(ingredients::table)
+            .filter(meal_ingredients::meal_id.eq(item_id))
+            .filter(ingredients::profile_id.is_not_null())
+            .select((meal_ingredients::ingredient_id, ingredients::profile_id))
+            .load::<(i32, Option<i32>)>(conn_mgr)?
+        {
+            check_visible(CRUDIngredient::ENTITY, iid, pid, owner)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Compute the nutrients of a meal from the masses of its ingredients and their macros.
+     */
+    pub fn nutrients(conn_mgr: &ConnMgrPool, item_id: i32)
+        -> Result<MealNutrients, NutriplanError>
+    {
+        if !Self::check(conn_mgr, item_id)? {
+            return Err(NutriplanError::not_found(Self::ENTITY, item_id))
+        }
+        Ok(Self::nutrients_of_meals(conn_mgr, &[item_id])?.remove(0))
+    }
+
+    /**
+     * Compute the nutrients of several distinct meals with a few queries per chunk of
+     * `MAX_BOUND_IDS` meals. The result holds one entry per given id, in the same order.
+     */
+    pub(crate) fn nutrients_of_meals(conn_mgr: &ConnMgrPool, meal_ids: &[i32])
+        -> Result<Vec<MealNutrients>, NutriplanError>
+    {
+        let mut rows: Vec<(i32, i32, String, f32)> = Vec::new();
+        for chunk in meal_ids.chunks(MAX_BOUND_IDS) {
+            rows.extend(meal_ingredients::table
+                .inner_join(ingredients::table)
+                .filter(meal_ingredients::meal_id.eq_any(chunk))
+                .order(meal_ingredients::id.asc())
+                .select((meal_ingredients::meal_id, meal_ingredients::ingredient_id,
+                    ingredients::name, meal_ingredients::mass))
+                .load::<(i32, i32, String, f32)>(conn_mgr)?);
+        }
+        let owners: Vec<i32> = rows.iter().map(|(mid, _, _, _)| *mid).collect();
+        let parts = rows
+            .into_iter()
+            .map(|(_, iid, name, mass)| (iid, name, mass))
+            .collect();
+        let mut result: Vec<MealNutrients> = meal_ids.iter().map(|mid| MealNutrients {
+            meal_id: *mid,
+            total: Nutrients::default(),
+            ingredients: Vec::new()
+        }).collect();
+        let positions: HashMap<i32, usize> = meal_ids.iter()
+            .enumerate()
+            .map(|(pos, mid)| (*mid, pos))
+            .collect();
+        for (mid, item) in owners.into_iter().zip(ingredient_nutrients(conn_mgr, parts)?) {
+            let meal = &mut result[positions[&mid]];
+            meal.total += &item.nutrients;
+            meal.ingredients.push(item);
+        }
+        Ok(result)
+    }
+
+    /**
+     * List all meals eaten between `start` and `end`, both inclusive, ordered by date and time.
+     * If a profile is given, only its meals are listed.
+     */
+    pub fn list_by_date(conn_mgr: &ConnMgrPool, start: &NaiveDate, end: &NaiveDate,
+        profile: Option<i32>) -> Result<Vec<Meal>, NutriplanError>
+    {
+        use crate::schema::meals::dsl::*;
+
+        if start > end {
+            return Err(NutriplanError::InvalidInput(
+                format!("start {} is after end {}", start, end)))
+        }
+        let mut query = meals
+            .filter(date.ge(start))
+            .filter(date.le(end))
+            .order((date.asc(), time.asc(), id.asc()))
+            .into_boxed();
+        if let Some(pid) = profile {
+            query = query.filter(profile_id.eq(pid));
+        }
+        Ok(query.load::<Meal>(conn_mgr)?)
+    }
+
+    /**
+     * List the meals of a profile, latest first.
+     */
+    pub fn list_by_profile(conn_mgr: &ConnMgrPool, pid: i32, limit: i64, offset: i64)
+        -> Result<Vec<Meal>, NutriplanError>
+    {
+        use crate::schema::meals::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(meals
+            .filter(profile_id.eq(pid))
+            .order((date.desc(), time.desc(), id.desc()))
+            .limit(limit)
+            .offset(offset)
+            .load::<Meal>(conn_mgr)?)
+    }
+
+    pub fn delete_by_profile_id(conn_mgr: &ConnMgrPool, pid: i32) -> Result<(), NutriplanError> {
+        use crate::schema::meals::dsl::*;
+
+        let ids = meals
+            .filter(profile_id.eq(pid))
+            .select(id)
+            .load::<Option<i32>>(conn_mgr)?;
+        for mid in ids.into_iter().flatten() {
+            CRUDMealIngredient::delete_by_meal_id(conn_mgr, mid)?;
+        }
+        diesel::delete(
+            meals.filter(profile_id.eq(pid)))
+            .execute(conn_mgr)?;
+        Ok(())
+    }
+}
 
 impl CRUDController for CRUDMeal {
     type NewItem = NewMeal;
     type Item = Meal;
 
-    fn create(conn_mgr: &ConnMgrPool, new_item: &NewMeal) -> bool {
-        match diesel::insert_into(meals::table)
-        .values(new_item)
-        .execute(conn_mgr) {
-            Ok(_) => true,
-            Err(e) => {
-                error!("Could not insert meal: {}", e);
-                false
-            }
-        }
+    const ENTITY: &'static str = "meal";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewMeal) -> Result<i32, NutriplanError> {
+        check_profile(conn_mgr, new_item.profile_id)?;
+        diesel::insert_into(meals::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
     }
 
-    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Option<Meal> {
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<Meal, NutriplanError> {
         use crate::schema::meals::dsl::*;
 
-        match meals
+        meals
             .filter(id.eq(item_id))
-            .load::<Meal>(conn_mgr)
-        {
-            Ok(mut entities) => {
-                if entities.len() == 0 {
-                    error!("Could not find item with id: {}", item_id);
-                    None
-                } else {
-                    Some(entities.remove(0))
-                }
-            },
-            Err(e) => {
-                error!("Could not read from database: {}", e);
-                None
-            }
-        }
+            .first::<Meal>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
     }
-    
-    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Meal) -> bool {
+
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<Meal>, NutriplanError>
+    {
         use crate::schema::meals::dsl::*;
 
-        match diesel::update(
-            meals.filter(id.eq(item_id)))
-            .set(item)
-            .execute(conn_mgr)
-            {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not update item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+        check_page(limit, offset)?;
+        Ok(meals
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Meal>(conn_mgr)?)
     }
 
-    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> bool {
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Meal) -> Result<(), NutriplanError> {
         use crate::schema::meals::dsl::*;
 
-        CRUDMealIngredient::delete_by_meal_id(conn_mgr, item_id);
-        match diesel::delete(
+        check_profile(conn_mgr, item.profile_id)?;
+        Self::check_owner(conn_mgr, item_id, item.profile_id)?;
+        let rows = diesel::update(
             meals.filter(id.eq(item_id)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+            .set((
+                name.eq(item.name),
+                date.eq(item.date),
+                time.eq(item.time),
+                profile_id.eq(item.profile_id),
+            ))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
+        use crate::schema::meals::dsl::*;
+
+        conn_mgr.transaction(|| {
+            CRUDMealIngredient::delete_by_meal_id(conn_mgr, item_id)?;
+            let rows = diesel::delete(
+                meals.filter(id.eq(item_id)))
+                .execute(conn_mgr)?;
+            Self::affected(rows, item_id)
+        })
     }
 }
 
@@ -132,8 +322,8 @@ mod test {
     fn create_accepts_ingredient_as_parameter() {
         run_db_test(|| {
             let item = NewMeal::new("created",
-                &chrono::NaiveDate::from_ymd(2020, 01, 02),
-                &chrono::NaiveTime::from_hms(9, 10, 11));
+                &chrono::NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+                &chrono::NaiveTime::from_hms_opt(9, 10, 11).unwrap());
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDMeal::create(&conn_mgr, &item);
         })
@@ -143,11 +333,23 @@ mod test {
     fn create_returns_ok_on_sane_parameters() {
         run_db_test(|| {
             let item = NewMeal::new("created",
-                &chrono::NaiveDate::from_ymd(2020, 01, 02),
-                &chrono::NaiveTime::from_hms(9, 10, 11));
+                &chrono::NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+                &chrono::NaiveTime::from_hms_opt(9, 10, 11).unwrap());
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDMeal::create(&conn_mgr, &item);
-            assert!(ret_val, "could not create meal");
+            assert!(ret_val.is_ok(), "could not create meal");
+        })
+    }
+
+    #[test]
+    fn create_returns_id_of_created_item() {
+        run_db_test(|| {
+            let item = NewMeal::new("created",
+                &chrono::NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+                &chrono::NaiveTime::from_hms_opt(9, 10, 11).unwrap());
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMeal::create(&conn_mgr, &item).unwrap();
+            assert_eq!(ret_val, 3);
         })
     }
 
@@ -157,13 +359,13 @@ mod test {
             use std::process::Command;
             use std::str;
             let item = NewMeal::new("created",
-                &chrono::NaiveDate::from_ymd(2020, 01, 02),
-                &chrono::NaiveTime::from_hms(9, 10, 11));
+                &chrono::NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+                &chrono::NaiveTime::from_hms_opt(9, 10, 11).unwrap());
             let conn_mgr = setup_conn_mgr();
-            let _ = CRUDMeal::create(&conn_mgr, &item);
+            let created_id = CRUDMeal::create(&conn_mgr, &item).unwrap();
             let output = Command::new("sqlite3")
                 .arg("test.db")
-                .arg("SELECT name FROM meals WHERE id=(select max(id) from meals);")
+                .arg(format!("SELECT name FROM meals WHERE id={};", created_id))
                 .output()
                 .expect("Failed to execute process");
             let expected = "created\n";
@@ -178,6 +380,153 @@ mod test {
             let ret_val = CRUDMeal::read(&conn_mgr, 1).unwrap();
             assert_eq!(ret_val.name, "testmeal1");
             assert_eq!(ret_val.id, Some(1));
+            assert_eq!(ret_val.date, NaiveDate::from_ymd_opt(2000, 1, 1).unwrap());
+            assert_eq!(ret_val.time, NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+        })
+    }
+
+    #[test]
+    fn list_returns_items_ordered_by_id() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDMeal::list(&conn_mgr, 10, 0).unwrap();
+            let ids: Vec<Option<i32>> = items.iter().map(|item| item.id).collect();
+            assert_eq!(ids, vec![Some(1), Some(2)]);
+        })
+    }
+
+    #[test]
+    fn nutrients_sums_ingredients_scaled_by_mass() {
+        run_db_test(|| {
+            use crate::controller::database::meal_ingredient::NewMealIngredient;
+            let conn_mgr = setup_conn_mgr();
+            let _ = CRUDMealIngredient::create(&conn_mgr, &NewMealIngredient::new(1, 2, 50.0));
+            let nutrients = CRUDMeal::nutrients(&conn_mgr, 1).unwrap();
+            assert_eq!(nutrients.ingredients.len(), 2);
+            assert_eq!(nutrients.ingredients[0].name, "test1");
+            assert!((nutrients.ingredients[0].nutrients.proteins - 1.11).abs() < 1e-4);
+            assert!((nutrients.ingredients[1].nutrients.proteins - 1.0).abs() < 1e-4);
+            assert!((nutrients.total.proteins - 2.11).abs() < 1e-4);
+            assert!((nutrients.total.calories - 2.11).abs() < 1e-4);
+        })
+    }
+
+    #[test]
+    fn nutrients_keeps_fractional_masses() {
+        run_db_test(|| {
+            use crate::controller::database::meal_ingredient::NewMealIngredient;
+            let conn_mgr = setup_conn_mgr();
+            let _ = CRUDMealIngredient::create(&conn_mgr, &NewMealIngredient::new(1, 2, 0.5));
+            let nutrients = CRUDMeal::nutrients(&conn_mgr, 1).unwrap();
+            assert_eq!(nutrients.ingredients[1].mass, 0.5);
+            assert!((nutrients.ingredients[1].nutrients.proteins - 0.01).abs() < 1e-6);
+        })
+    }
+
+    #[test]
+    fn nutrients_counts_ingredients_without_macros_as_zero() {
+        run_db_test(|| {
+            use crate::controller::database::ingredient::{CRUDIngredient, NewIngredient};
+            use crate::controller::database::meal_ingredient::NewMealIngredient;
+            let conn_mgr = setup_conn_mgr();
+            let iid = CRUDIngredient::create(&conn_mgr, &NewIngredient::new("water")).unwrap();
+            let _ = CRUDMealIngredient::create(&conn_mgr, &NewMealIngredient::new(2, iid, 300.0));
+            let nutrients = CRUDMeal::nutrients(&conn_mgr, 2).unwrap();
+            assert!(!nutrients.ingredients[1].has_macros);
+            assert_eq!(nutrients.ingredients[1].nutrients, Default::default());
+            assert!((nutrients.total.fats - 4.44).abs() < 1e-4);
+        })
+    }
+
+    #[test]
+    fn nutrients_of_meals_loads_more_meals_than_bound_per_query() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            // The stored meals follow enough unknown ones to be loaded by a later chunk
+            let mut meal_ids: Vec<i32> = (-2 * MAX_BOUND_IDS as i32..0).collect();
+            meal_ids.extend([2, 1]);
+            let nutrients = CRUDMeal::nutrients_of_meals(&conn_mgr, &meal_ids).unwrap();
+            assert_eq!(nutrients.len(), 2 * MAX_BOUND_IDS + 2);
+            assert!(nutrients[..2 * MAX_BOUND_IDS].iter().all(|meal| meal.ingredients.is_empty()));
+            assert_eq!(nutrients[2 * MAX_BOUND_IDS].meal_id, 2);
+            assert!((nutrients[2 * MAX_BOUND_IDS].total.proteins - 4.44).abs() < 1e-4);
+            assert_eq!(nutrients[2 * MAX_BOUND_IDS + 1].meal_id, 1);
+            assert!((nutrients[2 * MAX_BOUND_IDS + 1].total.proteins - 1.11).abs() < 1e-4);
+        })
+    }
+
+    #[test]
+    fn nutrients_of_unknown_meal_returns_not_found() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMeal::nutrients(&conn_mgr, 3);
+            assert!(matches!(ret_val, Err(NutriplanError::NotFound { .. })));
+        })
+    }
+
+    #[test]
+    fn list_by_date_returns_meals_within_range() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDMeal::list_by_date(&conn_mgr,
+                &NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+                &NaiveDate::from_ymd_opt(2000, 2, 1).unwrap(), None).unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].name, "testmeal1");
+        })
+    }
+
+    #[test]
+    fn list_by_date_orders_by_date_and_time() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+            let _ = CRUDMeal::create(&conn_mgr,
+                &NewMeal::new("breakfast", &date, &NaiveTime::from_hms_opt(7, 30, 0).unwrap()));
+            let _ = CRUDMeal::create(&conn_mgr,
+                &NewMeal::new("dinner", &date, &NaiveTime::from_hms_opt(19, 0, 0).unwrap()));
+            let items = CRUDMeal::list_by_date(&conn_mgr, &date, &date, None).unwrap();
+            let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+            assert_eq!(names, vec!["breakfast", "testmeal1", "dinner"]);
+        })
+    }
+
+    #[test]
+    fn list_by_date_with_profile_returns_only_its_meals() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+            let time = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+            let item = NewMeal { profile_id: Some(2), ..NewMeal::new("owned", &date, &time) };
+            let created_id = CRUDMeal::create(&conn_mgr, &item).unwrap();
+            let items = CRUDMeal::list_by_date(&conn_mgr, &date, &date, Some(2)).unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].id, Some(created_id));
+            assert!(CRUDMeal::list_by_date(&conn_mgr, &date, &date, Some(1)).unwrap().is_empty());
+            assert_eq!(CRUDMeal::list_by_date(&conn_mgr, &date, &date, None).unwrap().len(), 2);
+        })
+    }
+
+    #[test]
+    fn create_returns_foreign_key_violation_on_unknown_profile() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+            let time = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+            let item = NewMeal { profile_id: Some(3), ..NewMeal::new("owned", &date, &time) };
+            let ret_val = CRUDMeal::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
+    #[test]
+    fn list_by_date_with_reversed_range_returns_invalid_input() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMeal::list_by_date(&conn_mgr,
+                &NaiveDate::from_ymd_opt(2000, 2, 1).unwrap(),
+                &NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(), None);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
         })
     }
 
@@ -187,11 +536,11 @@ mod test {
             use std::process::Command;
             use std::str;
             let item = Meal::new(1, "updated",
-                &chrono::NaiveDate::from_ymd(2020, 12, 31),
-                &chrono::NaiveTime::from_hms(12, 13, 14));
+                &chrono::NaiveDate::from_ymd_opt(2020, 12, 31).unwrap(),
+                &chrono::NaiveTime::from_hms_opt(12, 13, 14).unwrap());
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDMeal::update(&conn_mgr, 1, item);
-            let expected = "1|updated|2020-12-31|12:13:14\n";
+            let expected = "1|updated|2020-12-31|12:13:14|\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM meals WHERE id=1;")
@@ -201,6 +550,37 @@ mod test {
         })
     }
 
+    #[test]
+    fn update_clears_profile_set_to_none() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let meal = CRUDMeal::read(&conn_mgr, 1).unwrap();
+            CRUDMeal::update(&conn_mgr, 1, Meal { profile_id: Some(1), ..meal }).unwrap();
+            let meal = CRUDMeal::read(&conn_mgr, 1).unwrap();
+            CRUDMeal::update(&conn_mgr, 1, Meal { profile_id: None, ..meal }).unwrap();
+            assert_eq!(CRUDMeal::read(&conn_mgr, 1).unwrap().profile_id, None);
+        })
+    }
+
+    #[test]
+    fn update_rejects_profile_not_seeing_private_ingredient() {
+        run_db_test(|| {
+            use crate::controller::database::ingredient::NewIngredient;
+            let conn_mgr = setup_conn_mgr();
+            let meal = CRUDMeal::read(&conn_mgr, 1).unwrap();
+            CRUDMeal::update(&conn_mgr, 1, Meal { profile_id: Some(1), ..meal }).unwrap();
+            let item = NewIngredient { profile_id: Some(1), ..NewIngredient::new("private") };
+            let iid = CRUDIngredient::create(&conn_mgr, &item).unwrap();
+            CRUDMealIngredient::create(&conn_mgr, &NewMealIngredient::new(1, iid, 10.0)).unwrap();
+            for owner in [Some(2), None] {
+                let meal = CRUDMeal::read(&conn_mgr, 1).unwrap();
+                let ret_val = CRUDMeal::update(&conn_mgr, 1, Meal { profile_id: owner, ..meal });
+                assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+            }
+            assert_eq!(CRUDMeal::read(&conn_mgr, 1).unwrap().profile_id, Some(1));
+        })
+    }
+
     #[test]
     fn delete_with_sane_id_deletes_as_expected() {
         run_db_test(|| {
@@ -208,7 +588,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDMeal::delete(&conn_mgr, 1);
-            let expected = "2|testmeal2|2000-02-02|20:00:00\n";
+            let expected = "2|testmeal2|2000-02-02|20:00:00|\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM meals;")
@@ -225,7 +605,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDMeal::delete(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM meal_ingredients;")
@@ -239,8 +619,8 @@ mod test {
     fn check_returns_true_if_ingredient_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDMeal::check(&conn_mgr, 1);
-            assert_eq!(inserted, true)
+            let inserted = CRUDMeal::check(&conn_mgr, 1).unwrap();
+            assert!(inserted)
         })
     }
 
@@ -248,9 +628,85 @@ mod test {
     fn check_returns_false_if_ingredient_not_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDMeal::check(&conn_mgr, 3);
-            assert_eq!(inserted, false)
+            let inserted = CRUDMeal::check(&conn_mgr, 3).unwrap();
+            assert!(!inserted)
+        })
+    }
+
+    #[test]
+    fn add_recipe_scales_ingredients_by_servings() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let created = CRUDMeal::add_recipe(&conn_mgr, 2, 1, RecipePortion::Servings(1.5))
+                .unwrap();
+            assert_eq!(created, vec![3]);
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT * FROM meal_ingredients WHERE meal_id=2;")
+                .output()
+                .expect("Failed to execute process");
+            let expected = "2|2|2|222.0||\n3|2|1|166.5||\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn add_recipe_scales_ingredients_by_yield_mass() {
+        run_db_test(|| {
+            use crate::controller::database::recipe::Recipe;
+            let conn_mgr = setup_conn_mgr();
+            let recipe = Recipe::with_servings(2, "testitem2", "testdescription2", 1, Some(111.0));
+            CRUDRecipe::update(&conn_mgr, 2, recipe).unwrap();
+            let _ = CRUDMeal::add_recipe(&conn_mgr, 1, 2, RecipePortion::Mass(50.0)).unwrap();
+            let nutrients = CRUDMeal::nutrients(&conn_mgr, 1).unwrap();
+            assert_eq!(nutrients.ingredients[1].ingredient_id, 2);
+            assert_eq!(nutrients.ingredients[1].mass, 100.0);
         })
     }
-}
 
+    #[test]
+    fn add_recipe_rejects_non_positive_portion() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMeal::add_recipe(&conn_mgr, 1, 1, RecipePortion::Servings(0.0));
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn add_recipe_with_unknown_recipe_is_not_found() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMeal::add_recipe(&conn_mgr, 1, 3, RecipePortion::Servings(1.0));
+            assert!(matches!(ret_val, Err(NutriplanError::NotFound { entity: "recipe", .. })));
+        })
+    }
+
+    #[test]
+    fn add_recipe_rejects_private_recipe_of_other_profile() {
+        run_db_test(|| {
+            use crate::controller::database::recipe::NewRecipe;
+            let conn_mgr = setup_conn_mgr();
+            let item = NewRecipe { profile_id: Some(2), ..NewRecipe::new("private", "secret") };
+            let rid = CRUDRecipe::create(&conn_mgr, &item).unwrap();
+            let ret_val = CRUDMeal::add_recipe(&conn_mgr, 1, rid, RecipePortion::Servings(1.0));
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_writes_date_and_time_as_iso_strings() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let item = CRUDMeal::read(&conn_mgr, 1).unwrap();
+            let json = serde_json::to_string(&item).unwrap();
+            assert_eq!(json, "{\"id\":1,\"name\":\"testmeal1\",\"date\":\"2000-01-01\",\
+                \"time\":\"08:00:00\",\"profile_id\":null}");
+            let item: Meal = serde_json::from_str(&json).unwrap();
+            assert_eq!(item.date, NaiveDate::from_ymd_opt(2000, 1, 1).unwrap());
+        })
+    }
+}
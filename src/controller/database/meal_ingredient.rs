@@ -1,73 +1,125 @@
 use diesel::{prelude::*, Queryable, Insertable};
-use log::error;
 
 use crate::{
-    controller::database::{ConnMgrPool, CRUDController},
+    controller::database::{
+        ConnMgrPool, CRUDController, check_mass, check_page, last_insert_id, read_reference
+    },
     controller::database::ingredient::CRUDIngredient,
+    controller::database::ingredient_portion::CRUDIngredientPortion,
+    controller::database::profile::check_visible,
     controller::database::meal::CRUDMeal,
+    controller::units::Quantity,
+    error::NutriplanError,
     schema::*
 };
 
 #[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="meal_ingredients"]
 pub struct NewMealIngredient {
     pub meal_id: i32,
     pub ingredient_id: i32,
-    pub mass: i32
+    pub mass: f32,
+    /** Amount as entered, in `unit` */
+    pub quantity: Option<f32>,
+    pub unit: Option<String>
 }
 
 impl NewMealIngredient {
-    pub fn new(meal_id: i32, ingredient_id: i32, mass: i32) -> Self {
-        NewMealIngredient { meal_id, ingredient_id, mass }
+    pub fn new(meal_id: i32, ingredient_id: i32, mass: f32) -> Self {
+        NewMealIngredient { meal_id, ingredient_id, mass, quantity: None, unit: None }
+    }
+
+    /**
+     * Create an entry which remembers the quantity its mass was derived from.
+     */
+    pub fn with_quantity(meal_id: i32, ingredient_id: i32, mass: f32, quantity: &Quantity)
+        -> Self
+    {
+        NewMealIngredient {
+            meal_id,
+            ingredient_id,
+            mass,
+            quantity: Some(quantity.amount),
+            unit: Some(quantity.unit.clone())
+        }
     }
 }
 
 #[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="meal_ingredients"]
 pub struct MealIngredient {
     pub id: Option<i32>,
     pub meal_id: i32,
     pub ingredient_id: i32,
-    pub mass: i32
+    pub mass: f32,
+    /** Amount as entered, in `unit` */
+    pub quantity: Option<f32>,
+    pub unit: Option<String>
 }
 
 impl MealIngredient {
-    pub fn new(id: i32, meal_id: i32, ingredient_id: i32, mass: i32) -> Self {
-        MealIngredient { id: Some(id), meal_id, ingredient_id, mass }
+    pub fn new(id: i32, meal_id: i32, ingredient_id: i32, mass: f32) -> Self {
+        MealIngredient { id: Some(id), meal_id, ingredient_id, mass, quantity: None, unit: None }
+    }
+
+    /**
+     * Quantity the mass was derived from, if it was not entered in gram directly.
+     */
+    pub fn quantity(&self) -> Option<Quantity> {
+        match (self.quantity, &self.unit) {
+            (Some(amount), Some(unit)) => Some(Quantity::new(amount, unit)),
+            _ => None
+        }
     }
 }
 
 pub struct CRUDMealIngredient { }
 
 impl CRUDMealIngredient {
-    pub fn delete_by_ingredient_id(conn_mgr: &ConnMgrPool, iid: i32) -> bool {
+    /**
+     * Create an entry from a quantity in any unit known for the ingredient, see
+     * `CRUDIngredientPortion::to_grams`.
+     */
+    pub fn create_from_quantity(conn_mgr: &ConnMgrPool, meal_id: i32, ingredient_id: i32,
+        quantity: &Quantity) -> Result<i32, NutriplanError>
+    {
+        let mass = CRUDIngredientPortion::to_grams(conn_mgr, ingredient_id, quantity)?;
+        let item = NewMealIngredient::with_quantity(meal_id, ingredient_id, mass, quantity);
+        Self::create(conn_mgr, &item)
+    }
+
+    /**
+     * Validate that the meal and the ingredient exist and that the ingredient is visible to the
+     * profile of the meal.
+     */
+    fn check_references(conn_mgr: &ConnMgrPool, mid: i32, iid: i32) -> Result<(), NutriplanError> {
+        let meal = read_reference::<CRUDMeal>(conn_mgr, mid)?;
+        let ingredient = read_reference::<CRUDIngredient>(conn_mgr, iid)?;
+        check_visible(CRUDIngredient::ENTITY, iid, ingredient.profile_id, meal.profile_id)
+    }
+
+    pub fn delete_by_ingredient_id(conn_mgr: &ConnMgrPool, iid: i32)
+        -> Result<(), NutriplanError>
+    {
         use crate::schema::meal_ingredients::dsl::*;
 
-        match diesel::delete(
+        diesel::delete(
             meal_ingredients.filter(ingredient_id.eq(iid)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with ingredient id {}: {}", iid, e);
-                    false
-                }
-            }
-
+            .execute(conn_mgr)?;
+        Ok(())
     }
-    
-    pub fn delete_by_meal_id(conn_mgr: &ConnMgrPool, mid: i32) -> bool {
+
+    pub fn delete_by_meal_id(conn_mgr: &ConnMgrPool, mid: i32)
+        -> Result<(), NutriplanError>
+    {
         use crate::schema::meal_ingredients::dsl::*;
 
-        match diesel::delete(
+        diesel::delete(
             meal_ingredients.filter(meal_id.eq(mid)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with meal id {}: {}", mid, e);
-                    false
-                }
-            }
-
+            .execute(conn_mgr)?;
+        Ok(())
     }
 }
 
@@ -75,75 +127,68 @@ impl CRUDController for CRUDMealIngredient {
     type NewItem = NewMealIngredient;
     type Item = MealIngredient;
 
-    fn create(conn_mgr: &ConnMgrPool, new_item: &NewMealIngredient) -> bool {
-        let meal_avail = CRUDMeal::check(conn_mgr, new_item.meal_id);
-        let ingredient_avail = CRUDIngredient::check(conn_mgr, new_item.ingredient_id);
-        match meal_avail & ingredient_avail {
-            true => (),
-            false => return false
-        }
+    const ENTITY: &'static str = "meal ingredient";
 
-        match diesel::insert_into(meal_ingredients::table)
-        .values(new_item)
-        .execute(conn_mgr) {
-            Ok(_) => true,
-            Err(e) => {
-                error!("Could not insert meal: {}", e);
-                false
-            }
-        }
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewMealIngredient) -> Result<i32, NutriplanError> {
+        check_mass(new_item.mass)?;
+        Self::check_references(conn_mgr, new_item.meal_id, new_item.ingredient_id)?;
+
+        diesel::insert_into(meal_ingredients::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
     }
 
-    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Option<MealIngredient> {
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<MealIngredient, NutriplanError> {
         use crate::schema::meal_ingredients::dsl::*;
 
-        match meal_ingredients
+        meal_ingredients
             .filter(id.eq(item_id))
-            .load::<MealIngredient>(conn_mgr)
-        {
-            Ok(mut entities) => {
-                if entities.len() == 0 {
-                    error!("Could not find item with id: {}", item_id);
-                    None
-                } else {
-                    Some(entities.remove(0))
-                }
-            },
-            Err(e) => {
-                error!("Could not read from database: {}", e);
-                None
-            }
-        }
+            .first::<MealIngredient>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
     }
 
-    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: MealIngredient) -> bool {
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<MealIngredient>, NutriplanError>
+    {
         use crate::schema::meal_ingredients::dsl::*;
 
-        match diesel::update(
+        check_page(limit, offset)?;
+        Ok(meal_ingredients
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<MealIngredient>(conn_mgr)?)
+    }
+
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: MealIngredient)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::meal_ingredients::dsl::*;
+
+        check_mass(item.mass)?;
+        Self::check_references(conn_mgr, item.meal_id, item.ingredient_id)?;
+        let rows = diesel::update(
             meal_ingredients.filter(id.eq(item_id)))
-            .set(item)
-            .execute(conn_mgr)
-            {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not update item with id {}: {}", item_id, e);
-                    false
-                }
-            }
-    }
-
-    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> bool {
+            .set((
+                meal_id.eq(item.meal_id),
+                ingredient_id.eq(item.ingredient_id),
+                mass.eq(item.mass),
+                quantity.eq(item.quantity),
+                unit.eq(item.unit),
+            ))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
         use crate::schema::meal_ingredients::dsl::*;
 
-        match diesel::delete(
+        let rows = diesel::delete(
             meal_ingredients.filter(id.eq(item_id)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
     }
 }
 
@@ -156,7 +201,7 @@ mod test {
     #[test]
     fn create_accepts_item_as_parameter() {
         run_db_test(|| {
-            let item = NewMealIngredient::new(1, 1, 123);
+            let item = NewMealIngredient::new(1, 1, 123.0);
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDMealIngredient::create(&conn_mgr, &item);
         })
@@ -165,10 +210,20 @@ mod test {
     #[test]
     fn create_returns_ok_on_sane_parameters() {
         run_db_test(|| {
-            let item = NewMealIngredient::new(1, 1, 123);
+            let item = NewMealIngredient::new(1, 1, 123.0);
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDMealIngredient::create(&conn_mgr, &item);
-            assert!(ret_val, "could not create item");
+            assert!(ret_val.is_ok(), "could not create item");
+        })
+    }
+
+    #[test]
+    fn create_returns_id_of_created_item() {
+        run_db_test(|| {
+            let item = NewMealIngredient::new(1, 1, 123.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMealIngredient::create(&conn_mgr, &item).unwrap();
+            assert_eq!(ret_val, 3);
         })
     }
 
@@ -177,36 +232,63 @@ mod test {
         run_db_test(|| {
             use std::process::Command;
             use std::str;
-            let item = NewMealIngredient::new(1, 1, 123);
+            let item = NewMealIngredient::new(1, 1, 123.0);
             let conn_mgr = setup_conn_mgr();
-            let _ = CRUDMealIngredient::create(&conn_mgr, &item);
+            let created_id = CRUDMealIngredient::create(&conn_mgr, &item).unwrap();
             let output = Command::new("sqlite3")
                 .arg("test.db")
-                .arg("SELECT * FROM meal_ingredients WHERE id=(select max(id) from meal_ingredients);")
+                .arg(format!("SELECT * FROM meal_ingredients WHERE id={};", created_id))
                 .output()
                 .expect("Failed to execute process");
-            let expected = "3|1|1|123\n";
+            let expected = "3|1|1|123.0||\n";
             assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
         })
     }
 
     #[test]
-    fn create_returns_false_on_unknown_meal() {
+    fn create_returns_foreign_key_violation_on_unknown_meal() {
+        run_db_test(|| {
+            let item = NewMealIngredient::new(3, 1, 123.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMealIngredient::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
+    #[test]
+    fn create_returns_foreign_key_violation_on_unknown_ingredient() {
         run_db_test(|| {
-            let item = NewMealIngredient::new(3, 1, 123);
+            let item = NewMealIngredient::new(1, 3, 123.0);
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDMealIngredient::create(&conn_mgr, &item);
-            assert_eq!(ret_val, false);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
         })
     }
 
     #[test]
-    fn create_returns_false_on_unknown_ingredient() {
+    fn create_rejects_negative_mass() {
         run_db_test(|| {
-            let item = NewMealIngredient::new(1, 3, 123);
+            let item = NewMealIngredient::new(1, 1, -0.5);
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDMealIngredient::create(&conn_mgr, &item);
-            assert_eq!(ret_val, false);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn create_rejects_private_ingredient_of_other_profile() {
+        run_db_test(|| {
+            use crate::controller::database::ingredient::NewIngredient;
+            use crate::controller::database::meal::Meal;
+            let conn_mgr = setup_conn_mgr();
+            let item = NewIngredient { profile_id: Some(2), ..NewIngredient::new("private") };
+            let iid = CRUDIngredient::create(&conn_mgr, &item).unwrap();
+            let entry = NewMealIngredient::new(1, iid, 10.0);
+            let ret_val = CRUDMealIngredient::create(&conn_mgr, &entry);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+            let meal = CRUDMeal::read(&conn_mgr, 1).unwrap();
+            CRUDMeal::update(&conn_mgr, 1, Meal { profile_id: Some(2), ..meal }).unwrap();
+            assert!(CRUDMealIngredient::create(&conn_mgr, &entry).is_ok());
         })
     }
 
@@ -218,7 +300,17 @@ mod test {
             assert_eq!(ret_val.id, Some(1));
             assert_eq!(ret_val.meal_id, 1);
             assert_eq!(ret_val.ingredient_id, 1);
-            assert_eq!(ret_val.mass, 111);
+            assert_eq!(ret_val.mass, 111.0);
+        })
+    }
+
+    #[test]
+    fn list_returns_items_ordered_by_id() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDMealIngredient::list(&conn_mgr, 10, 0).unwrap();
+            let ids: Vec<Option<i32>> = items.iter().map(|item| item.id).collect();
+            assert_eq!(ids, vec![Some(1), Some(2)]);
         })
     }
 
@@ -227,10 +319,10 @@ mod test {
         run_db_test(|| {
             use std::process::Command;
             use std::str;
-            let item = MealIngredient::new(1, 2, 3, 456);
+            let item = MealIngredient::new(1, 2, 2, 456.0);
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDMealIngredient::update(&conn_mgr, 1, item);
-            let expected = "1|2|3|456\n";
+            let expected = "1|2|2|456.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM meal_ingredients WHERE id=1;")
@@ -240,6 +332,48 @@ mod test {
         })
     }
 
+    #[test]
+    fn update_clears_quantity_set_to_none() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let quantity = Quantity::parse("1.5 slices").unwrap();
+            let created_id = CRUDMealIngredient::create_from_quantity(&conn_mgr, 2, 1, &quantity)
+                .unwrap();
+            let item = MealIngredient::new(created_id, 2, 1, 50.0);
+            CRUDMealIngredient::update(&conn_mgr, created_id, item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM meal_ingredients WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            let expected = "3|2|1|50.0||\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+            assert_eq!(CRUDMealIngredient::read(&conn_mgr, created_id).unwrap().quantity(), None);
+        })
+    }
+
+    #[test]
+    fn update_returns_foreign_key_violation_on_unknown_meal() {
+        run_db_test(|| {
+            let item = MealIngredient::new(1, 3, 1, 456.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMealIngredient::update(&conn_mgr, 1, item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
+    #[test]
+    fn update_returns_foreign_key_violation_on_unknown_ingredient() {
+        run_db_test(|| {
+            let item = MealIngredient::new(1, 1, 3, 456.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMealIngredient::update(&conn_mgr, 1, item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
     #[test]
     fn delete_by_item_id_with_sane_id_deletes_as_expected() {
         run_db_test(|| {
@@ -247,7 +381,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDMealIngredient::delete(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM meal_ingredients;")
@@ -264,7 +398,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDMealIngredient::delete_by_ingredient_id(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM meal_ingredients;")
@@ -281,7 +415,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDMealIngredient::delete_by_meal_id(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM meal_ingredients;")
@@ -292,11 +426,11 @@ mod test {
     }
 
     #[test]
-    fn delete_by_meal_id_with_sane_id_returns_true() {
+    fn delete_by_meal_id_with_sane_id_returns_ok() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDMealIngredient::delete_by_meal_id(&conn_mgr, 1);
-            assert_eq!(ret_val, true);
+            assert!(ret_val.is_ok());
         })
     }
 
@@ -304,8 +438,8 @@ mod test {
     fn check_returns_true_if_ingredient_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDMealIngredient::check(&conn_mgr, 1);
-            assert_eq!(inserted, true)
+            let inserted = CRUDMealIngredient::check(&conn_mgr, 1).unwrap();
+            assert!(inserted)
         })
     }
 
@@ -313,9 +447,29 @@ mod test {
     fn check_returns_false_if_ingredient_not_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDMealIngredient::check(&conn_mgr, 3);
-            assert_eq!(inserted, false)
+            let inserted = CRUDMealIngredient::check(&conn_mgr, 3).unwrap();
+            assert!(!inserted)
         })
     }
-}
 
+    #[test]
+    fn create_from_quantity_stores_mass_and_quantity() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let quantity = Quantity::parse("1.5 slices").unwrap();
+            let created_id = CRUDMealIngredient::create_from_quantity(&conn_mgr, 2, 1, &quantity)
+                .unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM meal_ingredients WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            let expected = "3|2|1|45.0|1.5|slices\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+            let item = CRUDMealIngredient::read(&conn_mgr, created_id).unwrap();
+            assert_eq!(item.quantity(), Some(quantity));
+        })
+    }
+}
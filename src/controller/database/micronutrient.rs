@@ -0,0 +1,189 @@
+use diesel::{prelude::*, Queryable, Insertable};
+
+use crate::{
+    controller::database::{ConnMgrPool, CRUDController, check_page, last_insert_id},
+    controller::database::ingredient_micro::CRUDIngredientMicro,
+    error::NutriplanError,
+    schema::*
+};
+
+/**
+ * Definition of a nutrient besides the macros, e.g. sugars or vitamin C. The code is a stable
+ * identifier used to refer to the nutrient, amounts are stored in `unit` per 100 g.
+ */
+#[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="micronutrients"]
+pub struct NewMicronutrient {
+    pub code: String,
+    pub name: String,
+    pub unit: String
+}
+
+impl NewMicronutrient {
+    pub fn new(code: &str, name: &str, unit: &str) -> Self {
+        NewMicronutrient { code: code.to_owned(), name: name.to_owned(), unit: unit.to_owned() }
+    }
+}
+
+#[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="micronutrients"]
+pub struct Micronutrient {
+    pub id: Option<i32>,
+    pub code: String,
+    pub name: String,
+    pub unit: String
+}
+
+impl Micronutrient {
+    pub fn new(id: i32, code: &str, name: &str, unit: &str) -> Self {
+        Micronutrient {
+            id: Some(id),
+            code: code.to_owned(),
+            name: name.to_owned(),
+            unit: unit.to_owned()
+        }
+    }
+}
+
+pub struct CRUDMicronutrient { }
+
+impl CRUDMicronutrient {
+    pub fn find_by_code(conn_mgr: &ConnMgrPool, item_code: &str)
+        -> Result<Option<Micronutrient>, NutriplanError>
+    {
+        use crate::schema::micronutrients::dsl::*;
+
+        Ok(micronutrients
+            .filter(code.eq(item_code))
+            .first::<Micronutrient>(conn_mgr)
+            .optional()?)
+    }
+}
+
+impl CRUDController for CRUDMicronutrient {
+    type NewItem = NewMicronutrient;
+    type Item = Micronutrient;
+
+    const ENTITY: &'static str = "micronutrient";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewMicronutrient)
+        -> Result<i32, NutriplanError>
+    {
+        diesel::insert_into(micronutrients::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
+    }
+
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<Micronutrient, NutriplanError> {
+        use crate::schema::micronutrients::dsl::*;
+
+        micronutrients
+            .filter(id.eq(item_id))
+            .first::<Micronutrient>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
+    }
+
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<Micronutrient>, NutriplanError>
+    {
+        use crate::schema::micronutrients::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(micronutrients
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Micronutrient>(conn_mgr)?)
+    }
+
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Micronutrient)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::micronutrients::dsl::*;
+
+        let rows = diesel::update(
+            micronutrients.filter(id.eq(item_id)))
+            .set(item)
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
+        use crate::schema::micronutrients::dsl::*;
+
+        conn_mgr.transaction(|| {
+            CRUDIngredientMicro::delete_by_micronutrient_id(conn_mgr, item_id)?;
+            let rows = diesel::delete(
+                micronutrients.filter(id.eq(item_id)))
+                .execute(conn_mgr)?;
+            Self::affected(rows, item_id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    #[test]
+    fn create_creates_item_correct_parameters() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let item = NewMicronutrient::new("vitamin_c", "Vitamin C", "mg");
+            let conn_mgr = setup_conn_mgr();
+            let created_id = CRUDMicronutrient::create(&conn_mgr, &item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM micronutrients WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            let expected = "5|vitamin_c|Vitamin C|mg\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn create_rejects_duplicate_code() {
+        run_db_test(|| {
+            let item = NewMicronutrient::new("sugars", "Sugar", "g");
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDMicronutrient::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::ConstraintViolation(_))));
+        })
+    }
+
+    #[test]
+    fn find_by_code_returns_matching_item() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let item = CRUDMicronutrient::find_by_code(&conn_mgr, "salt").unwrap().unwrap();
+            assert_eq!(item.id, Some(3));
+            assert_eq!(item.unit, "g");
+            assert!(CRUDMicronutrient::find_by_code(&conn_mgr, "unknown").unwrap().is_none());
+        })
+    }
+
+    #[test]
+    fn delete_also_removes_ingredient_micro_entries() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            CRUDMicronutrient::delete(&conn_mgr, 1).unwrap();
+            let expected = "2|2|4|400.0\n";
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT * FROM ingredient_micros;")
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+}
@@ -0,0 +1,243 @@
+use diesel::{prelude::*, Queryable, Insertable};
+
+use crate::{
+    controller::database::{ConnMgrPool, CRUDController, check_page, last_insert_id},
+    controller::database::goal::CRUDGoal,
+    controller::database::ingredient::CRUDIngredient,
+    controller::database::meal::CRUDMeal,
+    controller::database::recipe::CRUDRecipe,
+    error::NutriplanError,
+    schema::*
+};
+
+/**
+ * A person sharing the database. Meals and goals belong to a profile, ingredients and recipes
+ * are either private to a profile or shared if they have none.
+ */
+#[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="profiles"]
+pub struct NewProfile {
+    pub name: String
+}
+
+impl NewProfile {
+    pub fn new(name: &str) -> Self {
+        NewProfile { name: name.to_owned() }
+    }
+}
+
+#[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="profiles"]
+pub struct Profile {
+    pub id: Option<i32>,
+    pub name: String
+}
+
+impl Profile {
+    pub fn new(id: i32, name: &str) -> Self {
+        Profile { id: Some(id), name: name.to_owned() }
+    }
+}
+
+/**
+ * Validate the owner of an item, no owner is always valid.
+ */
+pub(crate) fn check_profile(conn_mgr: &ConnMgrPool, profile_id: Option<i32>)
+    -> Result<(), NutriplanError>
+{
+    match profile_id {
+        Some(pid) if !CRUDProfile::check(conn_mgr, pid)? => {
+            Err(NutriplanError::missing_reference(CRUDProfile::ENTITY, pid))
+        },
+        _ => Ok(())
+    }
+}
+
+/**
+ * Validate that an item owned by `item_profile` may be used by an item owned by `owner`. Shared
+ * items may be used by all items, private items only by items of the same profile.
+ */
+pub(crate) fn check_visible(entity: &'static str, item_id: i32, item_profile: Option<i32>,
+    owner: Option<i32>) -> Result<(), NutriplanError>
+{
+    match item_profile {
+        Some(_) if item_profile != owner => Err(NutriplanError::private_reference(entity, item_id)),
+        _ => Ok(())
+    }
+}
+
+pub struct CRUDProfile { }
+
+impl CRUDProfile {
+    pub fn find_by_name(conn_mgr: &ConnMgrPool, item_name: &str)
+        -> Result<Option<Profile>, NutriplanError>
+    {
+        use crate::schema::profiles::dsl::*;
+
+        Ok(profiles
+            .filter(name.eq(item_name))
+            .first::<Profile>(conn_mgr)
+            .optional()?)
+    }
+}
+
+impl CRUDController for CRUDProfile {
+    type NewItem = NewProfile;
+    type Item = Profile;
+
+    const ENTITY: &'static str = "profile";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewProfile) -> Result<i32, NutriplanError> {
+        diesel::insert_into(profiles::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
+    }
+
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<Profile, NutriplanError> {
+        use crate::schema::profiles::dsl::*;
+
+        profiles
+            .filter(id.eq(item_id))
+            .first::<Profile>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
+    }
+
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<Profile>, NutriplanError>
+    {
+        use crate::schema::profiles::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(profiles
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Profile>(conn_mgr)?)
+    }
+
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Profile) -> Result<(), NutriplanError> {
+        use crate::schema::profiles::dsl::*;
+
+        let rows = diesel::update(
+            profiles.filter(id.eq(item_id)))
+            .set(item)
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    /**
+     * Delete a profile along with its meals and goals. Its ingredients and recipes may be used by
+     * others, so they are kept and become shared.
+     */
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
+        use crate::schema::profiles::dsl::*;
+
+        conn_mgr.transaction(|| {
+            CRUDMeal::delete_by_profile_id(conn_mgr, item_id)?;
+            CRUDGoal::delete_by_profile_id(conn_mgr, item_id)?;
+            CRUDIngredient::share_by_profile_id(conn_mgr, item_id)?;
+            CRUDRecipe::share_by_profile_id(conn_mgr, item_id)?;
+            let rows = diesel::delete(
+                profiles.filter(id.eq(item_id)))
+                .execute(conn_mgr)?;
+            Self::affected(rows, item_id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    #[test]
+    fn create_creates_item_correct_parameters() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let created_id = CRUDProfile::create(&conn_mgr, &NewProfile::new("guest")).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM profiles WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("3|guest\n", str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn create_rejects_duplicate_name() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDProfile::create(&conn_mgr, &NewProfile::new("testprofile1"));
+            assert!(matches!(ret_val, Err(NutriplanError::ConstraintViolation(_))));
+        })
+    }
+
+    #[test]
+    fn find_by_name_returns_matching_item() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let item = CRUDProfile::find_by_name(&conn_mgr, "testprofile2").unwrap().unwrap();
+            assert_eq!(item.id, Some(2));
+            assert!(CRUDProfile::find_by_name(&conn_mgr, "unknown").unwrap().is_none());
+        })
+    }
+
+    #[test]
+    fn delete_removes_meals_and_shares_recipes() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            use crate::controller::database::meal::Meal;
+            use crate::controller::database::recipe::Recipe;
+            let conn_mgr = setup_conn_mgr();
+            let meal = CRUDMeal::read(&conn_mgr, 1).unwrap();
+            CRUDMeal::update(&conn_mgr, 1, Meal { profile_id: Some(1), ..meal }).unwrap();
+            let recipe = CRUDRecipe::read(&conn_mgr, 1).unwrap();
+            CRUDRecipe::update(&conn_mgr, 1, Recipe { profile_id: Some(1), ..recipe }).unwrap();
+            CRUDProfile::delete(&conn_mgr, 1).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT id FROM meals; SELECT meal_id FROM meal_ingredients; \
+                    SELECT id, profile_id FROM recipes;")
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("2\n2\n1|\n2|\n", str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+    #[test]
+    fn delete_keeps_meals_and_owned_recipes_if_deleting_profile_fails() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            use crate::controller::database::meal::Meal;
+            use crate::controller::database::recipe::Recipe;
+            let conn_mgr = setup_conn_mgr();
+            let meal = CRUDMeal::read(&conn_mgr, 1).unwrap();
+            CRUDMeal::update(&conn_mgr, 1, Meal { profile_id: Some(1), ..meal }).unwrap();
+            let recipe = CRUDRecipe::read(&conn_mgr, 1).unwrap();
+            CRUDRecipe::update(&conn_mgr, 1, Recipe { profile_id: Some(1), ..recipe }).unwrap();
+            Command::new("sqlite3")
+                .arg("test.db")
+                .arg("CREATE TRIGGER block_delete BEFORE DELETE ON profiles \
+                    BEGIN SELECT RAISE(ABORT, 'blocked'); END;")
+                .output()
+                .expect("Failed to execute process");
+            assert!(CRUDProfile::delete(&conn_mgr, 1).is_err());
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT id FROM meals; SELECT meal_id FROM meal_ingredients; \
+                    SELECT id, profile_id FROM recipes;")
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("1\n2\n1\n2\n1|1\n2|\n", str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+}
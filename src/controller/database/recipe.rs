@@ -1,107 +1,351 @@
 use diesel::{prelude::*, Queryable, Insertable};
-use log::error;
 
 use crate::{
-    controller::database::{ConnMgrPool, CRUDController},
+    controller::database::{ConnMgrPool, CRUDController, check_page, last_insert_id},
+    controller::database::ingredient::CRUDIngredient,
+    controller::database::profile::{check_profile, check_visible},
+    controller::database::recipe_component::CRUDRecipeComponent,
     controller::database::recipe_ingredient::CRUDRecipeIngredient,
+    controller::nutrition::{ingredient_nutrients, ComponentNutrients, Nutrients, RecipeNutrients},
+    error::NutriplanError,
     schema::*
 };
 
 #[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="recipes"]
 pub struct NewRecipe {
     pub name: String,
-    pub description: String
+    pub description: String,
+    pub servings: i32,
+    /** Mass in gram of the finished recipe, if it differs from the sum of its ingredients */
+    pub yield_mass: Option<f32>,
+    /** Owner of a private recipe, shared with all profiles if `None` */
+    pub profile_id: Option<i32>
 }
 
 impl NewRecipe {
     pub fn new(name: &str, description: &str) -> Self {
-        NewRecipe { name: name.to_owned(), description: description.to_owned() }
+        NewRecipe::with_servings(name, description, 1, None)
+    }
+
+    pub fn with_servings(name: &str, description: &str, servings: i32, yield_mass: Option<f32>)
+        -> Self
+    {
+        NewRecipe {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            servings,
+            yield_mass,
+            profile_id: None
+        }
     }
 }
 
 #[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="recipes"]
 pub struct Recipe {
     pub id: Option<i32>,
     pub name: String,
-    pub description: String
+    pub description: String,
+    pub servings: i32,
+    /** Mass in gram of the finished recipe, if it differs from the sum of its ingredients */
+    pub yield_mass: Option<f32>,
+    /** Owner of a private recipe, shared with all profiles if `None` */
+    pub profile_id: Option<i32>
 }
 
 impl Recipe {
     pub fn new(id: i32, name: &str, description: &str) -> Self {
-        Recipe { id: Some(id), name: name.to_owned(), description: description.to_owned() }
+        Recipe::with_servings(id, name, description, 1, None)
+    }
+
+    pub fn with_servings(id: i32, name: &str, description: &str, servings: i32,
+        yield_mass: Option<f32>) -> Self
+    {
+        Recipe {
+            id: Some(id),
+            name: name.to_owned(),
+            description: description.to_owned(),
+            servings,
+            yield_mass,
+            profile_id: None
+        }
+    }
+}
+
+fn check_yield(servings: i32, yield_mass: Option<f32>) -> Result<(), NutriplanError> {
+    if servings < 1 {
+        return Err(NutriplanError::InvalidInput(
+            format!("servings must be at least 1, got {}", servings)))
+    }
+    match yield_mass {
+        Some(mass) if !(mass > 0.0 && mass.is_finite()) => Err(NutriplanError::InvalidInput(
+            format!("yield mass must be positive, got {}", mass))),
+        _ => Ok(())
     }
 }
 
 pub struct CRUDRecipe { }
 
+impl CRUDRecipe {
+    /**
+     * List the recipes visible to a profile, i.e. its private and all shared recipes.
+     */
+    pub fn list_by_profile(conn_mgr: &ConnMgrPool, pid: i32, limit: i64, offset: i64)
+        -> Result<Vec<Recipe>, NutriplanError>
+    {
+        use crate::schema::recipes::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(recipes
+            .filter(profile_id.is_null().or(profile_id.eq(pid)))
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Recipe>(conn_mgr)?)
+    }
+
+    /**
+     * Validate that all ingredients and components of a recipe are visible to the given profile
+     * and that all recipes using it as component may still do so.
+     */
+    fn check_owner(conn_mgr: &ConnMgrPool, item_id: i32, owner: Option<i32>)
+        -> Result<(), NutriplanError>
+    {
+        for (iid, pid) in recipe_ingredients::table
+            .inner_join(ingredients::table)
+            .filter(recipe_ingredients::recipe_id.eq(item_id))
+            .filter(ingredients::profile_id.is_not_null())
+            .select((recipe_ingredients::ingredient_id, ingredients::profile_id))
+            .load::<(i32, Option<i32>)>(conn_mgr)?
+        {
+            check_visible(CRUDIngredient::ENTITY, iid, pid, owner)?;
+        }
+        let components = recipe_components::table
+            .filter(recipe_components::recipe_id.eq(item_id))
+            .select(recipe_components::component_id.nullable());
+        for (cid, pid) in recipes::table
+            .filter(recipes::id.eq_any(components))
+            .filter(recipes::profile_id.is_not_null())
+            .select((recipes::id, recipes::profile_id))
+            .load::<(Option<i32>, Option<i32>)>(conn_mgr)?
+        {
+            check_visible(Self::ENTITY, cid.unwrap_or_default(), pid, owner)?;
+        }
+        let parents = recipe_components::table
+            .filter(recipe_components::component_id.eq(item_id))
+            .select(recipe_components::recipe_id.nullable());
+        for pid in recipes::table
+            .filter(recipes::id.eq_any(parents))
+            .select(recipes::profile_id)
+            .load::<Option<i32>>(conn_mgr)?
+        {
+            check_visible(Self::ENTITY, item_id, owner, pid)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Make the private recipes of a profile shared.
+     */
+    pub fn share_by_profile_id(conn_mgr: &ConnMgrPool, pid: i32) -> Result<(), NutriplanError> {
+        use crate::schema::recipes::dsl::*;
+
+        diesel::update(
+            recipes.filter(profile_id.eq(pid)))
+            .set(profile_id.eq(None::<i32>))
+            .execute(conn_mgr)?;
+        Ok(())
+    }
+
+    /**
+     * Compute the nutrients of a recipe in total, per serving and per 100 g of the finished dish.
+     * Component recipes are resolved recursively and scaled by the mass they are used with.
+     *
+     * The mass of the finished dish is the yield mass if one is stored, otherwise the sum of the
+     * masses of all ingredients and components.
+     */
+    pub fn nutrients(conn_mgr: &ConnMgrPool, item_id: i32)
+        -> Result<RecipeNutrients, NutriplanError>
+    {
+        Ok(Self::nutrients_inner(conn_mgr, item_id, &mut Vec::new())?.1)
+    }
+
+    fn nutrients_inner(conn_mgr: &ConnMgrPool, item_id: i32, parents: &mut Vec<i32>)
+        -> Result<(Recipe, RecipeNutrients), NutriplanError>
+    {
+        let recipe = Self::read(conn_mgr, item_id)?;
+        Self::enter(parents, item_id)?;
+        let parts = recipe_ingredients::table
+            .inner_join(ingredients::table)
+            .filter(recipe_ingredients::recipe_id.eq(item_id))
+            .order(recipe_ingredients::id.asc())
+            .select((recipe_ingredients::ingredient_id, ingredients::name,
+                recipe_ingredients::mass))
+            .load::<(i32, String, f32)>(conn_mgr)?;
+        let ingredients = ingredient_nutrients(conn_mgr, parts)?;
+        let mut components = Vec::new();
+        for (cid, mass) in Self::components_of(conn_mgr, item_id)? {
+            let (component, nutrients) = Self::nutrients_inner(conn_mgr, cid, parents)?;
+            components.push(ComponentNutrients {
+                recipe_id: cid,
+                name: component.name,
+                mass,
+                nutrients: nutrients.scaled_to(mass)
+            });
+        }
+        parents.pop();
+        let total: Nutrients = ingredients.iter().map(|item| &item.nutrients)
+            .chain(components.iter().map(|item| &item.nutrients))
+            .cloned()
+            .sum();
+        let mass = match recipe.yield_mass {
+            Some(mass) => mass,
+            None => ingredients.iter().map(|item| item.mass)
+                .chain(components.iter().map(|item| item.mass))
+                .sum()
+        };
+        let nutrients = RecipeNutrients {
+            recipe_id: item_id,
+            servings: recipe.servings,
+            mass,
+            per_serving: total.scaled(1.0 / recipe.servings as f32),
+            per_100g: if mass > 0.0 { total.scaled(100.0 / mass) } else { Nutrients::default() },
+            total,
+            ingredients,
+            components
+        };
+        Ok((recipe, nutrients))
+    }
+
+    /**
+     * Resolve a recipe into raw ingredients. Component recipes are expanded recursively, their
+     * ingredients are scaled by the mass they are used with.
+     *
+     * Returns the mass of the finished dish and the `(ingredient id, mass)` entries.
+     */
+    pub(crate) fn expand(conn_mgr: &ConnMgrPool, item_id: i32)
+        -> Result<(f32, Vec<(i32, f32)>), NutriplanError>
+    {
+        Self::expand_inner(conn_mgr, item_id, &mut Vec::new())
+    }
+
+    fn expand_inner(conn_mgr: &ConnMgrPool, item_id: i32, parents: &mut Vec<i32>)
+        -> Result<(f32, Vec<(i32, f32)>), NutriplanError>
+    {
+        let recipe = Self::read(conn_mgr, item_id)?;
+        Self::enter(parents, item_id)?;
+        let mut parts: Vec<(i32, f32)> = recipe_ingredients::table
+            .filter(recipe_ingredients::recipe_id.eq(item_id))
+            .order(recipe_ingredients::id.asc())
+            .select((recipe_ingredients::ingredient_id, recipe_ingredients::mass))
+            .load::<(i32, f32)>(conn_mgr)?;
+        let mut mass: f32 = parts.iter().map(|(_, mass)| mass).sum();
+        for (cid, component_mass) in Self::components_of(conn_mgr, item_id)? {
+            let (total, component_parts) = Self::expand_inner(conn_mgr, cid, parents)?;
+            mass += component_mass;
+            if total > 0.0 {
+                let factor = component_mass / total;
+                parts.extend(component_parts.into_iter().map(|(iid, mass)| (iid, mass * factor)));
+            }
+        }
+        parents.pop();
+        Ok((recipe.yield_mass.unwrap_or(mass), parts))
+    }
+
+    fn components_of(conn_mgr: &ConnMgrPool, item_id: i32)
+        -> Result<Vec<(i32, f32)>, NutriplanError>
+    {
+        Ok(recipe_components::table
+            .filter(recipe_components::recipe_id.eq(item_id))
+            .order(recipe_components::id.asc())
+            .select((recipe_components::component_id, recipe_components::mass))
+            .load::<(i32, f32)>(conn_mgr)?)
+    }
+
+    /**
+     * Guard against cycles which were stored bypassing `CRUDRecipeComponent`.
+     */
+    fn enter(parents: &mut Vec<i32>, item_id: i32) -> Result<(), NutriplanError> {
+        if parents.contains(&item_id) {
+            return Err(NutriplanError::InvalidInput(
+                format!("recipe with id {} contains itself", item_id)))
+        }
+        parents.push(item_id);
+        Ok(())
+    }
+}
+
 impl CRUDController for CRUDRecipe {
     type NewItem = NewRecipe;
     type Item = Recipe;
 
-    fn create(conn_mgr: &ConnMgrPool, new_item: &NewRecipe) -> bool {
-        match diesel::insert_into(recipes::table)
-        .values(new_item)
-        .execute(conn_mgr) {
-            Ok(_) => true,
-            Err(e) => {
-                error!("Could not insert meal: {}", e);
-                false
-            }
-        }
+    const ENTITY: &'static str = "recipe";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewRecipe) -> Result<i32, NutriplanError> {
+        check_yield(new_item.servings, new_item.yield_mass)?;
+        check_profile(conn_mgr, new_item.profile_id)?;
+        diesel::insert_into(recipes::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
     }
 
-    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Option<Recipe> {
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<Recipe, NutriplanError> {
         use crate::schema::recipes::dsl::*;
 
-        match recipes
+        recipes
             .filter(id.eq(item_id))
-            .load::<Recipe>(conn_mgr)
-        {
-            Ok(mut entities) => {
-                if entities.len() == 0 {
-                    error!("Could not find ingredient with id: {}", item_id);
-                    None
-                } else {
-                    Some(entities.remove(0))
-                }
-            },
-            Err(e) => {
-                error!("Could not read from database: {}", e);
-                None
-            }
-        }
+            .first::<Recipe>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
     }
 
-    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Recipe) -> bool {
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<Recipe>, NutriplanError>
+    {
         use crate::schema::recipes::dsl::*;
 
-        match diesel::update(
-            recipes.filter(id.eq(item_id)))
-            .set(item)
-            .execute(conn_mgr)
-            {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not update item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+        check_page(limit, offset)?;
+        Ok(recipes
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Recipe>(conn_mgr)?)
     }
-    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> bool {
+
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: Recipe) -> Result<(), NutriplanError> {
         use crate::schema::recipes::dsl::*;
 
-        CRUDRecipeIngredient::delete_by_recipe_id(conn_mgr, item_id);
-        match diesel::delete(
+        check_yield(item.servings, item.yield_mass)?;
+        check_profile(conn_mgr, item.profile_id)?;
+        Self::check_owner(conn_mgr, item_id, item.profile_id)?;
+        let rows = diesel::update(
             recipes.filter(id.eq(item_id)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+            .set((
+                name.eq(item.name),
+                description.eq(item.description),
+                servings.eq(item.servings),
+                yield_mass.eq(item.yield_mass),
+                profile_id.eq(item.profile_id),
+            ))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
+        use crate::schema::recipes::dsl::*;
+
+        conn_mgr.transaction(|| {
+            CRUDRecipeIngredient::delete_by_recipe_id(conn_mgr, item_id)?;
+            CRUDRecipeComponent::delete_by_recipe_id(conn_mgr, item_id)?;
+            let rows = diesel::delete(
+                recipes.filter(id.eq(item_id)))
+                .execute(conn_mgr)?;
+            Self::affected(rows, item_id)
+        })
     }
 }
 
@@ -126,7 +370,17 @@ mod test {
             let item = NewRecipe::new("testitem", "testdescription");
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDRecipe::create(&conn_mgr, &item);
-            assert!(ret_val, "could not create meal");
+            assert!(ret_val.is_ok(), "could not create meal");
+        })
+    }
+
+    #[test]
+    fn create_returns_id_of_created_item() {
+        run_db_test(|| {
+            let item = NewRecipe::new("testitem", "testdescription");
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDRecipe::create(&conn_mgr, &item).unwrap();
+            assert_eq!(ret_val, 3);
         })
     }
 
@@ -137,13 +391,13 @@ mod test {
             use std::str;
             let item = NewRecipe::new("createditem", "createddescription");
             let conn_mgr = setup_conn_mgr();
-            let _ = CRUDRecipe::create(&conn_mgr, &item);
+            let created_id = CRUDRecipe::create(&conn_mgr, &item).unwrap();
             let output = Command::new("sqlite3")
                 .arg("test.db")
-                .arg("SELECT * FROM recipes WHERE id=(select max(id) from recipes);")
+                .arg(format!("SELECT * FROM recipes WHERE id={};", created_id))
                 .output()
                 .expect("Failed to execute process");
-            let expected = "3|createditem|createddescription\n";
+            let expected = "3|createditem|createddescription|1||\n";
             assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
         })
     }
@@ -159,6 +413,16 @@ mod test {
         })
     }
 
+    #[test]
+    fn list_returns_items_ordered_by_id() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDRecipe::list(&conn_mgr, 10, 0).unwrap();
+            let ids: Vec<Option<i32>> = items.iter().map(|item| item.id).collect();
+            assert_eq!(ids, vec![Some(1), Some(2)]);
+        })
+    }
+
     #[test]
     fn update_with_sane_id_updates_as_expected() {
         run_db_test(|| {
@@ -167,7 +431,7 @@ mod test {
             let item = Recipe::new(1, "updatedname", "updateddescription");
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDRecipe::update(&conn_mgr, 1, item);
-            let expected = "1|updatedname|updateddescription\n";
+            let expected = "1|updatedname|updateddescription|1||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM recipes WHERE id=1;")
@@ -177,6 +441,61 @@ mod test {
         })
     }
 
+    #[test]
+    fn create_keeps_fractional_yield_mass() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let item = NewRecipe::with_servings("testitem", "testdescription", 2, Some(412.5));
+            let created_id = CRUDRecipe::create(&conn_mgr, &item).unwrap();
+            assert_eq!(CRUDRecipe::read(&conn_mgr, created_id).unwrap().yield_mass, Some(412.5));
+        })
+    }
+
+    #[test]
+    fn update_clears_profile_set_to_none() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let item = Recipe { profile_id: Some(1), ..CRUDRecipe::read(&conn_mgr, 1).unwrap() };
+            CRUDRecipe::update(&conn_mgr, 1, item).unwrap();
+            let item = Recipe { profile_id: None, ..CRUDRecipe::read(&conn_mgr, 1).unwrap() };
+            CRUDRecipe::update(&conn_mgr, 1, item).unwrap();
+            assert_eq!(CRUDRecipe::read(&conn_mgr, 1).unwrap().profile_id, None);
+        })
+    }
+
+    #[test]
+    fn update_rejects_making_component_of_shared_recipe_private() {
+        run_db_test(|| {
+            use crate::controller::database::recipe_component::NewRecipeComponent;
+            let conn_mgr = setup_conn_mgr();
+            CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(1, 2, 50.0)).unwrap();
+            let item = Recipe { profile_id: Some(1), ..CRUDRecipe::read(&conn_mgr, 2).unwrap() };
+            let ret_val = CRUDRecipe::update(&conn_mgr, 2, item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+            assert_eq!(CRUDRecipe::read(&conn_mgr, 2).unwrap().profile_id, None);
+        })
+    }
+
+    #[test]
+    fn update_clears_yield_mass_set_to_none() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let item = Recipe::with_servings(1, "testitem1", "testdescription1", 2, Some(300.0));
+            CRUDRecipe::update(&conn_mgr, 1, item).unwrap();
+            let item = Recipe::with_servings(1, "testitem1", "testdescription1", 2, None);
+            CRUDRecipe::update(&conn_mgr, 1, item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT * FROM recipes WHERE id=1;")
+                .output()
+                .expect("Failed to execute process");
+            let expected = "1|testitem1|testdescription1|2||\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
     #[test]
     fn delete_by_item_id_with_sane_id_deletes_as_expected() {
         run_db_test(|| {
@@ -184,7 +503,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDRecipe::delete(&conn_mgr, 1);
-            let expected = "2|testitem2|testdescription2\n";
+            let expected = "2|testitem2|testdescription2|1||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM recipes;")
@@ -201,7 +520,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDRecipe::delete(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM recipe_ingredients;")
@@ -215,8 +534,8 @@ mod test {
     fn check_returns_true_if_ingredient_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDRecipe::check(&conn_mgr, 1);
-            assert_eq!(inserted, true)
+            let inserted = CRUDRecipe::check(&conn_mgr, 1).unwrap();
+            assert!(inserted)
         })
     }
 
@@ -224,8 +543,103 @@ mod test {
     fn check_returns_false_if_ingredient_not_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDRecipe::check(&conn_mgr, 3);
-            assert_eq!(inserted, false)
+            let inserted = CRUDRecipe::check(&conn_mgr, 3).unwrap();
+            assert!(!inserted)
+        })
+    }
+
+    #[test]
+    fn create_rejects_zero_servings() {
+        run_db_test(|| {
+            let item = NewRecipe::with_servings("testitem", "testdescription", 0, None);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDRecipe::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn nutrients_divides_total_by_servings() {
+        run_db_test(|| {
+            use crate::controller::database::recipe_ingredient::NewRecipeIngredient;
+            let conn_mgr = setup_conn_mgr();
+            let item = Recipe::with_servings(2, "testitem2", "testdescription2", 4, None);
+            CRUDRecipe::update(&conn_mgr, 2, item).unwrap();
+            let _ = CRUDRecipeIngredient::create(&conn_mgr, &NewRecipeIngredient::new(2, 1, 78.0));
+            let nutrients = CRUDRecipe::nutrients(&conn_mgr, 2).unwrap();
+            assert_eq!(nutrients.ingredients.len(), 2);
+            assert_eq!(nutrients.mass, 300.0);
+            assert!((nutrients.total.proteins - 5.22).abs() < 1e-4);
+            assert!((nutrients.per_serving.proteins - 1.305).abs() < 1e-4);
+            assert!((nutrients.per_100g.proteins - 1.74).abs() < 1e-4);
+        })
+    }
+
+    #[test]
+    fn nutrients_per_100g_uses_yield_mass() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let item = Recipe::with_servings(1, "testitem1", "testdescription1", 1, Some(50.0));
+            CRUDRecipe::update(&conn_mgr, 1, item).unwrap();
+            let nutrients = CRUDRecipe::nutrients(&conn_mgr, 1).unwrap();
+            assert_eq!(nutrients.mass, 50.0);
+            assert!((nutrients.total.fats - 1.11).abs() < 1e-4);
+            assert!((nutrients.per_100g.fats - 2.22).abs() < 1e-4);
+        })
+    }
+
+    #[test]
+    fn nutrients_of_unknown_recipe_is_not_found() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDRecipe::nutrients(&conn_mgr, 3);
+            assert!(matches!(ret_val, Err(NutriplanError::NotFound { .. })));
+        })
+    }
+
+    #[test]
+    fn nutrients_includes_components_scaled_by_mass() {
+        run_db_test(|| {
+            use crate::controller::database::recipe_component::NewRecipeComponent;
+            let conn_mgr = setup_conn_mgr();
+            let item = NewRecipeComponent::new(2, 1, 222.0);
+            CRUDRecipeComponent::create(&conn_mgr, &item).unwrap();
+            let nutrients = CRUDRecipe::nutrients(&conn_mgr, 2).unwrap();
+            assert_eq!(nutrients.components.len(), 1);
+            assert_eq!(nutrients.components[0].name, "testitem1");
+            assert!((nutrients.components[0].nutrients.proteins - 2.22).abs() < 1e-4);
+            assert_eq!(nutrients.mass, 444.0);
+            assert!((nutrients.total.proteins - 6.66).abs() < 1e-4);
+        })
+    }
+
+    #[test]
+    fn expand_resolves_nested_components() {
+        run_db_test(|| {
+            use crate::controller::database::recipe_component::NewRecipeComponent;
+            let conn_mgr = setup_conn_mgr();
+            let rid = CRUDRecipe::create(&conn_mgr, &NewRecipe::new("testitem3", "")).unwrap();
+            CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(2, 1, 111.0)).unwrap();
+            CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(rid, 2, 111.0))
+                .unwrap();
+            let (mass, parts) = CRUDRecipe::expand(&conn_mgr, rid).unwrap();
+            assert_eq!(mass, 111.0);
+            assert_eq!(parts.len(), 2);
+            assert_eq!(parts[0].0, 2);
+            assert!((parts[0].1 - 74.0).abs() < 1e-4);
+            assert_eq!(parts[1].0, 1);
+            assert!((parts[1].1 - 37.0).abs() < 1e-4);
+        })
+    }
+
+    #[test]
+    fn delete_also_removes_recipe_component_entries() {
+        run_db_test(|| {
+            use crate::controller::database::recipe_component::NewRecipeComponent;
+            let conn_mgr = setup_conn_mgr();
+            CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(2, 1, 111.0)).unwrap();
+            CRUDRecipe::delete(&conn_mgr, 1).unwrap();
+            assert!(CRUDRecipeComponent::list(&conn_mgr, 10, 0).unwrap().is_empty());
         })
     }
 }
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use diesel::{prelude::*, Queryable, Insertable};
+
+use crate::{
+    controller::database::{
+        ConnMgrPool, CRUDController, check_mass, check_page, last_insert_id, read_reference
+    },
+    controller::database::profile::check_visible,
+    controller::database::recipe::CRUDRecipe,
+    error::NutriplanError,
+    schema::*
+};
+
+/**
+ * A recipe used as part of another recipe, e.g. a dough in a pizza. The mass refers to the
+ * finished component recipe.
+ */
+#[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="recipe_components"]
+pub struct NewRecipeComponent {
+    pub recipe_id: i32,
+    pub component_id: i32,
+    pub mass: f32
+}
+
+impl NewRecipeComponent {
+    pub fn new(recipe_id: i32, component_id: i32, mass: f32) -> Self {
+        NewRecipeComponent { recipe_id, component_id, mass }
+    }
+}
+
+#[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[table_name="recipe_components"]
+pub struct RecipeComponent {
+    pub id: Option<i32>,
+    pub recipe_id: i32,
+    pub component_id: i32,
+    pub mass: f32
+}
+
+impl RecipeComponent {
+    pub fn new(id: i32, recipe_id: i32, component_id: i32, mass: f32) -> Self {
+        RecipeComponent { id: Some(id), recipe_id, component_id, mass }
+    }
+}
+
+pub struct CRUDRecipeComponent { }
+
+impl CRUDRecipeComponent {
+    /**
+     * Delete all entries where the given recipe is either the containing recipe or the component.
+     */
+    pub fn delete_by_recipe_id(conn_mgr: &ConnMgrPool, rid: i32)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::recipe_components::dsl::*;
+
+        diesel::delete(
+            recipe_components.filter(recipe_id.eq(rid).or(component_id.eq(rid))))
+            .execute(conn_mgr)?;
+        Ok(())
+    }
+
+    /**
+     * Check whether `recipe` contains `target`, directly or through any depth of components. The
+     * entry with id `ignore` is not followed, which allows checking an entry about to be updated.
+     */
+    pub(crate) fn contains(conn_mgr: &ConnMgrPool, recipe: i32, target: i32, ignore: Option<i32>)
+        -> Result<bool, NutriplanError>
+    {
+        use crate::schema::recipe_components::dsl::*;
+
+        let mut edges: HashMap<i32, Vec<i32>> = HashMap::new();
+        for (eid, rid, cid) in recipe_components
+            .select((id, recipe_id, component_id))
+            .load::<(Option<i32>, i32, i32)>(conn_mgr)?
+        {
+            if ignore.is_none() || eid != ignore {
+                edges.entry(rid).or_default().push(cid);
+            }
+        }
+        let mut visited = vec![recipe];
+        let mut pending = vec![recipe];
+        while let Some(current) = pending.pop() {
+            for next in edges.get(&current).into_iter().flatten() {
+                if *next == target {
+                    return Ok(true)
+                }
+                if !visited.contains(next) {
+                    visited.push(*next);
+                    pending.push(*next);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /**
+     * Validate that both recipes exist, that the component is visible to the profile of the
+     * recipe and that adding the component does not make a recipe contain itself.
+     */
+    fn check_references(conn_mgr: &ConnMgrPool, rid: i32, cid: i32, ignore: Option<i32>)
+        -> Result<(), NutriplanError>
+    {
+        let recipe = read_reference::<CRUDRecipe>(conn_mgr, rid)?;
+        let component = read_reference::<CRUDRecipe>(conn_mgr, cid)?;
+        check_visible(CRUDRecipe::ENTITY, cid, component.profile_id, recipe.profile_id)?;
+        if rid == cid || Self::contains(conn_mgr, cid, rid, ignore)? {
+            return Err(NutriplanError::InvalidInput(format!(
+                "recipe with id {} cannot contain recipe with id {}, it would contain itself",
+                rid, cid)))
+        }
+        Ok(())
+    }
+}
+
+impl CRUDController for CRUDRecipeComponent {
+    type NewItem = NewRecipeComponent;
+    type Item = RecipeComponent;
+
+    const ENTITY: &'static str = "recipe component";
+
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewRecipeComponent)
+        -> Result<i32, NutriplanError>
+    {
+        check_mass(new_item.mass)?;
+        Self::check_references(conn_mgr, new_item.recipe_id, new_item.component_id, None)?;
+
+        diesel::insert_into(recipe_components::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
+    }
+
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<RecipeComponent, NutriplanError> {
+        use crate::schema::recipe_components::dsl::*;
+
+        recipe_components
+            .filter(id.eq(item_id))
+            .first::<RecipeComponent>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
+    }
+
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<RecipeComponent>, NutriplanError>
+    {
+        use crate::schema::recipe_components::dsl::*;
+
+        check_page(limit, offset)?;
+        Ok(recipe_components
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<RecipeComponent>(conn_mgr)?)
+    }
+
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: RecipeComponent)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::recipe_components::dsl::*;
+
+        check_mass(item.mass)?;
+        Self::check_references(conn_mgr, item.recipe_id, item.component_id, Some(item_id))?;
+        let rows = diesel::update(
+            recipe_components.filter(id.eq(item_id)))
+            .set(item)
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
+        use crate::schema::recipe_components::dsl::*;
+
+        let rows = diesel::delete(
+            recipe_components.filter(id.eq(item_id)))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    #[test]
+    fn create_creates_item_correct_parameters() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let item = NewRecipeComponent::new(1, 2, 123.0);
+            let conn_mgr = setup_conn_mgr();
+            let created_id = CRUDRecipeComponent::create(&conn_mgr, &item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM recipe_components WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            let expected = "1|1|2|123.0\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn create_rejects_private_component_of_other_profile() {
+        run_db_test(|| {
+            use crate::controller::database::recipe::NewRecipe;
+            let conn_mgr = setup_conn_mgr();
+            let item = NewRecipe { profile_id: Some(2), ..NewRecipe::new("private", "dough") };
+            let cid = CRUDRecipe::create(&conn_mgr, &item).unwrap();
+            let item = NewRecipeComponent::new(1, cid, 10.0);
+            let ret_val = CRUDRecipeComponent::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
+    #[test]
+    fn create_returns_foreign_key_violation_on_unknown_component() {
+        run_db_test(|| {
+            let item = NewRecipeComponent::new(1, 3, 123.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDRecipeComponent::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
+    #[test]
+    fn create_rejects_recipe_containing_itself() {
+        run_db_test(|| {
+            let item = NewRecipeComponent::new(1, 1, 123.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDRecipeComponent::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn create_rejects_indirect_cycle() {
+        run_db_test(|| {
+            use crate::controller::database::recipe::NewRecipe;
+            let conn_mgr = setup_conn_mgr();
+            let rid = CRUDRecipe::create(&conn_mgr, &NewRecipe::new("testitem3", "")).unwrap();
+            CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(1, 2, 10.0)).unwrap();
+            CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(2, rid, 10.0)).unwrap();
+            let item = NewRecipeComponent::new(rid, 1, 10.0);
+            let ret_val = CRUDRecipeComponent::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn update_ignores_entry_being_replaced() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let cid = CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(1, 2, 10.0))
+                .unwrap();
+            let ret_val = CRUDRecipeComponent::update(&conn_mgr, cid,
+                RecipeComponent::new(cid, 2, 1, 10.0));
+            assert!(ret_val.is_ok());
+        })
+    }
+
+    #[test]
+    fn update_rejects_cycle() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let _ = CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(1, 2, 10.0));
+            let cid = CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(1, 2, 20.0))
+                .unwrap();
+            let ret_val = CRUDRecipeComponent::update(&conn_mgr, cid,
+                RecipeComponent::new(cid, 2, 1, 20.0));
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn delete_by_recipe_id_removes_both_directions() {
+        run_db_test(|| {
+            use crate::controller::database::recipe::NewRecipe;
+            let conn_mgr = setup_conn_mgr();
+            let rid = CRUDRecipe::create(&conn_mgr, &NewRecipe::new("testitem3", "")).unwrap();
+            let _ = CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(1, 2, 10.0));
+            let _ = CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(2, rid, 10.0));
+            let _ = CRUDRecipeComponent::create(&conn_mgr, &NewRecipeComponent::new(1, rid, 10.0));
+            CRUDRecipeComponent::delete_by_recipe_id(&conn_mgr, 2).unwrap();
+            let items = CRUDRecipeComponent::list(&conn_mgr, 10, 0).unwrap();
+            let ids: Vec<Option<i32>> = items.iter().map(|item| item.id).collect();
+            assert_eq!(ids, vec![Some(3)]);
+        })
+    }
+}
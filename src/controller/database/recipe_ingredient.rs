@@ -1,73 +1,132 @@
 use diesel::{prelude::*, Queryable, Insertable};
-use log::error;
 
 use crate::{
-    controller::database::{ConnMgrPool, CRUDController},
+    controller::database::{
+        ConnMgrPool, CRUDController, check_mass, check_page, last_insert_id, read_reference
+    },
     controller::database::ingredient::CRUDIngredient,
+    controller::database::ingredient_portion::CRUDIngredientPortion,
+    controller::database::profile::check_visible,
     controller::database::recipe::CRUDRecipe,
+    controller::units::Quantity,
+    error::NutriplanError,
     schema::*
 };
 
 #[derive(Insertable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="recipe_ingredients"]
 pub struct NewRecipeIngredient {
     pub recipe_id: i32,
     pub ingredient_id: i32,
-    pub mass: i32
+    pub mass: f32,
+    /** Amount as entered, in `unit` */
+    pub quantity: Option<f32>,
+    pub unit: Option<String>
 }
 
 impl NewRecipeIngredient {
-    pub fn new(recipe_id: i32, ingredient_id: i32, mass: i32) -> Self {
-        NewRecipeIngredient { recipe_id, ingredient_id, mass }
+    pub fn new(recipe_id: i32, ingredient_id: i32, mass: f32) -> Self {
+        NewRecipeIngredient { recipe_id, ingredient_id, mass, quantity: None, unit: None }
+    }
+
+    /**
+     * Create an entry which remembers the quantity its mass was derived from.
+     */
+    pub fn with_quantity(recipe_id: i32, ingredient_id: i32, mass: f32, quantity: &Quantity)
+        -> Self
+    {
+        NewRecipeIngredient {
+            recipe_id,
+            ingredient_id,
+            mass,
+            quantity: Some(quantity.amount),
+            unit: Some(quantity.unit.clone())
+        }
     }
 }
 
 #[derive(AsChangeset, Queryable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[table_name="recipe_ingredients"]
 pub struct RecipeIngredient {
     pub id: Option<i32>,
     pub recipe_id: i32,
     pub ingredient_id: i32,
-    pub mass: i32
+    pub mass: f32,
+    /** Amount as entered, in `unit` */
+    pub quantity: Option<f32>,
+    pub unit: Option<String>
 }
 
 impl RecipeIngredient {
-    pub fn new(id: i32, recipe_id: i32, ingredient_id: i32, mass: i32) -> Self {
-        RecipeIngredient { id: Some(id), recipe_id, ingredient_id, mass }
+    pub fn new(id: i32, recipe_id: i32, ingredient_id: i32, mass: f32) -> Self {
+        RecipeIngredient {
+            id: Some(id),
+            recipe_id,
+            ingredient_id,
+            mass,
+            quantity: None,
+            unit: None
+        }
+    }
+
+    /**
+     * Quantity the mass was derived from, if it was not entered in gram directly.
+     */
+    pub fn quantity(&self) -> Option<Quantity> {
+        match (self.quantity, &self.unit) {
+            (Some(amount), Some(unit)) => Some(Quantity::new(amount, unit)),
+            _ => None
+        }
     }
 }
 
 pub struct CRUDRecipeIngredient { }
 
 impl CRUDRecipeIngredient {
-    pub fn delete_by_ingredient_id(conn_mgr: &ConnMgrPool, iid: i32) -> bool {
+    /**
+     * Create an entry from a quantity in any unit known for the ingredient, see
+     * `CRUDIngredientPortion::to_grams`.
+     */
+    pub fn create_from_quantity(conn_mgr: &ConnMgrPool, recipe_id: i32, ingredient_id: i32,
+        quantity: &Quantity) -> Result<i32, NutriplanError>
+    {
+        let mass = CRUDIngredientPortion::to_grams(conn_mgr, ingredient_id, quantity)?;
+        let item = NewRecipeIngredient::with_quantity(recipe_id, ingredient_id, mass, quantity);
+        Self::create(conn_mgr, &item)
+    }
+
+    /**
+     * Validate that the recipe and the ingredient exist and that the ingredient is visible to the
+     * profile of the recipe.
+     */
+    fn check_references(conn_mgr: &ConnMgrPool, rid: i32, iid: i32) -> Result<(), NutriplanError> {
+        let recipe = read_reference::<CRUDRecipe>(conn_mgr, rid)?;
+        let ingredient = read_reference::<CRUDIngredient>(conn_mgr, iid)?;
+        check_visible(CRUDIngredient::ENTITY, iid, ingredient.profile_id, recipe.profile_id)
+    }
+
+    pub fn delete_by_ingredient_id(conn_mgr: &ConnMgrPool, iid: i32)
+        -> Result<(), NutriplanError>
+    {
         use crate::schema::recipe_ingredients::dsl::*;
 
-        match diesel::delete(
+        diesel::delete(
             recipe_ingredients.filter(ingredient_id.eq(iid)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with ingredient id {}: {}", iid, e);
-                    false
-                }
-            }
-
+            .execute(conn_mgr)?;
+        Ok(())
     }
-    
-    pub fn delete_by_recipe_id(conn_mgr: &ConnMgrPool, rid: i32) -> bool {
+
+    pub fn delete_by_recipe_id(conn_mgr: &ConnMgrPool, rid: i32)
+        -> Result<(), NutriplanError>
+    {
         use crate::schema::recipe_ingredients::dsl::*;
 
-        match diesel::delete(
+        diesel::delete(
             recipe_ingredients.filter(recipe_id.eq(rid)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with recipe id {}: {}", rid, e);
-                    false
-                }
-            }
-
+            .execute(conn_mgr)?;
+        Ok(())
     }
 }
 
@@ -75,73 +134,70 @@ impl CRUDController for CRUDRecipeIngredient {
     type NewItem = NewRecipeIngredient;
     type Item = RecipeIngredient;
 
-    fn create(conn_mgr: &ConnMgrPool, new_item: &NewRecipeIngredient) -> bool {
-        let recipe_avail = CRUDRecipe::check(conn_mgr, new_item.recipe_id);
-        let ingredient_avail = CRUDIngredient::check(conn_mgr, new_item.ingredient_id);
-        match recipe_avail & ingredient_avail {
-            true => (),
-            false => return false
-        }
+    const ENTITY: &'static str = "recipe ingredient";
 
-        match diesel::insert_into(recipe_ingredients::table)
-        .values(new_item)
-        .execute(conn_mgr) {
-            Ok(_) => true,
-            Err(e) => {
-                error!("Could not insert meal: {}", e);
-                false
-            }
-        }
+    fn create(conn_mgr: &ConnMgrPool, new_item: &NewRecipeIngredient)
+        -> Result<i32, NutriplanError>
+    {
+        check_mass(new_item.mass)?;
+        Self::check_references(conn_mgr, new_item.recipe_id, new_item.ingredient_id)?;
+
+        diesel::insert_into(recipe_ingredients::table)
+            .values(new_item)
+            .execute(conn_mgr)?;
+        last_insert_id(conn_mgr)
     }
-    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Option<RecipeIngredient> {
+
+    fn read(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<RecipeIngredient, NutriplanError> {
         use crate::schema::recipe_ingredients::dsl::*;
 
-        match recipe_ingredients
+        recipe_ingredients
             .filter(id.eq(item_id))
-            .load::<RecipeIngredient>(conn_mgr)
-        {
-            Ok(mut entities) => {
-                if entities.len() == 0 {
-                    error!("Could not find item with id: {}", item_id);
-                    None
-                } else {
-                    Some(entities.remove(0))
-                }
-            },
-            Err(e) => {
-                error!("Could not read from database: {}", e);
-                None
-            }
-        }
+            .first::<RecipeIngredient>(conn_mgr)
+            .optional()?
+            .ok_or_else(|| NutriplanError::not_found(Self::ENTITY, item_id))
     }
-    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: RecipeIngredient) -> bool {
+
+    fn list(conn_mgr: &ConnMgrPool, limit: i64, offset: i64)
+        -> Result<Vec<RecipeIngredient>, NutriplanError>
+    {
         use crate::schema::recipe_ingredients::dsl::*;
 
-        match diesel::update(
+        check_page(limit, offset)?;
+        Ok(recipe_ingredients
+            .order(id.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<RecipeIngredient>(conn_mgr)?)
+    }
+
+    fn update(conn_mgr: &ConnMgrPool, item_id: i32, item: RecipeIngredient)
+        -> Result<(), NutriplanError>
+    {
+        use crate::schema::recipe_ingredients::dsl::*;
+
+        check_mass(item.mass)?;
+        Self::check_references(conn_mgr, item.recipe_id, item.ingredient_id)?;
+        let rows = diesel::update(
             recipe_ingredients.filter(id.eq(item_id)))
-            .set(item)
-            .execute(conn_mgr)
-            {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not update item with id {}: {}", item_id, e);
-                    false
-                }
-            }
-    }
-
-    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> bool {
+            .set((
+                recipe_id.eq(item.recipe_id),
+                ingredient_id.eq(item.ingredient_id),
+                mass.eq(item.mass),
+                quantity.eq(item.quantity),
+                unit.eq(item.unit),
+            ))
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
+    }
+
+    fn delete(conn_mgr: &ConnMgrPool, item_id: i32) -> Result<(), NutriplanError> {
         use crate::schema::recipe_ingredients::dsl::*;
 
-        match diesel::delete(
+        let rows = diesel::delete(
             recipe_ingredients.filter(id.eq(item_id)))
-            .execute(conn_mgr) {
-                Ok(_) => true,
-                Err(e) => {
-                    error!("Could not delete item with id {}: {}", item_id, e);
-                    false
-                }
-            }
+            .execute(conn_mgr)?;
+        Self::affected(rows, item_id)
     }
 }
 
@@ -154,7 +210,7 @@ mod test {
     #[test]
     fn create_accepts_item_as_parameter() {
         run_db_test(|| {
-            let item = NewRecipeIngredient::new(1, 1, 123);
+            let item = NewRecipeIngredient::new(1, 1, 123.0);
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDRecipeIngredient::create(&conn_mgr, &item);
         })
@@ -163,10 +219,20 @@ mod test {
     #[test]
     fn create_returns_ok_on_sane_parameters() {
         run_db_test(|| {
-            let item = NewRecipeIngredient::new(1, 1, 123);
+            let item = NewRecipeIngredient::new(1, 1, 123.0);
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDRecipeIngredient::create(&conn_mgr, &item);
-            assert!(ret_val, "could not create item");
+            assert!(ret_val.is_ok(), "could not create item");
+        })
+    }
+
+    #[test]
+    fn create_returns_id_of_created_item() {
+        run_db_test(|| {
+            let item = NewRecipeIngredient::new(1, 1, 123.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDRecipeIngredient::create(&conn_mgr, &item).unwrap();
+            assert_eq!(ret_val, 3);
         })
     }
 
@@ -175,36 +241,63 @@ mod test {
         run_db_test(|| {
             use std::process::Command;
             use std::str;
-            let item = NewRecipeIngredient::new(1, 1, 123);
+            let item = NewRecipeIngredient::new(1, 1, 123.0);
             let conn_mgr = setup_conn_mgr();
-            let _ = CRUDRecipeIngredient::create(&conn_mgr, &item);
+            let created_id = CRUDRecipeIngredient::create(&conn_mgr, &item).unwrap();
             let output = Command::new("sqlite3")
                 .arg("test.db")
-                .arg("SELECT * FROM recipe_ingredients WHERE id=(select max(id) from recipe_ingredients);")
+                .arg(format!("SELECT * FROM recipe_ingredients WHERE id={};", created_id))
                 .output()
                 .expect("Failed to execute process");
-            let expected = "3|1|1|123\n";
+            let expected = "3|1|1|123.0||\n";
             assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
         })
     }
 
     #[test]
-    fn create_returns_false_on_unknown_meal() {
+    fn create_returns_foreign_key_violation_on_unknown_recipe() {
+        run_db_test(|| {
+            let item = NewRecipeIngredient::new(3, 1, 123.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDRecipeIngredient::create(&conn_mgr, &item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
+    #[test]
+    fn create_returns_foreign_key_violation_on_unknown_ingredient() {
         run_db_test(|| {
-            let item = NewRecipeIngredient::new(3, 1, 123);
+            let item = NewRecipeIngredient::new(1, 3, 123.0);
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDRecipeIngredient::create(&conn_mgr, &item);
-            assert_eq!(ret_val, false);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
         })
     }
 
     #[test]
-    fn create_returns_false_on_unknown_ingredient() {
+    fn create_rejects_negative_mass() {
         run_db_test(|| {
-            let item = NewRecipeIngredient::new(1, 3, 123);
+            let item = NewRecipeIngredient::new(1, 1, -0.5);
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDRecipeIngredient::create(&conn_mgr, &item);
-            assert_eq!(ret_val, false);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn create_rejects_private_ingredient_of_other_profile() {
+        run_db_test(|| {
+            use crate::controller::database::ingredient::NewIngredient;
+            use crate::controller::database::recipe::Recipe;
+            let conn_mgr = setup_conn_mgr();
+            let item = NewIngredient { profile_id: Some(2), ..NewIngredient::new("private") };
+            let iid = CRUDIngredient::create(&conn_mgr, &item).unwrap();
+            let entry = NewRecipeIngredient::new(1, iid, 10.0);
+            let ret_val = CRUDRecipeIngredient::create(&conn_mgr, &entry);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+            let recipe = CRUDRecipe::read(&conn_mgr, 1).unwrap();
+            CRUDRecipe::update(&conn_mgr, 1, Recipe { profile_id: Some(2), ..recipe }).unwrap();
+            assert!(CRUDRecipeIngredient::create(&conn_mgr, &entry).is_ok());
         })
     }
 
@@ -216,7 +309,17 @@ mod test {
             assert_eq!(ret_val.id, Some(1));
             assert_eq!(ret_val.recipe_id, 1);
             assert_eq!(ret_val.ingredient_id, 1);
-            assert_eq!(ret_val.mass, 111);
+            assert_eq!(ret_val.mass, 111.0);
+        })
+    }
+
+    #[test]
+    fn list_returns_items_ordered_by_id() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let items = CRUDRecipeIngredient::list(&conn_mgr, 10, 0).unwrap();
+            let ids: Vec<Option<i32>> = items.iter().map(|item| item.id).collect();
+            assert_eq!(ids, vec![Some(1), Some(2)]);
         })
     }
 
@@ -225,10 +328,10 @@ mod test {
         run_db_test(|| {
             use std::process::Command;
             use std::str;
-            let item = RecipeIngredient::new(1, 2, 3, 456);
+            let item = RecipeIngredient::new(1, 2, 2, 456.0);
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDRecipeIngredient::update(&conn_mgr, 1, item);
-            let expected = "1|2|3|456\n";
+            let expected = "1|2|2|456.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM recipe_ingredients WHERE id=1;")
@@ -238,6 +341,48 @@ mod test {
         })
     }
 
+    #[test]
+    fn update_clears_quantity_set_to_none() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let quantity = Quantity::parse("1.5 slices").unwrap();
+            let created_id = CRUDRecipeIngredient::create_from_quantity(&conn_mgr, 2, 1, &quantity)
+                .unwrap();
+            let item = RecipeIngredient::new(created_id, 2, 1, 50.0);
+            CRUDRecipeIngredient::update(&conn_mgr, created_id, item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM recipe_ingredients WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            let expected = "3|2|1|50.0||\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+            assert_eq!(CRUDRecipeIngredient::read(&conn_mgr, created_id).unwrap().quantity(), None);
+        })
+    }
+
+    #[test]
+    fn update_returns_foreign_key_violation_on_unknown_recipe() {
+        run_db_test(|| {
+            let item = RecipeIngredient::new(1, 3, 1, 456.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDRecipeIngredient::update(&conn_mgr, 1, item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
+    #[test]
+    fn update_returns_foreign_key_violation_on_unknown_ingredient() {
+        run_db_test(|| {
+            let item = RecipeIngredient::new(1, 1, 3, 456.0);
+            let conn_mgr = setup_conn_mgr();
+            let ret_val = CRUDRecipeIngredient::update(&conn_mgr, 1, item);
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+        })
+    }
+
     #[test]
     fn delete_by_item_id_with_sane_id_deletes_as_expected() {
         run_db_test(|| {
@@ -245,7 +390,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDRecipeIngredient::delete(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM recipe_ingredients;")
@@ -262,7 +407,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDRecipeIngredient::delete_by_ingredient_id(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM recipe_ingredients;")
@@ -279,7 +424,7 @@ mod test {
             use std::str;
             let conn_mgr = setup_conn_mgr();
             let _ = CRUDRecipeIngredient::delete_by_recipe_id(&conn_mgr, 1);
-            let expected = "2|2|2|222\n";
+            let expected = "2|2|2|222.0||\n";
             let output = Command::new("sqlite3")
                 .arg("test.db")
                 .arg("SELECT * FROM recipe_ingredients;")
@@ -290,11 +435,11 @@ mod test {
     }
 
     #[test]
-    fn delete_by_recipe_id_with_sane_id_returns_true() {
+    fn delete_by_recipe_id_with_sane_id_returns_ok() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
             let ret_val = CRUDRecipeIngredient::delete_by_recipe_id(&conn_mgr, 1);
-            assert_eq!(ret_val, true);
+            assert!(ret_val.is_ok());
         })
     }
 
@@ -302,8 +447,8 @@ mod test {
     fn check_returns_true_if_ingredient_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDRecipeIngredient::check(&conn_mgr, 1);
-            assert_eq!(inserted, true)
+            let inserted = CRUDRecipeIngredient::check(&conn_mgr, 1).unwrap();
+            assert!(inserted)
         })
     }
 
@@ -311,8 +456,29 @@ mod test {
     fn check_returns_false_if_ingredient_not_available() {
         run_db_test(|| {
             let conn_mgr = setup_conn_mgr();
-            let inserted = CRUDRecipeIngredient::check(&conn_mgr, 3);
-            assert_eq!(inserted, false)
+            let inserted = CRUDRecipeIngredient::check(&conn_mgr, 3).unwrap();
+            assert!(!inserted)
+        })
+    }
+
+    #[test]
+    fn create_from_quantity_stores_mass_and_quantity() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let quantity = Quantity::parse("1.5 slices").unwrap();
+            let created_id = CRUDRecipeIngredient::create_from_quantity(&conn_mgr, 2, 1, &quantity)
+                .unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT * FROM recipe_ingredients WHERE id={};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            let expected = "3|2|1|45.0|1.5|slices\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+            let item = CRUDRecipeIngredient::read(&conn_mgr, created_id).unwrap();
+            assert_eq!(item.quantity(), Some(quantity));
         })
     }
 }
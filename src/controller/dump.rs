@@ -0,0 +1,565 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+use chrono::{NaiveDate, NaiveTime};
+use diesel::prelude::*;
+use serde_json::{Map, Value, json};
+
+use crate::{
+    controller::database::{ConnMgrPool, CRUDController},
+    controller::database::goal::{CRUDGoal, Goal, NewGoal},
+    controller::database::ingredient::{CRUDIngredient, Ingredient, NewIngredient},
+    controller::database::ingredient_macro::{CRUDIngredientMacro, IngredientMacro,
+        NewIngredientMacro},
+    controller::database::ingredient_micro::{CRUDIngredientMicro, IngredientMicro,
+        NewIngredientMicro},
+    controller::database::ingredient_portion::{CRUDIngredientPortion, IngredientPortion,
+        NewIngredientPortion},
+    controller::database::meal::{CRUDMeal, Meal, NewMeal},
+    controller::database::meal_ingredient::{CRUDMealIngredient, MealIngredient,
+        NewMealIngredient},
+    controller::database::micronutrient::{CRUDMicronutrient, Micronutrient, NewMicronutrient},
+    controller::database::profile::{CRUDProfile, NewProfile, Profile},
+    controller::database::recipe::{CRUDRecipe, NewRecipe, Recipe},
+    controller::database::recipe_component::{CRUDRecipeComponent, NewRecipeComponent,
+        RecipeComponent},
+    controller::database::recipe_ingredient::{CRUDRecipeIngredient, NewRecipeIngredient,
+        RecipeIngredient},
+    error::NutriplanError,
+    schema,
+};
+
+/** Name identifying a dump */
+const FORMAT: &str = "nutriplan";
+
+/**
+ * Version of the layout of a dump, which has to be increased on every change of the tables or
+ * fields written. Dumps of other versions are rejected on restore.
+ */
+pub const DUMP_VERSION: i64 = 1;
+
+/**
+ * Write an amount with the digits of the f32, instead of those of its f64 representation.
+ */
+fn float(value: f32) -> Value {
+    value.to_string().parse::<f64>().map(Value::from).unwrap_or(Value::Null)
+}
+
+fn optional_float(value: Option<f32>) -> Value {
+    value.map_or(Value::Null, float)
+}
+
+/**
+ * Write all tables into a single JSON document. Rows keep their ids, references between rows
+ * are given by these ids.
+ */
+pub fn dump<W: Write>(conn_mgr: &ConnMgrPool, writer: W) -> Result<(), NutriplanError> {
+    let mut tables = Map::new();
+    tables.insert(String::from("profiles"), schema::profiles::table
+        .order(schema::profiles::id)
+        .load::<Profile>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "name": item.name}))
+        .collect());
+    tables.insert(String::from("micronutrients"), schema::micronutrients::table
+        .order(schema::micronutrients::id)
+        .load::<Micronutrient>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "code": item.code, "name": item.name,
+            "unit": item.unit}))
+        .collect());
+    tables.insert(String::from("ingredients"), schema::ingredients::table
+        .order(schema::ingredients::id)
+        .load::<Ingredient>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "name": item.name,
+            "density": optional_float(item.density), "profile_id": item.profile_id,
+            "barcode": item.barcode, "source": item.source, "source_id": item.source_id}))
+        .collect());
+    tables.insert(String::from("ingredient_macros"), schema::ingredient_macros::table
+        .order(schema::ingredient_macros::id)
+        .load::<IngredientMacro>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "ingredient_id": item.ingredient_id,
+            "proteins": float(item.proteins), "carbs": float(item.carbs),
+            "fats": float(item.fats), "alcohols": float(item.alcohols),
+            "calories": float(item.calories), "fiber": float(item.fiber),
+            "polyols": float(item.polyols)}))
+        .collect());
+    tables.insert(String::from("ingredient_micros"), schema::ingredient_micros::table
+        .order(schema::ingredient_micros::id)
+        .load::<IngredientMicro>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "ingredient_id": item.ingredient_id,
+            "micronutrient_id": item.micronutrient_id, "amount": float(item.amount)}))
+        .collect());
+    tables.insert(String::from("ingredient_portions"), schema::ingredient_portions::table
+        .order(schema::ingredient_portions::id)
+        .load::<IngredientPortion>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "ingredient_id": item.ingredient_id,
+            "name": item.name, "mass": float(item.mass)}))
+        .collect());
+    tables.insert(String::from("recipes"), schema::recipes::table
+        .order(schema::recipes::id)
+        .load::<Recipe>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "name": item.name, "description": item.description,
+            "servings": item.servings, "yield_mass": optional_float(item.yield_mass),
+            "profile_id": item.profile_id}))
+        .collect());
+    tables.insert(String::from("recipe_ingredients"), schema::recipe_ingredients::table
+        .order(schema::recipe_ingredients::id)
+        .load::<RecipeIngredient>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "recipe_id": item.recipe_id,
+            "ingredient_id": item.ingredient_id, "mass": float(item.mass),
+            "quantity": optional_float(item.quantity), "unit": item.unit}))
+        .collect());
+    tables.insert(String::from("recipe_components"), schema::recipe_components::table
+        .order(schema::recipe_components::id)
+        .load::<RecipeComponent>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "recipe_id": item.recipe_id,
+            "component_id": item.component_id, "mass": float(item.mass)}))
+        .collect());
+    tables.insert(String::from("meals"), schema::meals::table
+        .order(schema::meals::id)
+        .load::<Meal>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "name": item.name, "date": item.date.to_string(),
+            "time": item.time.to_string(), "profile_id": item.profile_id}))
+        .collect());
+    tables.insert(String::from("meal_ingredients"), schema::meal_ingredients::table
+        .order(schema::meal_ingredients::id)
+        .load::<MealIngredient>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "meal_id": item.meal_id,
+            "ingredient_id": item.ingredient_id, "mass": float(item.mass),
+            "quantity": optional_float(item.quantity), "unit": item.unit}))
+        .collect());
+    tables.insert(String::from("goals"), schema::goals::table
+        .order(schema::goals::id)
+        .load::<Goal>(conn_mgr)?
+        .iter()
+        .map(|item| json!({"id": item.id, "name": item.name, "weekday": item.weekday,
+            "start_date": item.start_date.map(|date| date.to_string()),
+            "end_date": item.end_date.map(|date| date.to_string()),
+            "calories": optional_float(item.calories),
+            "proteins": optional_float(item.proteins), "carbs": optional_float(item.carbs),
+            "fats": optional_float(item.fats), "alcohols": optional_float(item.alcohols),
+            "fiber": optional_float(item.fiber), "polyols": optional_float(item.polyols),
+            "profile_id": item.profile_id}))
+        .collect());
+
+    let document = json!({"format": FORMAT, "version": DUMP_VERSION, "tables": tables});
+    serde_json::to_writer_pretty(writer, &document)
+        .map_err(|e| NutriplanError::InvalidInput(format!("could not write dump: {}", e)))
+}
+
+/**
+ * Row of a table of a dump, which fails with the position of the row on missing or invalid
+ * fields.
+ */
+struct Row<'a> {
+    table: &'static str,
+    pos: usize,
+    fields: &'a Map<String, Value>,
+}
+
+impl<'a> Row<'a> {
+    fn invalid(&self, field: &str, expected: &str) -> NutriplanError {
+        NutriplanError::InvalidInput(
+            format!("{} row {}: {} must be {}", self.table, self.pos, field, expected))
+    }
+
+    /** Value of a field, `None` if it is missing or null */
+    fn value(&self, field: &str) -> Option<&'a Value> {
+        self.fields.get(field).filter(|value| !value.is_null())
+    }
+
+    fn optional<T, F>(&self, field: &str, expected: &str, f: F) -> Result<Option<T>, NutriplanError>
+        where F: Fn(&'a Value) -> Option<T>
+    {
+        self.value(field).map(|value| f(value).ok_or_else(|| self.invalid(field, expected)))
+            .transpose()
+    }
+
+    fn required<T>(&self, field: &str, expected: &str, value: Option<T>)
+        -> Result<T, NutriplanError>
+    {
+        value.ok_or_else(|| self.invalid(field, expected))
+    }
+
+    fn optional_int(&self, field: &str) -> Result<Option<i32>, NutriplanError> {
+        self.optional(field, "an integer",
+            |value| value.as_i64().and_then(|value| i32::try_from(value).ok()))
+    }
+
+    fn int(&self, field: &str) -> Result<i32, NutriplanError> {
+        self.required(field, "an integer", self.optional_int(field)?)
+    }
+
+    fn optional_float(&self, field: &str) -> Result<Option<f32>, NutriplanError> {
+        self.optional(field, "a number", |value| value.as_f64().map(|value| value as f32))
+    }
+
+    fn float(&self, field: &str) -> Result<f32, NutriplanError> {
+        self.required(field, "a number", self.optional_float(field)?)
+    }
+
+    fn optional_text(&self, field: &str) -> Result<Option<String>, NutriplanError> {
+        self.optional(field, "a string", |value| value.as_str().map(str::to_owned))
+    }
+
+    fn text(&self, field: &str) -> Result<String, NutriplanError> {
+        self.required(field, "a string", self.optional_text(field)?)
+    }
+
+    fn optional_date(&self, field: &str) -> Result<Option<NaiveDate>, NutriplanError> {
+        self.optional(field, "a date like 2021-12-24", |value| value.as_str()
+            .and_then(|value| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()))
+    }
+
+    fn date(&self, field: &str) -> Result<NaiveDate, NutriplanError> {
+        self.required(field, "a date like 2021-12-24", self.optional_date(field)?)
+    }
+
+    fn time(&self, field: &str) -> Result<NaiveTime, NutriplanError> {
+        let time = self.optional(field, "a time like 08:30:00",
+            |value| value.as_str().and_then(|value| value.parse::<NaiveTime>().ok()))?;
+        self.required(field, "a time like 08:30:00", time)
+    }
+
+    /**
+     * Id a referenced row of the dump got in the database, `None` if the field is empty.
+     */
+    fn optional_reference(&self, field: &str, ids: &HashMap<i32, i32>)
+        -> Result<Option<i32>, NutriplanError>
+    {
+        self.optional_int(field)?
+            .map(|old_id| ids.get(&old_id).copied().ok_or_else(|| NutriplanError::InvalidInput(
+                format!("{} row {}: {} {} is not part of the dump", self.table, self.pos, field,
+                    old_id))))
+            .transpose()
+    }
+
+    fn reference(&self, field: &str, ids: &HashMap<i32, i32>) -> Result<i32, NutriplanError> {
+        self.required(field, "an integer", self.optional_reference(field, ids)?)
+    }
+}
+
+/**
+ * Rows of a table of a dump, a missing table has no rows.
+ */
+fn rows<'a>(tables: &'a Map<String, Value>, table: &'static str)
+    -> Result<Vec<Row<'a>>, NutriplanError>
+{
+    let items = match tables.get(table) {
+        Some(Value::Array(items)) => items,
+        None => return Ok(Vec::new()),
+        Some(_) => return Err(NutriplanError::InvalidInput(
+            format!("table {} must be a list of rows", table)))
+    };
+    items.iter()
+        .enumerate()
+        .map(|(pos, item)| match item.as_object() {
+            Some(fields) => Ok(Row { table, pos: pos + 1, fields }),
+            None => Err(NutriplanError::InvalidInput(
+                format!("{} row {}: row must be an object", table, pos + 1)))
+        })
+        .collect()
+}
+
+/**
+ * Restore a dump written by `dump` into an empty or existing database. Rows get new ids, which
+ * are used for the references between them. Profiles and micronutrients are matched by their
+ * name and code, ingredients by their barcode or source, existing ones are used instead of
+ * creating them again. The nutrients and portions of existing ingredients are kept. All rows are
+ * restored in a single transaction, nothing is stored if any row is invalid.
+ *
+ * # Returns
+ * * Number of restored rows by table
+ */
+pub fn restore<R: Read>(conn_mgr: &ConnMgrPool, reader: R)
+    -> Result<Vec<(&'static str, usize)>, NutriplanError>
+{
+    let document: Value = serde_json::from_reader(reader)
+        .map_err(|e| NutriplanError::InvalidInput(format!("could not parse dump: {}", e)))?;
+    if document.get("format").and_then(Value::as_str) != Some(FORMAT) {
+        return Err(NutriplanError::InvalidInput(String::from("file is no nutriplan dump")))
+    }
+    match document.get("version").and_then(Value::as_i64) {
+        Some(DUMP_VERSION) => (),
+        version => return Err(NutriplanError::InvalidInput(format!(
+            "dump version {} is not supported, expected {}",
+            version.map_or_else(|| String::from("unknown"), |version| version.to_string()),
+            DUMP_VERSION)))
+    }
+    let tables = document.get("tables")
+        .and_then(Value::as_object)
+        .ok_or_else(|| NutriplanError::InvalidInput(String::from("dump holds no tables")))?;
+    conn_mgr.transaction(|| restore_tables(conn_mgr, tables))
+}
+
+fn restore_tables(conn_mgr: &ConnMgrPool, tables: &Map<String, Value>)
+    -> Result<Vec<(&'static str, usize)>, NutriplanError>
+{
+    let mut counts = Vec::new();
+
+    let mut profiles = HashMap::new();
+    let rows_of_table = rows(tables, "profiles")?;
+    for row in rows_of_table.iter() {
+        let name = row.text("name")?;
+        let profile_id = match CRUDProfile::find_by_name(conn_mgr, &name)?
+            .and_then(|item| item.id)
+        {
+            Some(profile_id) => profile_id,
+            None => CRUDProfile::create(conn_mgr, &NewProfile::new(&name))?
+        };
+        profiles.insert(row.int("id")?, profile_id);
+    }
+    counts.push(("profiles", rows_of_table.len()));
+
+    let mut micronutrients = HashMap::new();
+    let rows_of_table = rows(tables, "micronutrients")?;
+    for row in rows_of_table.iter() {
+        let code = row.text("code")?;
+        let micronutrient_id = match CRUDMicronutrient::find_by_code(conn_mgr, &code)?
+            .and_then(|item| item.id)
+        {
+            Some(micronutrient_id) => micronutrient_id,
+            None => CRUDMicronutrient::create(conn_mgr,
+                &NewMicronutrient::new(&code, &row.text("name")?, &row.text("unit")?))?
+        };
+        micronutrients.insert(row.int("id")?, micronutrient_id);
+    }
+    counts.push(("micronutrients", rows_of_table.len()));
+
+    let mut ingredients = HashMap::new();
+    let mut existing_ingredients = HashSet::new();
+    let rows_of_table = rows(tables, "ingredients")?;
+    for row in rows_of_table.iter() {
+        let item = NewIngredient {
+            name: row.text("name")?,
+            density: row.optional_float("density")?,
+            profile_id: row.optional_reference("profile_id", &profiles)?,
+            barcode: row.optional_text("barcode")?,
+            source: row.optional_text("source")?,
+            source_id: row.optional_text("source_id")?,
+        };
+        let existing = match (&item.barcode, &item.source, &item.source_id) {
+            (Some(code), _, _) => CRUDIngredient::find_by_barcode(conn_mgr, code)?,
+            (None, Some(item_source), Some(item_source_id)) =>
+                CRUDIngredient::find_by_source(conn_mgr, item_source, item_source_id)?,
+            _ => None
+        };
+        let ingredient_id = match existing.and_then(|existing| existing.id) {
+            Some(ingredient_id) => {
+                existing_ingredients.insert(ingredient_id);
+                ingredient_id
+            },
+            None => CRUDIngredient::create(conn_mgr, &item)?
+        };
+        ingredients.insert(row.int("id")?, ingredient_id);
+    }
+    counts.push(("ingredients", rows_of_table.len()));
+
+    let rows_of_table = rows(tables, "ingredient_macros")?;
+    for row in rows_of_table.iter() {
+        let ingredient_id = row.reference("ingredient_id", &ingredients)?;
+        if existing_ingredients.contains(&ingredient_id) {
+            continue
+        }
+        CRUDIngredientMacro::create(conn_mgr, &NewIngredientMacro {
+            ingredient_id,
+            proteins: row.float("proteins")?,
+            carbs: row.float("carbs")?,
+            fats: row.float("fats")?,
+            alcohols: row.float("alcohols")?,
+            calories: row.float("calories")?,
+            fiber: row.float("fiber")?,
+            polyols: row.float("polyols")?,
+        })?;
+    }
+    counts.push(("ingredient_macros", rows_of_table.len()));
+
+    let rows_of_table = rows(tables, "ingredient_micros")?;
+    for row in rows_of_table.iter() {
+        let ingredient_id = row.reference("ingredient_id", &ingredients)?;
+        if existing_ingredients.contains(&ingredient_id) {
+            continue
+        }
+        CRUDIngredientMicro::create(conn_mgr, &NewIngredientMicro::new(ingredient_id,
+            row.reference("micronutrient_id", &micronutrients)?,
+            row.float("amount")?))?;
+    }
+    counts.push(("ingredient_micros", rows_of_table.len()));
+
+    let rows_of_table = rows(tables, "ingredient_portions")?;
+    for row in rows_of_table.iter() {
+        let ingredient_id = row.reference("ingredient_id", &ingredients)?;
+        if existing_ingredients.contains(&ingredient_id) {
+            continue
+        }
+        CRUDIngredientPortion::create(conn_mgr, &NewIngredientPortion::new(ingredient_id,
+            &row.text("name")?, row.float("mass")?))?;
+    }
+    counts.push(("ingredient_portions", rows_of_table.len()));
+
+    let mut recipes = HashMap::new();
+    let rows_of_table = rows(tables, "recipes")?;
+    for row in rows_of_table.iter() {
+        let item = NewRecipe {
+            name: row.text("name")?,
+            description: row.text("description")?,
+            servings: row.int("servings")?,
+            yield_mass: row.optional_float("yield_mass")?,
+            profile_id: row.optional_reference("profile_id", &profiles)?,
+        };
+        recipes.insert(row.int("id")?, CRUDRecipe::create(conn_mgr, &item)?);
+    }
+    counts.push(("recipes", rows_of_table.len()));
+
+    let rows_of_table = rows(tables, "recipe_ingredients")?;
+    for row in rows_of_table.iter() {
+        CRUDRecipeIngredient::create(conn_mgr, &NewRecipeIngredient {
+            recipe_id: row.reference("recipe_id", &recipes)?,
+            ingredient_id: row.reference("ingredient_id", &ingredients)?,
+            mass: row.float("mass")?,
+            quantity: row.optional_float("quantity")?,
+            unit: row.optional_text("unit")?,
+        })?;
+    }
+    counts.push(("recipe_ingredients", rows_of_table.len()));
+
+    let rows_of_table = rows(tables, "recipe_components")?;
+    for row in rows_of_table.iter() {
+        CRUDRecipeComponent::create(conn_mgr, &NewRecipeComponent::new(
+            row.reference("recipe_id", &recipes)?, row.reference("component_id", &recipes)?,
+            row.float("mass")?))?;
+    }
+    counts.push(("recipe_components", rows_of_table.len()));
+
+    let mut meals = HashMap::new();
+    let rows_of_table = rows(tables, "meals")?;
+    for row in rows_of_table.iter() {
+        let item = NewMeal {
+            name: row.text("name")?,
+            date: row.date("date")?,
+            time: row.time("time")?,
+            profile_id: row.optional_reference("profile_id", &profiles)?,
+        };
+        meals.insert(row.int("id")?, CRUDMeal::create(conn_mgr, &item)?);
+    }
+    counts.push(("meals", rows_of_table.len()));
+
+    let rows_of_table = rows(tables, "meal_ingredients")?;
+    for row in rows_of_table.iter() {
+        CRUDMealIngredient::create(conn_mgr, &NewMealIngredient {
+            meal_id: row.reference("meal_id", &meals)?,
+            ingredient_id: row.reference("ingredient_id", &ingredients)?,
+            mass: row.float("mass")?,
+            quantity: row.optional_float("quantity")?,
+            unit: row.optional_text("unit")?,
+        })?;
+    }
+    counts.push(("meal_ingredients", rows_of_table.len()));
+
+    let rows_of_table = rows(tables, "goals")?;
+    for row in rows_of_table.iter() {
+        CRUDGoal::create(conn_mgr, &NewGoal {
+            name: row.text("name")?,
+            weekday: row.optional_int("weekday")?,
+            start_date: row.optional_date("start_date")?,
+            end_date: row.optional_date("end_date")?,
+            calories: row.optional_float("calories")?,
+            proteins: row.optional_float("proteins")?,
+            carbs: row.optional_float("carbs")?,
+            fats: row.optional_float("fats")?,
+            alcohols: row.optional_float("alcohols")?,
+            fiber: row.optional_float("fiber")?,
+            polyols: row.optional_float("polyols")?,
+            profile_id: row.optional_reference("profile_id", &profiles)?,
+        })?;
+    }
+    counts.push(("goals", rows_of_table.len()));
+
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    #[test]
+    fn restore_of_dump_remaps_ids_and_reuses_profiles_and_ingredients() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let mut document = Vec::new();
+            dump(&conn_mgr, &mut document).unwrap();
+            let counts = restore(&conn_mgr, document.as_slice()).unwrap();
+            assert_eq!(counts[0], ("profiles", 2));
+            assert_eq!(counts[10], ("meal_ingredients", 2));
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT count(*) FROM profiles; SELECT count(*) FROM micronutrients; \
+                    SELECT count(*) FROM ingredients; \
+                    SELECT meal_id, ingredient_id, mass FROM meal_ingredients WHERE id > 2; \
+                    SELECT ingredient_id, micronutrient_id FROM ingredient_micros WHERE id > 2;")
+                .output()
+                .expect("Failed to execute process");
+            // The ingredient with a barcode exists already and keeps its micros
+            let expected = "2\n4\n3\n3|3|111.0\n4|2|222.0\n3|1\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn restore_reuses_ingredient_of_same_source() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let document = r#"{"format": "nutriplan", "version": 1, "tables": {
+                "ingredients": [{"id": 1, "name": "oats", "source": "fdc", "source_id": "173904"}],
+                "ingredient_portions": [{"id": 1, "ingredient_id": 1, "name": "cup",
+                    "mass": 81.0}]
+            }}"#;
+            restore(&conn_mgr, document.as_bytes()).unwrap();
+            restore(&conn_mgr, document.as_bytes()).unwrap();
+            assert_eq!(CRUDIngredient::list(&conn_mgr, 10, 0).unwrap().len(), 3);
+            let portions = CRUDIngredientPortion::list_by_ingredient_id(&conn_mgr, 3).unwrap();
+            assert_eq!(portions.len(), 1);
+        })
+    }
+
+    #[test]
+    fn restore_rejects_other_version() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let document = r#"{"format": "nutriplan", "version": 99, "tables": {}}"#;
+            let ret_val = restore(&conn_mgr, document.as_bytes());
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+
+    #[test]
+    fn restore_stores_nothing_on_reference_outside_of_dump() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let document = r#"{"format": "nutriplan", "version": 1, "tables": {
+                "ingredients": [{"id": 1, "name": "oats"}],
+                "ingredient_macros": [{"ingredient_id": 7, "proteins": 13.5, "carbs": 58.5,
+                    "fats": 7.0, "alcohols": 0.0, "calories": 372.0, "fiber": 10.0,
+                    "polyols": 0.0}]
+            }}"#;
+            let ret_val = restore(&conn_mgr, document.as_bytes());
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+            assert_eq!(CRUDIngredient::list(&conn_mgr, 10, 0).unwrap().len(), 2);
+        })
+    }
+}
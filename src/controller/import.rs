@@ -0,0 +1,459 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use crate::{
+    controller::database::{ConnMgrPool, CRUDController},
+    controller::database::ingredient::{CRUDIngredient, Ingredient, NewIngredient,
+        normalize_barcode},
+    controller::database::ingredient_macro::{CRUDIngredientMacro, NewIngredientMacro},
+    controller::database::ingredient_micro::{CRUDIngredientMicro, NewIngredientMicro},
+    controller::nutrition::EnergyModel,
+    error::NutriplanError,
+};
+
+pub mod csv;
+pub mod fdc;
+pub mod off;
+
+/**
+ * Macros of a food per 100 g.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FoodMacros {
+    pub proteins: f32,
+    pub carbs: f32,
+    pub fats: f32,
+    pub alcohols: f32,
+    pub fiber: f32,
+    pub polyols: f32,
+    /** Energy in kcal, computed with the Atwater factors if `None` */
+    pub calories: Option<f32>,
+}
+
+/**
+ * Food read from an external source, before it is stored as ingredient.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FoodRecord {
+    pub name: String,
+    pub barcode: Option<String>,
+    /** Density in g/ml */
+    pub density: Option<f32>,
+    /** Macros per 100 g, the ingredient is stored without macros if `None` */
+    pub macros: Option<FoodMacros>,
+    /**
+     * Amounts per 100 g by micronutrient code, in the unit of the micronutrient. Codes without a
+     * micronutrient in the database are skipped.
+     */
+    pub micros: Vec<(String, f32)>,
+    /** Database the record is taken from together with its id there, e.g. `fdc` */
+    pub source: Option<(String, String)>,
+}
+
+/**
+ * Outcome of a single record of an import.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum RowStatus {
+    /** Record is stored as new ingredient */
+    New,
+    /** Record was imported from the same source before, the given ingredient is updated */
+    Updated(i32),
+    /**
+     * Name or barcode is already known. Holds the id of the existing ingredient, `None` if the
+     * record duplicates an earlier record of the same import.
+     */
+    Duplicate(Option<i32>),
+    /** Record could not be read or holds invalid values */
+    Invalid(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportRow {
+    /** Line of the record in the source, or its position for sources without lines, from 1 */
+    pub line: usize,
+    pub name: String,
+    pub status: RowStatus,
+    /** Id of the created or updated ingredient, only set once the import is committed */
+    pub id: Option<i32>,
+}
+
+/**
+ * Result of an import, listing the outcome of every record.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub rows: Vec<ImportRow>,
+    /** Whether the ingredients were stored, false for a dry run or if any record is invalid */
+    pub committed: bool,
+    /** Number of records the source left out, e.g. products not matching a filter */
+    pub skipped: usize,
+}
+
+impl ImportReport {
+    pub fn new_count(&self) -> usize {
+        self.count(|status| *status == RowStatus::New)
+    }
+
+    pub fn updated_count(&self) -> usize {
+        self.count(|status| matches!(status, RowStatus::Updated(_)))
+    }
+
+    pub fn duplicate_count(&self) -> usize {
+        self.count(|status| matches!(status, RowStatus::Duplicate(_)))
+    }
+
+    pub fn invalid_count(&self) -> usize {
+        self.count(|status| matches!(status, RowStatus::Invalid(_)))
+    }
+
+    fn count<F: Fn(&RowStatus) -> bool>(&self, f: F) -> usize {
+        self.rows.iter().filter(|row| f(&row.status)).count()
+    }
+}
+
+/**
+ * Key used to detect duplicate names, which ignores case and surrounding whitespace.
+ */
+fn name_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/**
+ * Validate a record and bring its barcode into the stored form.
+ */
+fn check_record(record: FoodRecord) -> Result<FoodRecord, String> {
+    if record.name.trim().is_empty() {
+        return Err(String::from("name is empty"))
+    }
+    let barcode = match record.barcode.as_deref().map(normalize_barcode).transpose() {
+        Ok(barcode) => barcode,
+        Err(e) => return Err(e.to_string())
+    };
+    if record.density.is_some_and(|density| density <= 0.0 || !density.is_finite()) {
+        return Err(String::from("density must be positive"))
+    }
+    if let Some(macros) = &record.macros {
+        let amounts = [macros.proteins, macros.carbs, macros.fats, macros.alcohols, macros.fiber,
+            macros.polyols, macros.calories.unwrap_or_default()];
+        if amounts.iter().any(|amount| !(*amount >= 0.0 && amount.is_finite())) {
+            return Err(String::from("nutrients must not be negative"))
+        }
+        if macros.proteins + macros.carbs + macros.fats + macros.alcohols > 100.0 {
+            return Err(String::from("macros exceed 100 g per 100 g"))
+        }
+    }
+    if record.micros.iter().any(|(_, amount)| !(*amount >= 0.0 && amount.is_finite())) {
+        return Err(String::from("nutrients must not be negative"))
+    }
+    Ok(FoodRecord { name: record.name.trim().to_owned(), barcode, ..record })
+}
+
+/**
+ * Store foods as ingredients with their macros and micronutrients.
+ *
+ * Records imported from the same source before update their ingredient, its macros and
+ * micronutrients are replaced. Other records whose barcode, or name for records without barcode,
+ * matches an existing ingredient or an earlier record are skipped as duplicates. Nothing is stored
+ * if any record is invalid or if `dry_run` is set, otherwise all ingredients are stored in a
+ * single transaction.
+ *
+ * # Attributes
+ * * `records` - Line of each record in its source together with the record, or the reason it
+ *   could not be read
+ * * `dry_run` - Only report the outcome without storing anything
+ */
+pub fn import_records(conn_mgr: &ConnMgrPool,
+    records: Vec<(usize, Result<FoodRecord, String>)>, dry_run: bool)
+    -> Result<ImportReport, NutriplanError>
+{
+    use crate::schema::ingredients::dsl::*;
+
+    let mut names: HashMap<String, Option<i32>> = HashMap::new();
+    let mut barcodes: HashMap<String, Option<i32>> = HashMap::new();
+    let mut sources: HashMap<(String, String), Option<i32>> = HashMap::new();
+    for (item_id, item_name, item_barcode, item_source, item_source_id) in ingredients
+        .select((id, name, barcode, source, source_id))
+        .load::<(Option<i32>, String, Option<String>, Option<String>, Option<String>)>(conn_mgr)?
+    {
+        names.insert(name_key(&item_name), item_id);
+        if let Some(item_barcode) = item_barcode {
+            barcodes.insert(item_barcode, item_id);
+        }
+        if let (Some(item_source), Some(item_source_id)) = (item_source, item_source_id) {
+            sources.insert((item_source, item_source_id), item_id);
+        }
+    }
+
+    let mut rows = Vec::with_capacity(records.len());
+    let mut accepted = Vec::new();
+    for (line, record) in records {
+        let record = record.and_then(check_record);
+        let (row_name, status) = match record {
+            Err(reason) => (String::new(), RowStatus::Invalid(reason)),
+            Ok(record) => {
+                let status = match record.source.as_ref().and_then(|key| sources.get(key)) {
+                    Some(Some(existing)) => RowStatus::Updated(*existing),
+                    Some(None) => RowStatus::Duplicate(None),
+                    // Products with distinct barcodes are distinct, even if their names match
+                    None => match record.barcode.as_ref()
+                        .map_or_else(|| names.get(&name_key(&record.name)),
+                            |code| barcodes.get(code))
+                    {
+                        Some(existing) => RowStatus::Duplicate(*existing),
+                        None => RowStatus::New
+                    }
+                };
+                if status == RowStatus::New {
+                    names.insert(name_key(&record.name), None);
+                    if let Some(code) = &record.barcode {
+                        barcodes.insert(code.clone(), None);
+                    }
+                }
+                if let Some(key) = record.source.clone() {
+                    // Later records with the same reference are duplicates of this one
+                    sources.insert(key, None);
+                }
+                let row_name = record.name.clone();
+                if !matches!(status, RowStatus::Duplicate(_)) {
+                    accepted.push((rows.len(), record));
+                }
+                (row_name, status)
+            }
+        };
+        rows.push(ImportRow { line, name: row_name, status, id: None });
+    }
+
+    let mut report = ImportReport { rows, ..ImportReport::default() };
+    if dry_run || report.invalid_count() > 0 {
+        return Ok(report)
+    }
+    let micronutrients = load_micronutrients(conn_mgr)?;
+    conn_mgr.transaction(|| {
+        for (row, record) in accepted.iter() {
+            let existing = match report.rows[*row].status {
+                RowStatus::Updated(existing) => Some(existing),
+                _ => None
+            };
+            report.rows[*row].id = Some(store_record(conn_mgr, record, existing,
+                &micronutrients)?);
+        }
+        Ok::<(), NutriplanError>(())
+    })?;
+    report.committed = true;
+    Ok(report)
+}
+
+fn load_micronutrients(conn_mgr: &ConnMgrPool) -> Result<HashMap<String, i32>, NutriplanError> {
+    use crate::schema::micronutrients::dsl::*;
+
+    Ok(micronutrients
+        .select((code, id))
+        .load::<(String, Option<i32>)>(conn_mgr)?
+        .into_iter()
+        .filter_map(|(item_code, item_id)| item_id.map(|item_id| (item_code, item_id)))
+        .collect())
+}
+
+/**
+ * Create an ingredient for the record, or update the existing one and replace the nutrients the
+ * record provides.
+ */
+fn store_record(conn_mgr: &ConnMgrPool, record: &FoodRecord, existing: Option<i32>,
+    micronutrients: &HashMap<String, i32>) -> Result<i32, NutriplanError>
+{
+    let (source, source_id) = match record.source.clone() {
+        Some((source, source_id)) => (Some(source), Some(source_id)),
+        None => (None, None)
+    };
+    let ingredient_id = match existing {
+        Some(ingredient_id) => {
+            // Fields the record does not provide keep their stored value
+            let current = CRUDIngredient::read(conn_mgr, ingredient_id)?;
+            let ingredient = Ingredient {
+                id: Some(ingredient_id),
+                name: record.name.clone(),
+                density: record.density.or(current.density),
+                profile_id: current.profile_id,
+                barcode: record.barcode.clone().or(current.barcode),
+                source: source.or(current.source),
+                source_id: source_id.or(current.source_id)
+            };
+            CRUDIngredient::update(conn_mgr, ingredient_id, ingredient)?;
+            if record.macros.is_some() {
+                CRUDIngredientMacro::delete_by_ingredient_id(conn_mgr, ingredient_id)?;
+            }
+            if !record.micros.is_empty() {
+                CRUDIngredientMicro::delete_by_ingredient_id(conn_mgr, ingredient_id)?;
+            }
+            ingredient_id
+        },
+        None => {
+            let ingredient = NewIngredient {
+                barcode: record.barcode.clone(),
+                source,
+                source_id,
+                ..NewIngredient::with_density(&record.name, record.density)
+            };
+            CRUDIngredient::create(conn_mgr, &ingredient)?
+        }
+    };
+    for (micro_code, amount) in record.micros.iter() {
+        if let Some(micronutrient_id) = micronutrients.get(micro_code) {
+            CRUDIngredientMicro::create(conn_mgr,
+                &NewIngredientMicro::new(ingredient_id, *micronutrient_id, *amount))?;
+        }
+    }
+    if let Some(macros) = &record.macros {
+        let mut item = NewIngredientMacro::with_model(ingredient_id, macros.proteins,
+            macros.carbs, macros.fats, macros.alcohols, macros.fiber, macros.polyols,
+            &EnergyModel::default());
+        if let Some(calories) = macros.calories {
+            item.calories = calories;
+        }
+        CRUDIngredientMacro::create(conn_mgr, &item)?;
+    }
+    Ok(ingredient_id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    fn food(name: &str, barcode: Option<&str>) -> FoodRecord {
+        FoodRecord {
+            name: name.to_owned(),
+            barcode: barcode.map(str::to_owned),
+            macros: Some(FoodMacros { proteins: 10.0, carbs: 20.0, fats: 5.0,
+                ..FoodMacros::default() }),
+            ..FoodRecord::default()
+        }
+    }
+
+    #[test]
+    fn import_records_skips_duplicates_of_database_and_earlier_records() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let records = vec![
+                (2, Ok(food("Oats", Some("96385074")))),
+                (3, Ok(food(" TEST1 ", None))),
+                (4, Ok(food("oats", None))),
+                (5, Ok(food("rolled oats", Some("96385074")))),
+                (6, Ok(food("oats", Some("40123455")))),
+            ];
+            let report = import_records(&conn_mgr, records, false).unwrap();
+            let statuses: Vec<RowStatus> = report.rows.iter()
+                .map(|row| row.status.clone())
+                .collect();
+            assert_eq!(statuses, vec![RowStatus::New, RowStatus::Duplicate(Some(1)),
+                RowStatus::Duplicate(None), RowStatus::Duplicate(None), RowStatus::New]);
+            assert!(report.committed);
+            assert_eq!(report.rows[0].id, Some(3));
+        })
+    }
+
+    #[test]
+    fn import_records_stores_macros_per_100g() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let report = import_records(&conn_mgr, vec![(2, Ok(food("oats", None)))], false)
+                .unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT proteins, carbs, fats, calories FROM ingredient_macros \
+                    WHERE ingredient_id={};", report.rows[0].id.unwrap()))
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("10.0|20.0|5.0|165.0\n", str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn import_records_updates_ingredient_of_same_source() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let record = |proteins: f32| FoodRecord {
+                source: Some((String::from("fdc"), String::from("42"))),
+                macros: Some(FoodMacros { proteins, ..FoodMacros::default() }),
+                micros: vec![(String::from("sugars"), 4.5), (String::from("unknown"), 1.0)],
+                ..food("oats", None)
+            };
+            let report = import_records(&conn_mgr, vec![(1, Ok(record(10.0)))], false).unwrap();
+            let created_id = report.rows[0].id.unwrap();
+            let report = import_records(&conn_mgr, vec![(1, Ok(record(12.0)))], false).unwrap();
+            assert_eq!(report.rows[0].status, RowStatus::Updated(created_id));
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT proteins FROM ingredient_macros WHERE ingredient_id={0}; \
+                    SELECT amount FROM ingredient_micros WHERE ingredient_id={0}; \
+                    SELECT source, source_id FROM ingredients WHERE id={0};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("12.0\n4.5\nfdc|42\n", str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn import_records_keeps_nutrients_a_re_import_does_not_provide() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let record = FoodRecord {
+                source: Some((String::from("off"), String::from("96385074"))),
+                micros: vec![(String::from("sugars"), 4.5)],
+                ..food("oats", None)
+            };
+            let report = import_records(&conn_mgr, vec![(1, Ok(record.clone()))], false)
+                .unwrap();
+            let created_id = report.rows[0].id.unwrap();
+            let record = FoodRecord { macros: None, micros: Vec::new(), ..record };
+            let report = import_records(&conn_mgr, vec![(1, Ok(record))], false).unwrap();
+            assert_eq!(report.rows[0].status, RowStatus::Updated(created_id));
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg(format!("SELECT proteins, carbs, fats FROM ingredient_macros \
+                    WHERE ingredient_id={0}; \
+                    SELECT amount FROM ingredient_micros WHERE ingredient_id={0};", created_id))
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("10.0|20.0|5.0\n4.5\n", str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn import_records_stores_nothing_if_any_record_is_invalid() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let records = vec![
+                (2, Ok(food("oats", None))),
+                (3, Ok(food("unknown code", Some("96385075")))),
+                (4, Err(String::from("could not parse proteins"))),
+            ];
+            let report = import_records(&conn_mgr, records, false).unwrap();
+            assert_eq!(report.invalid_count(), 2);
+            assert!(!report.committed);
+            assert!(CRUDIngredient::list(&conn_mgr, 10, 0).unwrap().len() == 2);
+        })
+    }
+
+    #[test]
+    fn import_records_dry_run_stores_nothing() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let report = import_records(&conn_mgr, vec![(2, Ok(food("oats", None)))], true)
+                .unwrap();
+            assert_eq!(report.new_count(), 1);
+            assert_eq!(report.rows[0].id, None);
+            assert!(!report.committed);
+            assert!(CRUDIngredient::list(&conn_mgr, 10, 0).unwrap().len() == 2);
+        })
+    }
+}
@@ -0,0 +1,230 @@
+use std::io::Read;
+
+use crate::{
+    controller::database::ConnMgrPool,
+    controller::import::{FoodMacros, FoodRecord, ImportReport, import_records},
+    error::NutriplanError,
+};
+
+/**
+ * Header names of the columns holding the fields of an ingredient. Columns which are missing in
+ * a file are left empty, except for the name, which is required.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnMapping {
+    pub name: String,
+    pub barcode: String,
+    /** Density in g/ml */
+    pub density: String,
+    /** Macros in g per 100 g */
+    pub proteins: String,
+    pub carbs: String,
+    pub fats: String,
+    pub alcohols: String,
+    pub fiber: String,
+    pub polyols: String,
+    /** Energy in kcal per 100 g, computed from the macros if the column is missing or empty */
+    pub calories: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            name: String::from("name"),
+            barcode: String::from("barcode"),
+            density: String::from("density"),
+            proteins: String::from("proteins"),
+            carbs: String::from("carbs"),
+            fats: String::from("fats"),
+            alcohols: String::from("alcohols"),
+            fiber: String::from("fiber"),
+            polyols: String::from("polyols"),
+            calories: String::from("calories"),
+        }
+    }
+}
+
+impl ColumnMapping {
+    /**
+     * Map a field to another column, e.g. `proteins` to `Protein (g)`.
+     */
+    pub fn set(&mut self, field: &str, column: &str) -> Result<(), NutriplanError> {
+        let target = match field {
+            "name" => &mut self.name,
+            "barcode" => &mut self.barcode,
+            "density" => &mut self.density,
+            "proteins" => &mut self.proteins,
+            "carbs" => &mut self.carbs,
+            "fats" => &mut self.fats,
+            "alcohols" => &mut self.alcohols,
+            "fiber" => &mut self.fiber,
+            "polyols" => &mut self.polyols,
+            "calories" => &mut self.calories,
+            _ => return Err(NutriplanError::InvalidInput(format!("unknown field {}", field)))
+        };
+        *target = column.to_owned();
+        Ok(())
+    }
+}
+
+/**
+ * Layout of a CSV file with one ingredient per row and a header row.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvFormat {
+    pub delimiter: u8,
+    pub columns: ColumnMapping,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        CsvFormat { delimiter: b',', columns: ColumnMapping::default() }
+    }
+}
+
+/**
+ * Position of each mapped column within the header.
+ */
+struct ColumnIndex {
+    name: usize,
+    barcode: Option<usize>,
+    density: Option<usize>,
+    macros: [Option<usize>; 6],
+    calories: Option<usize>,
+}
+
+impl ColumnIndex {
+    fn new(header: &::csv::StringRecord, columns: &ColumnMapping)
+        -> Result<Self, NutriplanError>
+    {
+        let find = |column: &str| header.iter().position(|cell| cell.trim() == column);
+        Ok(ColumnIndex {
+            name: find(&columns.name).ok_or_else(|| NutriplanError::InvalidInput(
+                format!("column {} not found", columns.name)))?,
+            barcode: find(&columns.barcode),
+            density: find(&columns.density),
+            macros: [find(&columns.proteins), find(&columns.carbs), find(&columns.fats),
+                find(&columns.alcohols), find(&columns.fiber), find(&columns.polyols)],
+            calories: find(&columns.calories),
+        })
+    }
+
+    fn record(&self, row: &::csv::StringRecord) -> Result<FoodRecord, String> {
+        let cell = |pos: Option<usize>| pos
+            .and_then(|pos| row.get(pos))
+            .map(str::trim)
+            .filter(|cell| !cell.is_empty());
+        let number = |pos: Option<usize>| cell(pos).map(parse_number).transpose();
+        let amounts = self.macros.iter()
+            .map(|pos| number(*pos))
+            .collect::<Result<Vec<Option<f32>>, String>>()?;
+        let calories = number(self.calories)?;
+        // Rows without any nutrient only create the ingredient
+        let macros = match amounts.iter().any(Option::is_some) || calories.is_some() {
+            true => Some(FoodMacros {
+                proteins: amounts[0].unwrap_or_default(),
+                carbs: amounts[1].unwrap_or_default(),
+                fats: amounts[2].unwrap_or_default(),
+                alcohols: amounts[3].unwrap_or_default(),
+                fiber: amounts[4].unwrap_or_default(),
+                polyols: amounts[5].unwrap_or_default(),
+                calories,
+            }),
+            false => None
+        };
+        Ok(FoodRecord {
+            name: cell(Some(self.name)).unwrap_or_default().to_owned(),
+            barcode: cell(self.barcode).map(str::to_owned),
+            density: number(self.density)?,
+            macros,
+            ..FoodRecord::default()
+        })
+    }
+}
+
+/**
+ * Parse a number, which may use a decimal comma as exported by many spreadsheets.
+ */
+fn parse_number(cell: &str) -> Result<f32, String> {
+    cell.replace(',', ".")
+        .parse::<f32>()
+        .map_err(|_| format!("could not parse number {}", cell))
+}
+
+/**
+ * Import ingredients and their macros from a CSV file, see `import_records` for the handling of
+ * duplicates and invalid rows.
+ */
+pub fn import_csv<R: Read>(conn_mgr: &ConnMgrPool, reader: R, format: &CsvFormat,
+    dry_run: bool) -> Result<ImportReport, NutriplanError>
+{
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(format.delimiter)
+        .flexible(true)
+        .from_reader(reader);
+    let columns = ColumnIndex::new(reader.headers()?, &format.columns)?;
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row?;
+        let line = row.position().map(|pos| pos.line() as usize).unwrap_or_default();
+        records.push((line, columns.record(&row)));
+    }
+    import_records(conn_mgr, records, dry_run)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::import::RowStatus;
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    #[test]
+    fn import_csv_reads_mapped_columns() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let mut format = CsvFormat { delimiter: b';', ..CsvFormat::default() };
+            format.columns.set("name", "Lebensmittel").unwrap();
+            format.columns.set("proteins", "Eiweiß").unwrap();
+            let file = "Lebensmittel;Eiweiß;carbs;fats;barcode\n\
+                Haferflocken;13,5;58,5;7;96385074\n\
+                Salz;;;;\n";
+            let report = import_csv(&conn_mgr, file.as_bytes(), &format, false).unwrap();
+            assert_eq!(report.new_count(), 2);
+            assert_eq!(report.rows[0].line, 2);
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT i.name, i.barcode, m.proteins, m.carbs, m.fats FROM ingredients i \
+                    LEFT JOIN ingredient_macros m ON m.ingredient_id = i.id WHERE i.id > 2;")
+                .output()
+                .expect("Failed to execute process");
+            let expected = "Haferflocken|96385074|13.5|58.5|7.0\nSalz||||\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn import_csv_reports_unparsable_rows() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let file = "name,proteins\noats,13.5\nrice,lots\n";
+            let report = import_csv(&conn_mgr, file.as_bytes(), &CsvFormat::default(), false)
+                .unwrap();
+            assert_eq!(report.rows[1].line, 3);
+            assert!(matches!(report.rows[1].status, RowStatus::Invalid(_)));
+            assert!(!report.committed);
+        })
+    }
+
+    #[test]
+    fn import_csv_without_name_column_returns_invalid_input() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let file = "food,proteins\noats,13.5\n";
+            let ret_val = import_csv(&conn_mgr, file.as_bytes(), &CsvFormat::default(), false);
+            assert!(matches!(ret_val, Err(NutriplanError::InvalidInput(_))));
+        })
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{
+    controller::database::ConnMgrPool,
+    controller::import::{FoodMacros, FoodRecord, ImportReport, import_records},
+    error::NutriplanError,
+};
+
+/**
+ * Source name stored with ingredients imported from USDA FoodData Central.
+ */
+pub const SOURCE: &str = "fdc";
+
+/** Data types of the CSV dump holding reference foods, other types are skipped */
+const DATA_TYPES: [&str; 2] = ["foundation_food", "sr_legacy_food"];
+
+/** FDC nutrient numbers of the micronutrients, amounts are given in the unit of the code */
+const MICROS: [(&str, &str); 10] = [
+    ("269", "sugars"),
+    ("269.3", "sugars"),
+    ("606", "saturates"),
+    ("307", "sodium"),
+    ("320", "vitamin_a"),
+    ("401", "vitamin_c"),
+    ("328", "vitamin_d"),
+    ("301", "calcium"),
+    ("303", "iron"),
+    ("306", "potassium"),
+];
+
+/** FDC nutrient numbers used for the macros */
+const PROTEINS: &str = "203";
+const FATS: &str = "204";
+const CARBS: &str = "205";
+const CARBS_BY_SUMMATION: &str = "205.2";
+const ALCOHOLS: &str = "221";
+const FIBER: &str = "291";
+/** Energy in kcal, followed by the Atwater specific and general energy of foundation foods */
+const ENERGY: [&str; 3] = ["208", "958", "957"];
+
+/**
+ * Bring a nutrient number into the form used above, the CSV dump writes `203.0` for `203`.
+ */
+fn nutrient_number(number: &str) -> String {
+    let number = number.trim();
+    number.strip_suffix(".0").unwrap_or(number).to_owned()
+}
+
+fn is_used(number: &str) -> bool {
+    [PROTEINS, FATS, CARBS, CARBS_BY_SUMMATION, ALCOHOLS, FIBER].contains(&number)
+        || ENERGY.contains(&number)
+        || MICROS.iter().any(|(micro, _)| *micro == number)
+}
+
+/**
+ * Map a food with its nutrients per 100 g, keyed by nutrient number.
+ */
+fn food_record(fdc_id: &str, description: &str, nutrients: &HashMap<String, f32>)
+    -> FoodRecord
+{
+    let amount = |number: &str| nutrients.get(number).copied();
+    let fiber = amount(FIBER).unwrap_or_default();
+    // FDC counts fiber as carbohydrate, it is stored separately here
+    let carbs = amount(CARBS).or_else(|| amount(CARBS_BY_SUMMATION))
+        .map(|carbs| (carbs - fiber).max(0.0));
+    let macros = match (amount(PROTEINS), carbs, amount(FATS)) {
+        (None, None, None) => None,
+        (proteins, carbs, fats) => Some(FoodMacros {
+            proteins: proteins.unwrap_or_default(),
+            carbs: carbs.unwrap_or_default(),
+            fats: fats.unwrap_or_default(),
+            alcohols: amount(ALCOHOLS).unwrap_or_default(),
+            fiber,
+            polyols: 0.0,
+            calories: ENERGY.iter().find_map(|number| amount(number)),
+        })
+    };
+    let mut micros: Vec<(String, f32)> = Vec::new();
+    for (number, code) in MICROS.iter() {
+        if let Some(value) = amount(number) {
+            if !micros.iter().any(|(known, _)| known == code) {
+                micros.push((code.to_string(), value));
+            }
+        }
+    }
+    if let Some(sodium) = amount("307") {
+        micros.push((String::from("salt"), sodium * 2.5 / 1000.0));
+    }
+    FoodRecord {
+        name: description.trim().to_owned(),
+        macros,
+        micros,
+        source: Some((SOURCE.to_owned(), fdc_id.to_owned())),
+        ..FoodRecord::default()
+    }
+}
+
+fn json_food(food: &Value) -> Result<FoodRecord, String> {
+    let fdc_id = food.get("fdcId")
+        .and_then(Value::as_i64)
+        .ok_or_else(|| String::from("fdcId is missing"))?;
+    let description = food.get("description")
+        .and_then(Value::as_str)
+        .ok_or_else(|| String::from("description is missing"))?;
+    let nutrients = food.get("foodNutrients")
+        .and_then(Value::as_array)
+        .map(|nutrients| nutrients.iter()
+            .filter_map(|item| {
+                let number = item.get("nutrient")?.get("number")?.as_str()?;
+                let amount = item.get("amount")?.as_f64()?;
+                Some((nutrient_number(number), amount as f32))
+            })
+            .collect())
+        .unwrap_or_default();
+    Ok(food_record(&fdc_id.to_string(), description, &nutrients))
+}
+
+/**
+ * Import the foods of a FoodData Central JSON download, e.g. Foundation Foods or SR Legacy. Foods
+ * imported before are updated, see `import_records`.
+ */
+pub fn import_fdc_json<R: Read>(conn_mgr: &ConnMgrPool, reader: R, dry_run: bool)
+    -> Result<ImportReport, NutriplanError>
+{
+    let root: Value = serde_json::from_reader(reader)
+        .map_err(|e| NutriplanError::InvalidInput(format!("could not parse FDC file: {}", e)))?;
+    // Downloads hold a single list of foods named after the data type, e.g. `SRLegacyFoods`
+    let foods = match &root {
+        Value::Array(foods) => Some(foods),
+        Value::Object(items) => items.values().find_map(Value::as_array),
+        _ => None
+    }.ok_or_else(|| NutriplanError::InvalidInput(String::from("FDC file holds no foods")))?;
+    let records = foods.iter()
+        .enumerate()
+        .map(|(pos, food)| (pos + 1, json_food(food)))
+        .collect();
+    import_records(conn_mgr, records, dry_run)
+}
+
+/**
+ * Open a file of the CSV download and find the position of the given columns.
+ */
+fn open_csv(dir: &Path, file: &str, columns: &[&str])
+    -> Result<(::csv::Reader<std::fs::File>, Vec<usize>), NutriplanError>
+{
+    let mut reader = ::csv::Reader::from_path(dir.join(file))?;
+    let header = reader.headers()?.clone();
+    let positions = columns.iter()
+        .map(|column| header.iter().position(|cell| cell == *column).ok_or_else(|| {
+            NutriplanError::InvalidInput(format!("column {} not found in {}", column, file))
+        }))
+        .collect::<Result<Vec<usize>, NutriplanError>>()?;
+    Ok((reader, positions))
+}
+
+/**
+ * Import the reference foods of a FoodData Central CSV download. The directory has to hold the
+ * files `food.csv`, `nutrient.csv` and `food_nutrient.csv`, only foundation and SR Legacy foods
+ * are imported. Foods imported before are updated, see `import_records`.
+ */
+pub fn import_fdc_csv(conn_mgr: &ConnMgrPool, dir: &Path, dry_run: bool)
+    -> Result<ImportReport, NutriplanError>
+{
+    let (mut reader, columns) = open_csv(dir, "food.csv",
+        &["fdc_id", "data_type", "description"])?;
+    let mut foods = Vec::new();
+    for row in reader.records() {
+        let row = row?;
+        if DATA_TYPES.contains(&row.get(columns[1]).unwrap_or_default()) {
+            let line = row.position().map(|pos| pos.line() as usize).unwrap_or_default();
+            foods.push((line, row.get(columns[0]).unwrap_or_default().to_owned(),
+                row.get(columns[2]).unwrap_or_default().to_owned()));
+        }
+    }
+    let fdc_ids: HashSet<&str> = foods.iter().map(|(_, fdc_id, _)| fdc_id.as_str()).collect();
+
+    let (mut reader, columns) = open_csv(dir, "nutrient.csv", &["id", "nutrient_nbr"])?;
+    let mut numbers = HashMap::new();
+    for row in reader.records() {
+        let row = row?;
+        let number = nutrient_number(row.get(columns[1]).unwrap_or_default());
+        if is_used(&number) {
+            numbers.insert(row.get(columns[0]).unwrap_or_default().to_owned(), number);
+        }
+    }
+
+    let (mut reader, columns) = open_csv(dir, "food_nutrient.csv",
+        &["fdc_id", "nutrient_id", "amount"])?;
+    let mut nutrients: HashMap<String, HashMap<String, f32>> = HashMap::new();
+    let mut invalid: HashMap<String, String> = HashMap::new();
+    for row in reader.records() {
+        let row = row?;
+        let fdc_id = row.get(columns[0]).unwrap_or_default();
+        let number = match numbers.get(row.get(columns[1]).unwrap_or_default()) {
+            Some(number) if fdc_ids.contains(fdc_id) => number,
+            _ => continue
+        };
+        let amount = row.get(columns[2]).unwrap_or_default();
+        match amount.parse::<f32>() {
+            Ok(amount) => {
+                nutrients.entry(fdc_id.to_owned()).or_default().insert(number.clone(), amount);
+            },
+            Err(_) => {
+                invalid.insert(fdc_id.to_owned(), format!("could not parse amount {}", amount));
+            }
+        }
+    }
+
+    let empty = HashMap::new();
+    let records = foods.iter()
+        .map(|(line, fdc_id, description)| match invalid.get(fdc_id) {
+            Some(reason) => (*line, Err(reason.clone())),
+            None => (*line, Ok(food_record(fdc_id, description,
+                nutrients.get(fdc_id).unwrap_or(&empty))))
+        })
+        .collect();
+    import_records(conn_mgr, records, dry_run)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::import::RowStatus;
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    const FOODS: &str = r#"{"SRLegacyFoods": [
+        {"fdcId": 173904, "description": "Cereals, oats, regular and quick, not fortified, dry",
+         "foodNutrients": [
+            {"nutrient": {"id": 1003, "number": "203", "unitName": "g"}, "amount": 13.2},
+            {"nutrient": {"id": 1004, "number": "204", "unitName": "g"}, "amount": 6.5},
+            {"nutrient": {"id": 1005, "number": "205", "unitName": "g"}, "amount": 67.7},
+            {"nutrient": {"id": 1079, "number": "291", "unitName": "g"}, "amount": 10.1},
+            {"nutrient": {"id": 1008, "number": "208", "unitName": "kcal"}, "amount": 379},
+            {"nutrient": {"id": 1089, "number": "303", "unitName": "mg"}, "amount": 4.25}
+         ]},
+        {"fdcId": 174158, "description": "Salt, table",
+         "foodNutrients": [
+            {"nutrient": {"id": 1093, "number": "307", "unitName": "mg"}, "amount": 38758}
+         ]}
+    ]}"#;
+
+    #[test]
+    fn json_food_maps_nutrients() {
+        let root: Value = serde_json::from_str(FOODS).unwrap();
+        let record = json_food(&root["SRLegacyFoods"][0]).unwrap();
+        let macros = record.macros.unwrap();
+        assert_eq!(macros.proteins, 13.2);
+        assert!((macros.carbs - 57.6).abs() < 1e-4);
+        assert_eq!(macros.fiber, 10.1);
+        assert_eq!(macros.calories, Some(379.0));
+        assert_eq!(record.micros, vec![(String::from("iron"), 4.25)]);
+        assert_eq!(record.source, Some((String::from("fdc"), String::from("173904"))));
+        let record = json_food(&root["SRLegacyFoods"][1]).unwrap();
+        assert_eq!(record.macros, None);
+        assert!(record.micros.contains(&(String::from("salt"), 96.895)));
+    }
+
+    #[test]
+    fn import_fdc_json_updates_foods_on_second_import() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let report = import_fdc_json(&conn_mgr, FOODS.as_bytes(), false).unwrap();
+            assert_eq!(report.new_count(), 2);
+            assert_eq!(report.rows[1].line, 2);
+            let report = import_fdc_json(&conn_mgr, FOODS.as_bytes(), false).unwrap();
+            let statuses: Vec<RowStatus> = report.rows.iter()
+                .map(|row| row.status.clone())
+                .collect();
+            assert_eq!(statuses, vec![RowStatus::Updated(3), RowStatus::Updated(4)]);
+        })
+    }
+
+    #[test]
+    fn import_fdc_csv_reads_reference_foods_of_dump() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let report = import_fdc_csv(&conn_mgr, Path::new("test/fdc"), false).unwrap();
+            assert_eq!(report.new_count(), 2);
+            assert_eq!(report.rows[1].line, 4);
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT i.source_id, round(m.proteins, 1), round(m.carbs, 1), m.calories \
+                    FROM ingredients i \
+                    LEFT JOIN ingredient_macros m ON m.ingredient_id = i.id WHERE i.id > 2; \
+                    SELECT count(*) FROM ingredient_micros WHERE ingredient_id > 2;")
+                .output()
+                .expect("Failed to execute process");
+            // Iron is not known to the test database, salt is derived from sodium
+            let expected = "173904|13.2|57.6|379.0\n174158|||\n2\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+use serde_json::Value;
+
+use crate::{
+    controller::database::ConnMgrPool,
+    controller::database::ingredient::normalize_barcode,
+    controller::import::{FoodMacros, FoodRecord, ImportReport, check_record, import_records},
+    error::NutriplanError,
+};
+
+/**
+ * Source name stored with ingredients imported from Open Food Facts, the id is the barcode.
+ */
+pub const SOURCE: &str = "off";
+
+/**
+ * Micronutrients by nutriment of the export, which gives all amounts in g, with the factor to the
+ * unit of the micronutrient.
+ */
+const MICROS: [(&str, &str, f32); 10] = [
+    ("sugars", "sugars", 1.0),
+    ("saturated-fat", "saturates", 1.0),
+    ("salt", "salt", 1.0),
+    ("sodium", "sodium", 1e3),
+    ("vitamin-a", "vitamin_a", 1e6),
+    ("vitamin-c", "vitamin_c", 1e3),
+    ("vitamin-d", "vitamin_d", 1e6),
+    ("calcium", "calcium", 1e3),
+    ("iron", "iron", 1e3),
+    ("potassium", "potassium", 1e3),
+];
+
+/** Density of ethanol in g/ml, the export gives alcohol in % vol */
+const ETHANOL_DENSITY: f32 = 0.789;
+const KJ_PER_KCAL: f32 = 4.184;
+
+/**
+ * Products of an export to import, `None` matches every product.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OffFilter {
+    /** Country the product is sold in, as tag like `en:germany` or as name like `Germany` */
+    pub country: Option<String>,
+    /**
+     * Language of the product, e.g. `de`. Matches products with this main language or with a
+     * name in it, which is preferred over the name in the main language.
+     */
+    pub language: Option<String>,
+}
+
+impl OffFilter {
+    /**
+     * Bring a country into the form of the tags of the export, e.g. `United Kingdom` into
+     * `en:united-kingdom`.
+     */
+    fn country_tag(&self) -> Option<String> {
+        self.country.as_ref().map(|country| {
+            let tag = country.trim().to_lowercase().replace(' ', "-");
+            match tag.contains(':') {
+                true => tag,
+                false => format!("en:{}", tag)
+            }
+        })
+    }
+}
+
+/**
+ * Product of an export with its nutriments per 100 g, keyed without the `_100g` suffix.
+ */
+#[derive(Debug, Default)]
+struct Product {
+    code: String,
+    lang: String,
+    /** Name in the main language */
+    name: String,
+    /** Names by language */
+    names: HashMap<String, String>,
+    countries: Vec<String>,
+    nutriments: HashMap<String, f32>,
+}
+
+impl Product {
+    fn matches(&self, filter: &OffFilter, country_tag: Option<&str>) -> bool {
+        country_tag.is_none_or(|tag| self.countries.iter().any(|country| country == tag))
+            && filter.language.as_deref().is_none_or(|language| {
+                self.lang == language || self.names.contains_key(language)
+            })
+    }
+
+    /**
+     * Map the product onto a record, `None` if it lacks a valid barcode or a name.
+     */
+    fn record(&self, filter: &OffFilter) -> Option<FoodRecord> {
+        let barcode = normalize_barcode(&self.code).ok()?;
+        let name = filter.language.as_ref()
+            .and_then(|language| self.names.get(language))
+            .unwrap_or(&self.name)
+            .trim();
+        if name.is_empty() {
+            return None
+        }
+        let amount = |nutriment: &str| self.nutriments.get(nutriment).copied();
+        let macros = match (amount("proteins"), amount("carbohydrates"), amount("fat")) {
+            (None, None, None) => None,
+            // Carbohydrates of the export exclude fiber, as stored here
+            (proteins, carbs, fats) => Some(FoodMacros {
+                proteins: proteins.unwrap_or_default(),
+                carbs: carbs.unwrap_or_default(),
+                fats: fats.unwrap_or_default(),
+                alcohols: amount("alcohol").map(|alcohol| alcohol * ETHANOL_DENSITY)
+                    .unwrap_or_default(),
+                fiber: amount("fiber").unwrap_or_default(),
+                polyols: amount("polyols").unwrap_or_default(),
+                calories: amount("energy-kcal")
+                    .or_else(|| amount("energy-kj").map(|energy| energy / KJ_PER_KCAL)),
+            })
+        };
+        let micros = MICROS.iter()
+            .filter_map(|(nutriment, micro, factor)| {
+                amount(nutriment).map(|value| (micro.to_string(), value * factor))
+            })
+            .collect();
+        Some(FoodRecord {
+            name: name.to_owned(),
+            barcode: Some(barcode.clone()),
+            macros,
+            micros,
+            source: Some((SOURCE.to_owned(), barcode)),
+            ..FoodRecord::default()
+        })
+    }
+}
+
+/**
+ * Collects the products of an export, leaving out products which do not match the filter or
+ * cannot be imported.
+ */
+struct Products<'a> {
+    filter: &'a OffFilter,
+    country_tag: Option<String>,
+    records: Vec<(usize, Result<FoodRecord, String>)>,
+    skipped: usize,
+}
+
+impl<'a> Products<'a> {
+    fn new(filter: &'a OffFilter) -> Self {
+        Products { filter, country_tag: filter.country_tag(), records: Vec::new(), skipped: 0 }
+    }
+
+    fn push(&mut self, line: usize, product: Result<Product, String>) {
+        // The export holds many unreadable, incomplete or implausible products, which would
+        // otherwise prevent the import of all other products
+        let product = match product {
+            Ok(product) if product.matches(self.filter, self.country_tag.as_deref()) => product,
+            _ => {
+                self.skipped += 1;
+                return
+            }
+        };
+        match product.record(self.filter).filter(|record| check_record(record.clone()).is_ok()) {
+            Some(record) => self.records.push((line, Ok(record))),
+            None => self.skipped += 1
+        }
+    }
+
+    fn import(self, conn_mgr: &ConnMgrPool, dry_run: bool)
+        -> Result<ImportReport, NutriplanError>
+    {
+        let mut report = import_records(conn_mgr, self.records, dry_run)?;
+        report.skipped = self.skipped;
+        Ok(report)
+    }
+}
+
+/**
+ * Position of the used columns within the header of the CSV export.
+ */
+struct CsvColumns {
+    code: usize,
+    name: usize,
+    lang: Option<usize>,
+    countries: Option<usize>,
+    names: Vec<(String, usize)>,
+    nutriments: Vec<(String, usize)>,
+}
+
+impl CsvColumns {
+    fn new(header: &::csv::StringRecord) -> Result<Self, NutriplanError> {
+        let find = |column: &str| header.iter().position(|cell| cell == column);
+        let required = |column: &str| find(column).ok_or_else(|| {
+            NutriplanError::InvalidInput(format!("column {} not found", column))
+        });
+        let mut columns = CsvColumns {
+            code: required("code")?,
+            name: required("product_name")?,
+            lang: find("lang"),
+            countries: find("countries_tags"),
+            names: Vec::new(),
+            nutriments: Vec::new(),
+        };
+        for (pos, cell) in header.iter().enumerate() {
+            if let Some(nutriment) = cell.strip_suffix("_100g") {
+                columns.nutriments.push((nutriment.to_owned(), pos));
+            } else if let Some(language) = cell.strip_prefix("product_name_") {
+                columns.names.push((language.to_owned(), pos));
+            }
+        }
+        Ok(columns)
+    }
+
+    fn product(&self, row: &::csv::StringRecord) -> Result<Product, String> {
+        let cell = |pos: usize| row.get(pos).map(str::trim).unwrap_or_default();
+        let mut product = Product {
+            code: cell(self.code).to_owned(),
+            name: cell(self.name).to_owned(),
+            lang: self.lang.map(cell).unwrap_or_default().to_owned(),
+            countries: self.countries.map(cell).unwrap_or_default()
+                .split(',')
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            ..Product::default()
+        };
+        for (language, pos) in self.names.iter() {
+            if !cell(*pos).is_empty() {
+                product.names.insert(language.clone(), cell(*pos).to_owned());
+            }
+        }
+        for (nutriment, pos) in self.nutriments.iter() {
+            let value = cell(*pos);
+            if !value.is_empty() {
+                let value = value.parse::<f32>()
+                    .map_err(|_| format!("could not parse {} of {}", nutriment, product.code))?;
+                product.nutriments.insert(nutriment.clone(), value);
+            }
+        }
+        Ok(product)
+    }
+}
+
+/**
+ * Import the products of the tab separated CSV export of Open Food Facts. Products imported
+ * before are updated, products without valid barcode, name or plausible nutriments are skipped.
+ */
+pub fn import_off_csv<R: Read>(conn_mgr: &ConnMgrPool, reader: R, filter: &OffFilter,
+    dry_run: bool) -> Result<ImportReport, NutriplanError>
+{
+    // Cells of the export are not quoted, quotes are part of product names
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .flexible(true)
+        .from_reader(reader);
+    let columns = CsvColumns::new(reader.headers()?)?;
+    let mut products = Products::new(filter);
+    for row in reader.records() {
+        let row = row?;
+        let line = row.position().map(|pos| pos.line() as usize).unwrap_or_default();
+        products.push(line, columns.product(&row));
+    }
+    products.import(conn_mgr, dry_run)
+}
+
+fn json_product(line: &str) -> Result<Product, String> {
+    let item: Value = serde_json::from_str(line)
+        .map_err(|e| format!("could not parse product: {}", e))?;
+    let text = |key: &str| item.get(key).and_then(Value::as_str).unwrap_or_default().to_owned();
+    let mut product = Product {
+        code: text("code"),
+        name: text("product_name"),
+        lang: text("lang"),
+        countries: item.get("countries_tags")
+            .and_then(Value::as_array)
+            .map(|tags| tags.iter().filter_map(Value::as_str).map(str::to_owned).collect())
+            .unwrap_or_default(),
+        ..Product::default()
+    };
+    if let Some(fields) = item.as_object() {
+        for (key, value) in fields.iter() {
+            let name = value.as_str().filter(|name| !name.trim().is_empty());
+            if let (Some(language), Some(name)) = (key.strip_prefix("product_name_"), name) {
+                product.names.insert(language.to_owned(), name.to_owned());
+            }
+        }
+    }
+    if let Some(nutriments) = item.get("nutriments").and_then(Value::as_object) {
+        for (key, value) in nutriments.iter() {
+            // Amounts are numbers, but some products hold them as strings
+            let amount = value.as_f64()
+                .or_else(|| value.as_str().and_then(|value| value.trim().parse().ok()));
+            if let (Some(nutriment), Some(amount)) = (key.strip_suffix("_100g"), amount) {
+                product.nutriments.insert(nutriment.to_owned(), amount as f32);
+            }
+        }
+    }
+    Ok(product)
+}
+
+/**
+ * Import the products of the JSONL export of Open Food Facts, which holds one product per line.
+ * Products imported before are updated, products without valid barcode, name or plausible
+ * nutriments are skipped.
+ */
+pub fn import_off_jsonl<R: Read>(conn_mgr: &ConnMgrPool, reader: R, filter: &OffFilter,
+    dry_run: bool) -> Result<ImportReport, NutriplanError>
+{
+    let mut products = Products::new(filter);
+    for (pos, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line.map_err(|e| {
+            NutriplanError::InvalidInput(format!("could not read export: {}", e))
+        })?;
+        if !line.trim().is_empty() {
+            products.push(pos + 1, json_product(&line));
+        }
+    }
+    products.import(conn_mgr, dry_run)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::import::RowStatus;
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    const EXPORT: &str = "code\tproduct_name\tlang\tcountries_tags\tenergy-kcal_100g\t\
+        proteins_100g\tcarbohydrates_100g\tfat_100g\tsodium_100g\n\
+        3017620422003\tNutella\tfr\ten:france,en:germany\t539\t6.3\t57.5\t30.9\t0.043\n\
+        5449000000996\tCoca-Cola\ten\ten:united-kingdom\t42\t0\t10.6\t0\t0\n\
+        123\tHomemade jam\ten\ten:germany\t\t\t\t\t\n\
+        4008400402222\tUnreadable\tde\ten:germany\tn/a\t\t\t\t\n\
+        4006381333931\t\"Vollkorn\" Toast\tde\ten:germany\t\t9\t40\t4\t\n";
+
+    #[test]
+    fn import_off_csv_filters_by_country() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let filter = OffFilter { country: Some(String::from("Germany")), language: None };
+            let report = import_off_csv(&conn_mgr, EXPORT.as_bytes(), &filter, false).unwrap();
+            // Coca-Cola is sold in another country, the jam has no valid barcode and the
+            // calories of the last but one product cannot be read
+            assert_eq!(report.new_count(), 2);
+            assert_eq!(report.skipped, 3);
+            assert_eq!(report.rows[1].line, 6);
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT i.name, i.barcode, i.source, round(m.fats, 1), m.calories \
+                    FROM ingredients i JOIN ingredient_macros m ON m.ingredient_id = i.id \
+                    WHERE i.id > 2; \
+                    SELECT round(amount, 1) FROM ingredient_micros WHERE ingredient_id = 3;")
+                .output()
+                .expect("Failed to execute process");
+            let expected = "Nutella|3017620422003|off|30.9|539.0\n\
+                \"Vollkorn\" Toast|4006381333931|off|4.0|232.0\n43.0\n";
+            assert_eq!(expected, str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn import_off_jsonl_prefers_name_in_language_and_updates_products() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let filter = OffFilter { country: None, language: Some(String::from("de")) };
+            let export = "{\"code\": \"3017620422003\", \"product_name\": \"Nutella\", \
+                \"product_name_de\": \"Nutella Nuss-Nougat-Creme\", \"lang\": \"fr\", \
+                \"nutriments\": {\"proteins_100g\": 6.3, \"fat_100g\": \"30.9\"}}\n\
+                \n\
+                {\"code\": \"5449000000996\", \"product_name\": \"Coca-Cola\", \"lang\": \"en\"}\n";
+            let report = import_off_jsonl(&conn_mgr, export.as_bytes(), &filter, false).unwrap();
+            assert_eq!(report.rows.len(), 1);
+            assert_eq!(report.rows[0].name, "Nutella Nuss-Nougat-Creme");
+            assert_eq!(report.skipped, 1);
+            let report = import_off_jsonl(&conn_mgr, export.as_bytes(), &filter, false).unwrap();
+            assert_eq!(report.rows[0].status, RowStatus::Updated(3));
+        })
+    }
+
+    #[test]
+    fn import_off_jsonl_skips_unparsable_lines() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let conn_mgr = setup_conn_mgr();
+            let export = "{\"code\": \n\
+                {\"code\": \"3017620422003\", \"product_name\": \"Nutella\", \
+                \"nutriments\": {\"proteins_100g\": 6.3}}\n";
+            let report = import_off_jsonl(&conn_mgr, export.as_bytes(), &OffFilter::default(),
+                false).unwrap();
+            assert_eq!(report.skipped, 1);
+            assert_eq!(report.new_count(), 1);
+            assert!(report.committed);
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT name, barcode FROM ingredients WHERE id > 2;")
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("Nutella|3017620422003\n", str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::{Add, AddAssign};
+
+use diesel::prelude::*;
+
+use crate::{
+    controller::database::{ConnMgrPool, MAX_BOUND_IDS},
+    controller::database::ingredient_macro::IngredientMacro,
+    error::NutriplanError,
+};
+
+/**
+ * Energy conversion factors in kcal per gram used to derive the calories of an ingredient from its
+ * macro nutrients.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergyModel {
+    pub proteins: f32,
+    pub carbs: f32,
+    pub fats: f32,
+    pub alcohols: f32,
+    pub fiber: f32,
+    pub polyols: f32,
+}
+
+impl EnergyModel {
+    /** General Atwater factors, fiber and polyols do not contribute energy */
+    pub const ATWATER: EnergyModel = EnergyModel {
+        proteins: 4.0,
+        carbs: 4.0,
+        fats: 9.0,
+        alcohols: 7.0,
+        fiber: 0.0,
+        polyols: 0.0,
+    };
+
+    /** Atwater factors extended by fiber and polyols, as used for EU nutrition labels */
+    pub const ATWATER_WITH_FIBER: EnergyModel = EnergyModel {
+        fiber: 2.0,
+        polyols: 2.4,
+        ..EnergyModel::ATWATER
+    };
+
+    /**
+     * Compute the energy in kcal of the given masses in gram.
+     */
+    pub fn calories(&self, proteins: f32, carbs: f32, fats: f32, alcohols: f32, fiber: f32,
+        polyols: f32) -> f32
+    {
+        proteins * self.proteins
+            + carbs * self.carbs
+            + fats * self.fats
+            + alcohols * self.alcohols
+            + fiber * self.fiber
+            + polyols * self.polyols
+    }
+}
+
+impl Default for EnergyModel {
+    fn default() -> Self {
+        EnergyModel::ATWATER
+    }
+}
+
+/**
+ * Amounts of macro nutrients in gram and the resulting energy in kcal, along with amounts of
+ * micronutrients keyed by their code in the unit of the micronutrient.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Nutrients {
+    pub proteins: f32,
+    pub carbs: f32,
+    pub fats: f32,
+    pub alcohols: f32,
+    pub fiber: f32,
+    pub polyols: f32,
+    pub calories: f32,
+    pub micros: BTreeMap<String, f32>,
+}
+
+impl Nutrients {
+    /**
+     * Nutrients contained in the given mass in gram of an ingredient with the given macros. Macros
+     * are stored per 100 g.
+     */
+    pub fn of_mass(item: &IngredientMacro, mass: f32) -> Self {
+        Nutrients {
+            proteins: item.proteins,
+            carbs: item.carbs,
+            fats: item.fats,
+            alcohols: item.alcohols,
+            fiber: item.fiber,
+            polyols: item.polyols,
+            calories: item.calories,
+            micros: BTreeMap::new(),
+        }.scaled(mass / 100.0)
+    }
+
+    /**
+     * Multiply all amounts by the given factor.
+     */
+    pub fn scaled(&self, factor: f32) -> Self {
+        Nutrients {
+            proteins: self.proteins * factor,
+            carbs: self.carbs * factor,
+            fats: self.fats * factor,
+            alcohols: self.alcohols * factor,
+            fiber: self.fiber * factor,
+            polyols: self.polyols * factor,
+            calories: self.calories * factor,
+            micros: self.micros.iter().map(|(code, amount)| (code.clone(), amount * factor))
+                .collect(),
+        }
+    }
+
+    /**
+     * Amount of a micronutrient, zero if it is unknown.
+     */
+    pub fn micro(&self, code: &str) -> f32 {
+        self.micros.get(code).copied().unwrap_or_default()
+    }
+}
+
+impl Add for Nutrients {
+    type Output = Nutrients;
+
+    fn add(self, other: Nutrients) -> Nutrients {
+        let mut micros = self.micros;
+        for (code, amount) in other.micros {
+            *micros.entry(code).or_default() += amount;
+        }
+        Nutrients {
+            proteins: self.proteins + other.proteins,
+            carbs: self.carbs + other.carbs,
+            fats: self.fats + other.fats,
+            alcohols: self.alcohols + other.alcohols,
+            fiber: self.fiber + other.fiber,
+            polyols: self.polyols + other.polyols,
+            calories: self.calories + other.calories,
+            micros,
+        }
+    }
+}
+
+impl AddAssign for Nutrients {
+    fn add_assign(&mut self, other: Nutrients) {
+        *self = std::mem::take(self) + other;
+    }
+}
+
+impl AddAssign<&Nutrients> for Nutrients {
+    fn add_assign(&mut self, other: &Nutrients) {
+        *self += other.clone();
+    }
+}
+
+impl std::iter::Sum for Nutrients {
+    fn sum<I: Iterator<Item = Nutrients>>(iter: I) -> Nutrients {
+        iter.fold(Nutrients::default(), Add::add)
+    }
+}
+
+/**
+ * Nutrients contributed by a single ingredient.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct IngredientNutrients {
+    pub ingredient_id: i32,
+    pub name: String,
+    pub mass: f32,
+    /** False if no macros are stored for the ingredient, its nutrients are counted as zero */
+    pub has_macros: bool,
+    pub nutrients: Nutrients,
+}
+
+/**
+ * Nutrients of a meal, in total and per ingredient.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct MealNutrients {
+    pub meal_id: i32,
+    pub total: Nutrients,
+    pub ingredients: Vec<IngredientNutrients>,
+}
+
+/**
+ * Nutrients contributed by a recipe used as component of another recipe.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComponentNutrients {
+    pub recipe_id: i32,
+    pub name: String,
+    pub mass: f32,
+    pub nutrients: Nutrients,
+}
+
+/**
+ * Nutrients of a recipe, in total, per serving and per 100 g of the finished dish.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecipeNutrients {
+    pub recipe_id: i32,
+    pub servings: i32,
+    /** Mass in gram of the finished dish */
+    pub mass: f32,
+    pub total: Nutrients,
+    pub per_serving: Nutrients,
+    pub per_100g: Nutrients,
+    pub ingredients: Vec<IngredientNutrients>,
+    pub components: Vec<ComponentNutrients>,
+}
+
+impl RecipeNutrients {
+    /**
+     * Nutrients contained in the given mass in gram of the finished dish.
+     */
+    pub fn scaled_to(&self, mass: f32) -> Nutrients {
+        self.per_100g.scaled(mass / 100.0)
+    }
+}
+
+/**
+ * Compute the nutrients of a list of `(ingredient id, name, mass)` entries.
+ *
+ * Macros and micronutrients are loaded with a single query each per chunk of `MAX_BOUND_IDS`
+ * ingredients. If more than one macro entry exists for an ingredient, the one with the lowest id
+ * is used. Salt is derived from sodium for ingredients which only store the latter.
+ */
+pub(crate) fn ingredient_nutrients(conn_mgr: &ConnMgrPool, parts: Vec<(i32, String, f32)>)
+    -> Result<Vec<IngredientNutrients>, NutriplanError>
+{
+    use crate::schema::ingredient_macros::dsl::*;
+
+    let mut ids: Vec<i32> = parts.iter().map(|(iid, _, _)| *iid).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let mut macros: HashMap<i32, IngredientMacro> = HashMap::new();
+    let mut micros: HashMap<i32, BTreeMap<String, f32>> = HashMap::new();
+    for chunk in ids.chunks(MAX_BOUND_IDS) {
+        for item in ingredient_macros
+            .filter(ingredient_id.eq_any(chunk))
+            .order(id.desc())
+            .load::<IngredientMacro>(conn_mgr)?
+        {
+            macros.insert(item.ingredient_id, item);
+        }
+        micros.extend(ingredient_micros(conn_mgr, chunk)?);
+    }
+    Ok(parts.into_iter().map(|(iid, name, mass)| {
+        let item = macros.get(&iid);
+        let mut nutrients = item.map(|item| Nutrients::of_mass(item, mass)).unwrap_or_default();
+        if let Some(amounts) = micros.get(&iid) {
+            nutrients.micros = amounts.iter()
+                .map(|(code, amount)| (code.clone(), amount * mass / 100.0))
+                .collect();
+        }
+        IngredientNutrients {
+            ingredient_id: iid,
+            name,
+            mass,
+            has_macros: item.is_some(),
+            nutrients,
+        }
+    }).collect())
+}
+
+/** Gram of salt per milligram of sodium */
+const SALT_PER_SODIUM: f32 = 2.5 / 1000.0;
+
+/**
+ * Load the micronutrients per 100 g of the given ingredients, keyed by ingredient id and code.
+ */
+fn ingredient_micros(conn_mgr: &ConnMgrPool, ids: &[i32])
+    -> Result<HashMap<i32, BTreeMap<String, f32>>, NutriplanError>
+{
+    use crate::schema::{ingredient_micros, micronutrients};
+
+    let mut result: HashMap<i32, BTreeMap<String, f32>> = HashMap::new();
+    for (iid, code, amount) in ingredient_micros::table
+        .inner_join(micronutrients::table)
+        .filter(ingredient_micros::ingredient_id.eq_any(ids))
+        .select((ingredient_micros::ingredient_id, micronutrients::code,
+            ingredient_micros::amount))
+        .load::<(i32, String, f32)>(conn_mgr)?
+    {
+        result.entry(iid).or_default().insert(code, amount);
+    }
+    for amounts in result.values_mut() {
+        if !amounts.contains_key("salt") {
+            if let Some(sodium) = amounts.get("sodium").copied() {
+                amounts.insert(String::from("salt"), sodium * SALT_PER_SODIUM);
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    #[test]
+    fn nutrients_of_mass_scale_per_100_gram() {
+        let item = IngredientMacro::new(1, 1, 10.0, 20.0, 5.0, 0.0);
+        let nutrients = Nutrients::of_mass(&item, 50.0);
+        assert_eq!(nutrients.proteins, 5.0);
+        assert_eq!(nutrients.carbs, 10.0);
+        assert_eq!(nutrients.fats, 2.5);
+        assert_eq!(nutrients.calories, item.calories / 2.0);
+    }
+
+    #[test]
+    fn nutrients_sum_adds_all_fields() {
+        let item = IngredientMacro::new(1, 1, 10.0, 20.0, 5.0, 1.0);
+        let total: Nutrients = vec![Nutrients::of_mass(&item, 100.0); 3].into_iter().sum();
+        assert_eq!(total, Nutrients::of_mass(&item, 300.0));
+    }
+
+    #[test]
+    fn atwater_counts_fats_with_nine_kcal() {
+        let calories = EnergyModel::ATWATER.calories(1.0, 1.0, 1.0, 1.0, 1.0, 1.0);
+        assert_eq!(calories, 24.0);
+    }
+
+    #[test]
+    fn atwater_with_fiber_counts_fiber_and_polyols() {
+        let calories = EnergyModel::ATWATER_WITH_FIBER.calories(0.0, 0.0, 0.0, 0.0, 1.0, 1.0);
+        assert_eq!(calories, 4.4);
+    }
+
+    #[test]
+    fn nutrients_add_merges_micros() {
+        let mut a = Nutrients::default();
+        a.micros.insert(String::from("sugars"), 1.0);
+        let mut b = Nutrients::default();
+        b.micros.insert(String::from("sugars"), 2.0);
+        b.micros.insert(String::from("salt"), 0.5);
+        let total = (a + b).scaled(2.0);
+        assert_eq!(total.micro("sugars"), 6.0);
+        assert_eq!(total.micro("salt"), 1.0);
+        assert_eq!(total.micro("iron"), 0.0);
+    }
+
+    #[test]
+    fn ingredient_nutrients_scales_micros_and_derives_salt() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let parts = vec![(1, String::from("test1"), 200.0), (2, String::from("test2"), 50.0)];
+            let items = ingredient_nutrients(&conn_mgr, parts).unwrap();
+            assert_eq!(items[0].nutrients.micro("sugars"), 1.0);
+            assert_eq!(items[1].nutrients.micro("sodium"), 200.0);
+            assert!((items[1].nutrients.micro("salt") - 0.5).abs() < 1e-6);
+        })
+    }
+
+    #[test]
+    fn ingredient_nutrients_loads_more_ingredients_than_bound_per_query() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            // Unknown ids sort before the stored one, which is thereby loaded by a later chunk
+            let mut parts: Vec<(i32, String, f32)> = (-2 * MAX_BOUND_IDS as i32..0)
+                .map(|iid| (iid, String::from("unknown"), 100.0))
+                .collect();
+            parts.push((1, String::from("test1"), 200.0));
+            let items = ingredient_nutrients(&conn_mgr, parts).unwrap();
+            assert_eq!(items.len(), 2 * MAX_BOUND_IDS + 1);
+            assert!(items[..2 * MAX_BOUND_IDS].iter().all(|item| !item.has_macros));
+            assert!(items[2 * MAX_BOUND_IDS].has_macros);
+            assert_eq!(items[2 * MAX_BOUND_IDS].nutrients.proteins, 2.0);
+            assert_eq!(items[2 * MAX_BOUND_IDS].nutrients.micro("sugars"), 1.0);
+        })
+    }
+}
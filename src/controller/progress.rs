@@ -0,0 +1,151 @@
+use chrono::NaiveDate;
+
+use crate::{
+    controller::database::ConnMgrPool,
+    controller::database::goal::{CRUDGoal, Goal, most_specific},
+    controller::nutrition::Nutrients,
+    controller::report::Report,
+    error::NutriplanError,
+};
+
+/**
+ * Amount of a nutrient eaten compared to its target.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct NutrientProgress {
+    pub nutrient: &'static str,
+    pub target: f32,
+    pub actual: f32,
+    /** Amount left until the target is reached, zero once it is reached */
+    pub remaining: f32,
+    /** Amount above the target, zero as long as it is not exceeded */
+    pub exceeded: f32,
+    /** Actual amount in percent of the target, `None` for a target of zero */
+    pub percent: Option<f32>,
+}
+
+impl NutrientProgress {
+    pub fn new(nutrient: &'static str, target: f32, actual: f32) -> Self {
+        NutrientProgress {
+            nutrient,
+            target,
+            actual,
+            remaining: (target - actual).max(0.0),
+            exceeded: (actual - target).max(0.0),
+            percent: if target > 0.0 { Some(actual / target * 100.0) } else { None }
+        }
+    }
+}
+
+/**
+ * Nutrients eaten on a single day compared to the goal applying to that day.
+ */
+#[derive(Debug)]
+pub struct DayProgress {
+    pub date: NaiveDate,
+    /** Goal applying to the day, `None` if no goal is defined for it */
+    pub goal: Option<Goal>,
+    pub total: Nutrients,
+    /** One entry per tracked target of the goal */
+    pub nutrients: Vec<NutrientProgress>,
+}
+
+pub struct Progress { }
+
+impl Progress {
+    /**
+     * Compare each day between `start` and `end`, both inclusive, against its goal. Without a
+     * profile the meals of all profiles are compared against the goals for every profile.
+     */
+    pub fn daily(conn_mgr: &ConnMgrPool, start: &NaiveDate, end: &NaiveDate,
+        profile: Option<i32>) -> Result<Vec<DayProgress>, NutriplanError>
+    {
+        let goals = CRUDGoal::list_between(conn_mgr, start, end, profile)?;
+        Ok(Report::daily(conn_mgr, start, end, profile)?
+            .into_iter()
+            .map(|day| {
+                let goal = most_specific(&goals, &day.date).cloned();
+                let nutrients = goal.as_ref()
+                    .map(|goal| Self::compare(goal, &day.total))
+                    .unwrap_or_default();
+                DayProgress { date: day.date, goal, total: day.total, nutrients }
+            })
+            .collect())
+    }
+
+    /**
+     * Compare nutrients against the tracked targets of a goal.
+     */
+    pub fn compare(goal: &Goal, nutrients: &Nutrients) -> Vec<NutrientProgress> {
+        [
+            ("calories", goal.calories, nutrients.calories),
+            ("proteins", goal.proteins, nutrients.proteins),
+            ("carbs", goal.carbs, nutrients.carbs),
+            ("fats", goal.fats, nutrients.fats),
+            ("alcohols", goal.alcohols, nutrients.alcohols),
+            ("fiber", goal.fiber, nutrients.fiber),
+            ("polyols", goal.polyols, nutrients.polyols),
+        ]
+            .iter()
+            .filter_map(|(nutrient, target, actual)| {
+                target.map(|target| NutrientProgress::new(nutrient, target, *actual))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn progress_reports_remaining_and_exceeded_amounts() {
+        let progress = NutrientProgress::new("proteins", 100.0, 25.0);
+        assert_eq!(progress.remaining, 75.0);
+        assert_eq!(progress.exceeded, 0.0);
+        assert_eq!(progress.percent, Some(25.0));
+        let progress = NutrientProgress::new("fats", 50.0, 75.0);
+        assert_eq!(progress.remaining, 0.0);
+        assert_eq!(progress.exceeded, 25.0);
+        assert_eq!(progress.percent, Some(150.0));
+        assert_eq!(NutrientProgress::new("alcohols", 0.0, 0.0).percent, None);
+    }
+
+    #[test]
+    fn daily_compares_each_day_against_its_goal() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let days = Progress::daily(&conn_mgr, &date(2000, 1, 1), &date(2000, 1, 2), None)
+                .unwrap();
+            assert_eq!(days.len(), 2);
+            assert_eq!(days[0].goal.as_ref().map(|goal| goal.id), Some(Some(1)));
+            let nutrients: Vec<&str> = days[0].nutrients.iter()
+                .map(|progress| progress.nutrient)
+                .collect();
+            assert_eq!(nutrients, vec!["calories", "proteins", "carbs", "fats", "fiber"]);
+            let proteins = &days[0].nutrients[1];
+            assert!((proteins.actual - 1.11).abs() < 1e-4);
+            assert!((proteins.remaining - 98.89).abs() < 1e-4);
+            assert_eq!(days[1].nutrients[1].actual, 0.0);
+        })
+    }
+
+    #[test]
+    fn daily_without_goal_has_no_progress() {
+        run_db_test(|| {
+            use crate::controller::database::CRUDController;
+            let conn_mgr = setup_conn_mgr();
+            CRUDGoal::delete(&conn_mgr, 1).unwrap();
+            let days = Progress::daily(&conn_mgr, &date(2000, 1, 1), &date(2000, 1, 1), None)
+                .unwrap();
+            assert!(days[0].goal.is_none());
+            assert!(days[0].nutrients.is_empty());
+        })
+    }
+}
@@ -0,0 +1,198 @@
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::{
+    controller::database::ConnMgrPool,
+    controller::database::meal::{CRUDMeal, Meal},
+    controller::nutrition::{MealNutrients, Nutrients},
+    error::NutriplanError,
+};
+
+/**
+ * Length of the periods a report is aggregated by.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    Day,
+    /** Weeks start on monday */
+    Week,
+    Month,
+}
+
+impl Period {
+    /**
+     * First day of the period containing the given date.
+     */
+    pub fn start_of(&self, date: &NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => *date,
+            Period::Week => *date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Month => date.with_day(1).unwrap_or(*date),
+        }
+    }
+}
+
+/**
+ * A logged meal together with its nutrients.
+ */
+#[derive(Debug)]
+pub struct MealSummary {
+    pub meal: Meal,
+    pub nutrients: MealNutrients,
+}
+
+/**
+ * Nutrients eaten on a single day.
+ */
+#[derive(Debug)]
+pub struct DaySummary {
+    pub date: NaiveDate,
+    pub total: Nutrients,
+    pub meals: Vec<MealSummary>,
+}
+
+/**
+ * Nutrients eaten within a period, e.g. a week or a month.
+ */
+#[derive(Debug)]
+pub struct PeriodSummary {
+    /** First day of the period, clipped to the start of the requested range */
+    pub start: NaiveDate,
+    /** Last day of the period, clipped to the end of the requested range */
+    pub end: NaiveDate,
+    pub total: Nutrients,
+    /** Number of days with at least one logged meal */
+    pub days_logged: usize,
+    /** Average per day with at least one logged meal */
+    pub average: Nutrients,
+    pub days: Vec<DaySummary>,
+}
+
+pub struct Report { }
+
+impl Report {
+    /**
+     * Summarize each day between `start` and `end`, both inclusive. Days without meals are
+     * contained with zero nutrients. If a profile is given, only its meals are counted.
+     */
+    pub fn daily(conn_mgr: &ConnMgrPool, start: &NaiveDate, end: &NaiveDate,
+        profile: Option<i32>) -> Result<Vec<DaySummary>, NutriplanError>
+    {
+        let meals = CRUDMeal::list_by_date(conn_mgr, start, end, profile)?;
+        let meal_ids: Vec<i32> = meals.iter().map(|meal| meal.id.unwrap_or_default()).collect();
+        let nutrients = CRUDMeal::nutrients_of_meals(conn_mgr, &meal_ids)?;
+        let mut days: Vec<DaySummary> = start
+            .iter_days()
+            .take_while(|date| date <= end)
+            .map(|date| DaySummary { date, total: Nutrients::default(), meals: Vec::new() })
+            .collect();
+        for (meal, nutrients) in meals.into_iter().zip(nutrients) {
+            let day = &mut days[(meal.date - *start).num_days() as usize];
+            day.total += &nutrients.total;
+            day.meals.push(MealSummary { meal, nutrients });
+        }
+        Ok(days)
+    }
+
+    /**
+     * Summarize the days between `start` and `end`, both inclusive, aggregated by the given
+     * period. If a profile is given, only its meals are counted.
+     */
+    pub fn summarize(conn_mgr: &ConnMgrPool, start: &NaiveDate, end: &NaiveDate, period: Period,
+        profile: Option<i32>) -> Result<Vec<PeriodSummary>, NutriplanError>
+    {
+        let mut summaries: Vec<PeriodSummary> = Vec::new();
+        for day in Self::daily(conn_mgr, start, end, profile)? {
+            let period_start = period.start_of(&day.date).max(*start);
+            if summaries.last().map(|summary| summary.start) != Some(period_start) {
+                summaries.push(PeriodSummary {
+                    start: period_start,
+                    end: period_start,
+                    total: Nutrients::default(),
+                    days_logged: 0,
+                    average: Nutrients::default(),
+                    days: Vec::new()
+                });
+            }
+            if let Some(summary) = summaries.last_mut() {
+                summary.end = day.date;
+                summary.total += &day.total;
+                if !day.meals.is_empty() {
+                    summary.days_logged += 1;
+                }
+                summary.days.push(day);
+            }
+        }
+        for summary in summaries.iter_mut().filter(|summary| summary.days_logged > 0) {
+            summary.average = summary.total.scaled(1.0 / summary.days_logged as f32);
+        }
+        Ok(summaries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::{run_db_test, setup_conn_mgr};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn week_starts_on_monday() {
+        assert_eq!(Period::Week.start_of(&date(2021, 11, 6)), date(2021, 11, 1));
+        assert_eq!(Period::Week.start_of(&date(2021, 11, 1)), date(2021, 11, 1));
+    }
+
+    #[test]
+    fn month_starts_on_first_day() {
+        assert_eq!(Period::Month.start_of(&date(2021, 2, 28)), date(2021, 2, 1));
+    }
+
+    #[test]
+    fn daily_contains_every_day_of_range() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let days = Report::daily(&conn_mgr, &date(1999, 12, 31), &date(2000, 1, 2), None)
+                .unwrap();
+            assert_eq!(days.len(), 3);
+            assert!(days[0].meals.is_empty());
+            assert_eq!(days[1].meals.len(), 1);
+            assert_eq!(days[1].meals[0].meal.name, "testmeal1");
+            assert!((days[1].total.proteins - 1.11).abs() < 1e-4);
+        })
+    }
+
+    #[test]
+    fn summarize_by_month_averages_logged_days() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            let months = Report::summarize(&conn_mgr, &date(2000, 1, 15), &date(2000, 2, 10),
+                Period::Month, None).unwrap();
+            assert_eq!(months.len(), 2);
+            assert_eq!(months[0].start, date(2000, 1, 15));
+            assert_eq!(months[0].days_logged, 0);
+            assert_eq!(months[1].start, date(2000, 2, 1));
+            assert_eq!(months[1].end, date(2000, 2, 10));
+            assert_eq!(months[1].days_logged, 1);
+            assert!((months[1].average.proteins - 4.44).abs() < 1e-4);
+        })
+    }
+
+    #[test]
+    fn summarize_by_week_splits_on_monday() {
+        run_db_test(|| {
+            let conn_mgr = setup_conn_mgr();
+            // 2000-01-01 is a saturday
+            let weeks = Report::summarize(&conn_mgr, &date(1999, 12, 30), &date(2000, 1, 4),
+                Period::Week, None).unwrap();
+            assert_eq!(weeks.len(), 2);
+            assert_eq!(weeks[0].days.len(), 4);
+            assert_eq!(weeks[1].start, date(2000, 1, 3));
+            assert_eq!(weeks[0].days_logged, 1);
+            assert_eq!(weeks[1].days_logged, 0);
+            assert_eq!(weeks[1].average, Nutrients::default());
+        })
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+
+use crate::error::NutriplanError;
+
+/** Mass units and their size in gram */
+const MASS_UNITS: &[(&str, f32)] = &[
+    ("g", 1.0),
+    ("gram", 1.0),
+    ("kg", 1000.0),
+    ("kilogram", 1000.0),
+    ("mg", 0.001),
+    ("milligram", 0.001),
+    ("oz", 28.349_523),
+    ("ounce", 28.349_523),
+    ("lb", 453.592_37),
+    ("pound", 453.592_37),
+];
+
+/** Volume units and their size in milliliter */
+const VOLUME_UNITS: &[(&str, f32)] = &[
+    ("ml", 1.0),
+    ("milliliter", 1.0),
+    ("cl", 10.0),
+    ("dl", 100.0),
+    ("l", 1000.0),
+    ("liter", 1000.0),
+    ("tsp", 5.0),
+    ("teaspoon", 5.0),
+    ("tbsp", 15.0),
+    ("tablespoon", 15.0),
+    ("cup", 240.0),
+    ("fl oz", 29.573_53),
+];
+
+/**
+ * An amount in an arbitrary unit as entered by a user, e.g. "2 cups" or "1 slice".
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quantity {
+    pub amount: f32,
+    pub unit: String,
+}
+
+impl Quantity {
+    pub fn new(amount: f32, unit: &str) -> Self {
+        Quantity { amount, unit: normalize(unit) }
+    }
+
+    /**
+     * Parse a quantity of the form `<amount> [unit]`, the unit defaults to gram. The amount may
+     * directly be followed by the unit, e.g. "150g".
+     */
+    pub fn parse(text: &str) -> Result<Self, NutriplanError> {
+        let text = text.trim();
+        let split = text
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+            .unwrap_or(text.len());
+        let (amount, unit) = text.split_at(split);
+        let amount = amount.replace(',', ".").parse::<f32>().map_err(|_| {
+            NutriplanError::InvalidInput(format!("could not parse quantity '{}'", text))
+        })?;
+        if !(amount > 0.0 && amount.is_finite()) {
+            return Err(NutriplanError::InvalidInput(
+                format!("quantity must be positive, got '{}'", text)))
+        }
+        let unit = unit.trim();
+        Ok(Quantity::new(amount, if unit.is_empty() { "g" } else { unit }))
+    }
+
+    /**
+     * Size of the unit in gram, if it is a mass unit.
+     */
+    pub fn gram_per_unit(&self) -> Option<f32> {
+        lookup(MASS_UNITS, &self.unit)
+    }
+
+    /**
+     * Size of the unit in milliliter, if it is a volume unit.
+     */
+    pub fn milliliter_per_unit(&self) -> Option<f32> {
+        lookup(VOLUME_UNITS, &self.unit)
+    }
+
+    /**
+     * Check whether the unit refers to the given name, ignoring case and a plural suffix.
+     */
+    pub fn unit_matches(&self, name: &str) -> bool {
+        let name = normalize(name);
+        candidates(&self.unit).any(|unit| unit == name)
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.unit)
+    }
+}
+
+fn normalize(unit: &str) -> String {
+    unit.trim().to_lowercase()
+}
+
+/**
+ * The unit itself followed by its possible singular forms.
+ */
+fn candidates(unit: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(unit)
+        .chain(unit.strip_suffix('s'))
+        .chain(unit.strip_suffix("es"))
+}
+
+fn lookup(table: &[(&str, f32)], unit: &str) -> Option<f32> {
+    candidates(unit).find_map(|unit| {
+        table.iter().find(|(name, _)| *name == unit).map(|(_, size)| *size)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_splits_amount_and_unit() {
+        assert_eq!(Quantity::parse("2 Cups").unwrap(), Quantity::new(2.0, "cups"));
+        assert_eq!(Quantity::parse("150g").unwrap(), Quantity::new(150.0, "g"));
+        assert_eq!(Quantity::parse("0,5 l").unwrap(), Quantity::new(0.5, "l"));
+    }
+
+    #[test]
+    fn parse_defaults_to_gram() {
+        assert_eq!(Quantity::parse("42").unwrap(), Quantity::new(42.0, "g"));
+    }
+
+    #[test]
+    fn parse_rejects_missing_or_non_positive_amount() {
+        assert!(Quantity::parse("slice").is_err());
+        assert!(Quantity::parse("0 slices").is_err());
+    }
+
+    #[test]
+    fn units_are_found_in_plural() {
+        assert_eq!(Quantity::new(2.0, "cups").milliliter_per_unit(), Some(240.0));
+        assert_eq!(Quantity::new(1.0, "kg").gram_per_unit(), Some(1000.0));
+        assert_eq!(Quantity::new(1.0, "slice").gram_per_unit(), None);
+        assert!(Quantity::new(3.0, "slices").unit_matches("Slice"));
+        assert!(Quantity::new(3.0, "tomatoes").unit_matches("tomato"));
+    }
+}
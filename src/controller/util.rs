@@ -1,18 +1,31 @@
 use crate::controller::database::ConnMgrPool;
+use crate::error::NutriplanError;
 
 /**
  * Setup connection manager necessary to connect to the local database.
  */
-pub fn setup_conn_mgr(database_path: &str) -> ConnMgrPool {
+pub fn setup_conn_mgr(database_path: &str) -> Result<ConnMgrPool, NutriplanError> {
     use crate::controller::database::connect_database;
-    let db_pool = match connect_database(database_path) {
-        Some(db_pool) => db_pool,
-        None => panic!("No database url provided.")
-    };
-    match db_pool.get() {
-        Ok(conn_mgr) => conn_mgr,
-        Err(_) => panic!("Could not get a connection manager from database pool!")
+    let db_pool = connect_database(database_path)?;
+    Ok(db_pool.get()?)
+}
+
+/**
+ * Compute the Levenshtein distance between two strings, counted in characters.
+ */
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
     }
+    prev[b.len()]
 }
 
 #[cfg(test)]
@@ -25,7 +38,8 @@ pub mod test {
     static TEST_MUTEX: Lazy<Mutex<()>> = Lazy::new(Mutex::default);
 
     /**
-     * Setup test environment.
+     * Setup test environment. The schema is built by `setup_db.sh`, the rows are restored from
+     * `fixtures.json`.
      * NOTE: Currently we allow unused_must_use, since we want to enable the logger. If it is
      * already enabled, it would return an Err, which will occur on the second test we execute.
      * This is not critical and so we do not care.
@@ -37,6 +51,10 @@ pub mod test {
             .arg("./test/db/setup_db.sh")
             .output()
             .expect("Failed to build database");
+        let fixtures = std::fs::File::open("./test/db/fixtures.json")
+            .expect("Failed to open fixtures");
+        crate::controller::dump::restore(&setup_conn_mgr(), fixtures)
+            .expect("Failed to restore fixtures");
     }
 
     /**
@@ -54,8 +72,8 @@ pub mod test {
      * This method is used to run tests instead of the rust test method to allow setup and teardown
      * of each testrun.
      */
-    pub fn run_db_test<T>(test: T) -> ()
-        where T: FnOnce() -> () + std::panic::UnwindSafe
+    pub fn run_db_test<T>(test: T)
+        where T: FnOnce() + std::panic::UnwindSafe
     {
         let _shared = TEST_MUTEX.lock();
         db_setup();
@@ -67,6 +85,21 @@ pub mod test {
     }
 
     pub fn setup_conn_mgr() -> crate::controller::database::ConnMgrPool {
-        super::setup_conn_mgr("test.db")
+        super::setup_conn_mgr("test.db").expect("Could not connect to test database")
+    }
+
+    #[test]
+    fn edit_distance_counts_single_insertion() {
+        assert_eq!(super::edit_distance("chiken breast", "chicken breast"), 1);
+    }
+
+    #[test]
+    fn edit_distance_of_equal_strings_is_zero() {
+        assert_eq!(super::edit_distance("apple", "apple"), 0);
+    }
+
+    #[test]
+    fn edit_distance_to_empty_string_is_length() {
+        assert_eq!(super::edit_distance("", "pear"), 4);
     }
 }
@@ -0,0 +1,143 @@
+use std::fmt;
+
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+
+/**
+ * Error type returned by all database operations.
+ *
+ * The variants allow callers to tell apart a missing item from a broken connection, so they can
+ * decide whether a retry or a user message is appropriate.
+ */
+#[derive(Debug)]
+pub enum NutriplanError {
+    /** No item of the given entity exists with the given id */
+    NotFound { entity: &'static str, id: i32 },
+    /** Item references another item, which does not exist */
+    ForeignKeyViolation(String),
+    /** Item violates a unique, not null or check constraint */
+    ConstraintViolation(String),
+    /** Database could not be reached, the pool is exhausted or the database is locked */
+    Connection(String),
+    /** Parameters given by the caller are not usable */
+    InvalidInput(String),
+    /** Any other error reported by the database */
+    Database(DieselError),
+}
+
+impl NutriplanError {
+    /**
+     * Shorthand for a `NotFound` error on the given entity.
+     */
+    pub fn not_found(entity: &'static str, id: i32) -> Self {
+        NutriplanError::NotFound { entity, id }
+    }
+
+    /**
+     * Shorthand for a `ForeignKeyViolation` caused by a missing referenced item.
+     */
+    pub fn missing_reference(entity: &'static str, id: i32) -> Self {
+        NutriplanError::ForeignKeyViolation(format!("{} with id {} does not exist", entity, id))
+    }
+
+    /**
+     * Shorthand for a `ForeignKeyViolation` caused by a private item used outside of its profile.
+     */
+    pub fn private_reference(entity: &'static str, id: i32) -> Self {
+        NutriplanError::ForeignKeyViolation(
+            format!("{} with id {} is private to another profile", entity, id))
+    }
+}
+
+impl fmt::Display for NutriplanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NutriplanError::NotFound { entity, id } =>
+                write!(f, "Could not find {} with id {}", entity, id),
+            NutriplanError::ForeignKeyViolation(msg) =>
+                write!(f, "Foreign key violation: {}", msg),
+            NutriplanError::ConstraintViolation(msg) =>
+                write!(f, "Constraint violation: {}", msg),
+            NutriplanError::Connection(msg) =>
+                write!(f, "Could not access database: {}", msg),
+            NutriplanError::InvalidInput(msg) =>
+                write!(f, "Invalid input: {}", msg),
+            NutriplanError::Database(e) =>
+                write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for NutriplanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NutriplanError::Database(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<DieselError> for NutriplanError {
+    fn from(e: DieselError) -> Self {
+        match e {
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) =>
+                NutriplanError::ForeignKeyViolation(info.message().to_owned()),
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) =>
+                NutriplanError::ConstraintViolation(info.message().to_owned()),
+            DieselError::DatabaseError(kind, info) => {
+                let msg = info.message();
+                if msg.contains("constraint failed") {
+                    NutriplanError::ConstraintViolation(msg.to_owned())
+                } else if msg.contains("database is locked") || msg.contains("database is busy") {
+                    NutriplanError::Connection(msg.to_owned())
+                } else {
+                    NutriplanError::Database(DieselError::DatabaseError(kind, info))
+                }
+            },
+            e => NutriplanError::Database(e)
+        }
+    }
+}
+
+impl From<diesel::r2d2::PoolError> for NutriplanError {
+    fn from(e: diesel::r2d2::PoolError) -> Self {
+        NutriplanError::Connection(e.to_string())
+    }
+}
+
+impl From<csv::Error> for NutriplanError {
+    fn from(e: csv::Error) -> Self {
+        NutriplanError::InvalidInput(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn foreign_key_error_maps_to_foreign_key_violation() {
+        let e = DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation,
+            Box::new(String::from("FOREIGN KEY constraint failed")));
+        assert!(matches!(NutriplanError::from(e), NutriplanError::ForeignKeyViolation(_)));
+    }
+
+    #[test]
+    fn not_null_error_maps_to_constraint_violation() {
+        let e = DieselError::DatabaseError(DatabaseErrorKind::__Unknown,
+            Box::new(String::from("NOT NULL constraint failed: ingredients.name")));
+        assert!(matches!(NutriplanError::from(e), NutriplanError::ConstraintViolation(_)));
+    }
+
+    #[test]
+    fn locked_database_maps_to_connection_error() {
+        let e = DieselError::DatabaseError(DatabaseErrorKind::__Unknown,
+            Box::new(String::from("database is locked")));
+        assert!(matches!(NutriplanError::from(e), NutriplanError::Connection(_)));
+    }
+
+    #[test]
+    fn not_found_displays_entity_and_id() {
+        let e = NutriplanError::not_found("ingredient", 7);
+        assert_eq!(e.to_string(), "Could not find ingredient with id 7");
+    }
+}
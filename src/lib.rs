@@ -1,252 +1,1030 @@
+// diesel 1.x derives and table! macros define impls inside of anonymous consts
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate diesel;
 
 pub mod controller;
+pub mod error;
 pub mod schema;
 
-use controller::database::ConnMgrPool;
-use controller::util::setup_conn_mgr;
+pub use error::NutriplanError;
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use controller::database::{ConnMgrPool, DbPool, connect_database};
+use controller::dump::{dump, restore};
+use controller::import::ImportReport;
+use controller::import::csv::{CsvFormat, import_csv};
+use controller::import::fdc::{import_fdc_csv, import_fdc_json};
+use controller::import::off::{OffFilter, import_off_csv, import_off_jsonl};
+use controller::nutrition::{EnergyModel, MealNutrients, RecipeNutrients};
+use controller::progress::{DayProgress, Progress};
+use controller::report::{DaySummary, Period, PeriodSummary, Report};
+use controller::units::Quantity;
 
 
 use controller::database::{
-    ingredient::{NewIngredient, Ingredient},
+    goal::{NewGoal, Goal},
+    ingredient::{NewIngredient, Ingredient, SearchMode},
     ingredient_macro::{NewIngredientMacro, IngredientMacro},
-    meal::{NewMeal, Meal},
+    ingredient_micro::{NewIngredientMicro, IngredientMicro},
+    ingredient_portion::{NewIngredientPortion, IngredientPortion},
+    meal::{NewMeal, Meal, RecipePortion},
     meal_ingredient::{NewMealIngredient, MealIngredient},
+    micronutrient::{NewMicronutrient, Micronutrient},
+    profile::{NewProfile, Profile},
     recipe::{NewRecipe, Recipe},
+    recipe_component::{NewRecipeComponent, RecipeComponent},
     recipe_ingredient::{NewRecipeIngredient, RecipeIngredient},
 };
 
+pub trait INutriplanDbDump {
+    /* Dumps */
+    fn dump<W: Write>(&self, writer: W) -> Result<(), NutriplanError>;
+    fn restore<R: Read>(&self, reader: R) -> Result<Vec<(&'static str, usize)>, NutriplanError>;
+}
+pub trait INutriplanDbGoal {
+    /* Goals */
+    fn create(&self, item: NewGoal) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<Goal, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Goal>, NutriplanError>;
+    fn update(&self, item: Goal) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
+    fn for_date(&self, date: &NaiveDate, profile: Option<i32>)
+        -> Result<Option<Goal>, NutriplanError>;
+    fn progress(&self, start: &NaiveDate, end: &NaiveDate, profile: Option<i32>)
+        -> Result<Vec<DayProgress>, NutriplanError>;
+}
+pub trait INutriplanDbImport {
+    /* Imports */
+    fn csv<R: Read>(&self, reader: R, format: &CsvFormat, dry_run: bool)
+        -> Result<ImportReport, NutriplanError>;
+    fn fdc_json<R: Read>(&self, reader: R, dry_run: bool) -> Result<ImportReport, NutriplanError>;
+    fn fdc_csv(&self, dir: &Path, dry_run: bool) -> Result<ImportReport, NutriplanError>;
+    fn off_csv<R: Read>(&self, reader: R, filter: &OffFilter, dry_run: bool)
+        -> Result<ImportReport, NutriplanError>;
+    fn off_jsonl<R: Read>(&self, reader: R, filter: &OffFilter, dry_run: bool)
+        -> Result<ImportReport, NutriplanError>;
+}
 pub trait INutriplanDbIngredient {
     /* Ingredients */
-    fn create(&self, item: NewIngredient) -> bool;
-    fn read(&self,id: i32) -> Option<Ingredient>;
-    fn update(&self, item: Ingredient) -> bool;
-    fn delete(&self, id: i32) -> bool;
+    fn create(&self, item: NewIngredient) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<Ingredient, NutriplanError>;
+    fn find_by_barcode(&self, code: &str) -> Result<Option<Ingredient>, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Ingredient>, NutriplanError>;
+    fn list_by_profile(&self, profile_id: i32, limit: i64, offset: i64)
+        -> Result<Vec<Ingredient>, NutriplanError>;
+    fn search(&self, query: &str, mode: SearchMode, limit: usize, profile: Option<i32>)
+        -> Result<Vec<Ingredient>, NutriplanError>;
+    fn update(&self, item: Ingredient) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
 }
 pub trait INutriplanDbIngredientMacro {
     /* Ingredient Macros */
-    fn create(&self, item: NewIngredientMacro) -> bool;
-    fn read(&self, id: i32) -> Option<IngredientMacro>;
-    fn update(&self, item: IngredientMacro) -> bool;
-    fn delete(&self, id: i32) -> bool;
+    fn create(&self, item: NewIngredientMacro) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<IngredientMacro, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<IngredientMacro>, NutriplanError>;
+    fn recompute_calories(&self, model: &EnergyModel) -> Result<usize, NutriplanError>;
+    fn update(&self, item: IngredientMacro) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
+}
+pub trait INutriplanDbIngredientMicro {
+    /* Ingredient Micronutrients */
+    fn create(&self, item: NewIngredientMicro) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<IngredientMicro, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<IngredientMicro>, NutriplanError>;
+    fn list_by_ingredient(&self, ingredient_id: i32)
+        -> Result<Vec<IngredientMicro>, NutriplanError>;
+    fn update(&self, item: IngredientMicro) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
+}
+pub trait INutriplanDbIngredientPortion {
+    /* Ingredient Portions */
+    fn create(&self, item: NewIngredientPortion) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<IngredientPortion, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<IngredientPortion>, NutriplanError>;
+    fn list_by_ingredient(&self, ingredient_id: i32)
+        -> Result<Vec<IngredientPortion>, NutriplanError>;
+    fn to_grams(&self, ingredient_id: i32, quantity: &Quantity) -> Result<f32, NutriplanError>;
+    fn update(&self, item: IngredientPortion) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
+}
+pub trait INutriplanDbMicronutrient {
+    /* Micronutrients */
+    fn create(&self, item: NewMicronutrient) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<Micronutrient, NutriplanError>;
+    fn find_by_code(&self, code: &str) -> Result<Option<Micronutrient>, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Micronutrient>, NutriplanError>;
+    fn update(&self, item: Micronutrient) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
 }
 pub trait INutriplanDbMeal {
     /* Meals */
-    fn create(&self, item: NewMeal) -> bool;
-    fn read(&self, id: i32) -> Option<Meal>;
-    fn update(&self, item: Meal) -> bool;
-    fn delete(&self, id: i32) -> bool;
+    fn create(&self, item: NewMeal) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<Meal, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Meal>, NutriplanError>;
+    fn list_by_date(&self, start: &NaiveDate, end: &NaiveDate, profile: Option<i32>)
+        -> Result<Vec<Meal>, NutriplanError>;
+    fn list_by_profile(&self, profile_id: i32, limit: i64, offset: i64)
+        -> Result<Vec<Meal>, NutriplanError>;
+    fn nutrients(&self, id: i32) -> Result<MealNutrients, NutriplanError>;
+    fn add_recipe(&self, meal_id: i32, recipe_id: i32, portion: RecipePortion)
+        -> Result<Vec<i32>, NutriplanError>;
+    fn update(&self, item: Meal) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
 }
 pub trait INutriplanDbMealIngredient {
     /* Meal Ingredients */
-    fn create(&self, item: NewMealIngredient) -> bool;
-    fn read(&self, id: i32) -> Option<MealIngredient>;
-    fn update(&self, item: MealIngredient) -> bool;
-    fn delete(&self, id: i32) -> bool;
+    fn create(&self, item: NewMealIngredient) -> Result<i32, NutriplanError>;
+    fn create_from_quantity(&self, meal_id: i32, ingredient_id: i32, quantity: &Quantity)
+        -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<MealIngredient, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<MealIngredient>, NutriplanError>;
+    fn update(&self, item: MealIngredient) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
+}
+pub trait INutriplanDbProfile {
+    /* Profiles */
+    fn create(&self, item: NewProfile) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<Profile, NutriplanError>;
+    fn find_by_name(&self, name: &str) -> Result<Option<Profile>, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Profile>, NutriplanError>;
+    fn update(&self, item: Profile) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
 }
 pub trait INutriplanDbRecipe {
     /* Recipes */
-    fn create(&self, item: NewRecipe) -> bool;
-    fn read(&self, id: i32) -> Option<Recipe>;
-    fn update(&self, item: Recipe) -> bool;
-    fn delete(&self, id: i32) -> bool;
+    fn create(&self, item: NewRecipe) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<Recipe, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Recipe>, NutriplanError>;
+    fn list_by_profile(&self, profile_id: i32, limit: i64, offset: i64)
+        -> Result<Vec<Recipe>, NutriplanError>;
+    fn update(&self, item: Recipe) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
+    fn nutrients(&self, id: i32) -> Result<RecipeNutrients, NutriplanError>;
+}
+pub trait INutriplanDbRecipeComponent {
+    /* Recipe Components */
+    fn create(&self, item: NewRecipeComponent) -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<RecipeComponent, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<RecipeComponent>, NutriplanError>;
+    fn update(&self, item: RecipeComponent) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
 }
 pub trait INutriplanDbRecipeIngredient {
     /* Recept Ingredients */
-    fn create(&self, item: NewRecipeIngredient) -> bool;
-    fn read(&self, id: i32) -> Option<RecipeIngredient>;
-    fn update(&self, item: RecipeIngredient) -> bool;
-    fn delete(&self, id: i32) -> bool;
+    fn create(&self, item: NewRecipeIngredient) -> Result<i32, NutriplanError>;
+    fn create_from_quantity(&self, recipe_id: i32, ingredient_id: i32, quantity: &Quantity)
+        -> Result<i32, NutriplanError>;
+    fn read(&self, id: i32) -> Result<RecipeIngredient, NutriplanError>;
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<RecipeIngredient>, NutriplanError>;
+    fn update(&self, item: RecipeIngredient) -> Result<(), NutriplanError>;
+    fn delete(&self, id: i32) -> Result<(), NutriplanError>;
+}
+pub trait INutriplanDbReport {
+    /* Reports */
+    fn daily(&self, start: &NaiveDate, end: &NaiveDate, profile: Option<i32>)
+        -> Result<Vec<DaySummary>, NutriplanError>;
+    fn summarize(&self, start: &NaiveDate, end: &NaiveDate, period: Period,
+        profile: Option<i32>) -> Result<Vec<PeriodSummary>, NutriplanError>;
+}
+
+/**
+ * Handle to a nutriplan database.
+ *
+ * All entity handles returned by this object share a single connection pool, a connection is
+ * taken from the pool for each operation.
+ */
+#[derive(Clone)]
+pub struct NutriplanDb {
+    pool: DbPool
+}
+
+impl NutriplanDb {
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb { pool: connect_database(database_path)? })
+    }
+
+    pub fn dumps(&self) -> NutriplanSqliteDbDump {
+        NutriplanSqliteDbDump { pool: self.pool.clone() }
+    }
+
+    pub fn goals(&self) -> NutriplanSqliteDbGoal {
+        NutriplanSqliteDbGoal { pool: self.pool.clone() }
+    }
+
+    pub fn imports(&self) -> NutriplanSqliteDbImport {
+        NutriplanSqliteDbImport { pool: self.pool.clone() }
+    }
+
+    pub fn ingredients(&self) -> NutriplanSqliteDbIngredient {
+        NutriplanSqliteDbIngredient { pool: self.pool.clone() }
+    }
+
+    pub fn ingredient_macros(&self) -> NutriplanSqliteDbIngredientMacro {
+        NutriplanSqliteDbIngredientMacro { pool: self.pool.clone() }
+    }
+
+    pub fn ingredient_micros(&self) -> NutriplanSqliteDbIngredientMicro {
+        NutriplanSqliteDbIngredientMicro { pool: self.pool.clone() }
+    }
+
+    pub fn ingredient_portions(&self) -> NutriplanSqliteDbIngredientPortion {
+        NutriplanSqliteDbIngredientPortion { pool: self.pool.clone() }
+    }
+
+    pub fn micronutrients(&self) -> NutriplanSqliteDbMicronutrient {
+        NutriplanSqliteDbMicronutrient { pool: self.pool.clone() }
+    }
+
+    pub fn meals(&self) -> NutriplanSqliteDbMeal {
+        NutriplanSqliteDbMeal { pool: self.pool.clone() }
+    }
+
+    pub fn meal_ingredients(&self) -> NutriplanSqliteDbMealIngredient {
+        NutriplanSqliteDbMealIngredient { pool: self.pool.clone() }
+    }
+
+    pub fn profiles(&self) -> NutriplanSqliteDbProfile {
+        NutriplanSqliteDbProfile { pool: self.pool.clone() }
+    }
+
+    pub fn recipes(&self) -> NutriplanSqliteDbRecipe {
+        NutriplanSqliteDbRecipe { pool: self.pool.clone() }
+    }
+
+    pub fn recipe_components(&self) -> NutriplanSqliteDbRecipeComponent {
+        NutriplanSqliteDbRecipeComponent { pool: self.pool.clone() }
+    }
+
+    pub fn recipe_ingredients(&self) -> NutriplanSqliteDbRecipeIngredient {
+        NutriplanSqliteDbRecipeIngredient { pool: self.pool.clone() }
+    }
+
+    pub fn reports(&self) -> NutriplanSqliteDbReport {
+        NutriplanSqliteDbReport { pool: self.pool.clone() }
+    }
+
+    /**
+     * Run several operations atomically.
+     *
+     * The closure receives a connection, which is passed to the CRUD controllers. If the closure
+     * returns an error, all changes done within it are rolled back. Transactions may be nested.
+     *
+     * # Example
+     * ```no_run
+     * # use nutriplan_db::{NutriplanDb, NutriplanError};
+     * # use nutriplan_db::controller::database::{CRUDController, meal::*, meal_ingredient::*};
+     * # fn main() -> Result<(), NutriplanError> {
+     * let db = NutriplanDb::new("nutriplan.db")?;
+     * let date = chrono::NaiveDate::from_ymd_opt(2021, 11, 6).unwrap();
+     * let time = chrono::NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+     * let meal_id = db.transaction(|tx| {
+     *     let meal_id = CRUDMeal::create(tx, &NewMeal::new("breakfast", &date, &time))?;
+     *     CRUDMealIngredient::create(tx, &NewMealIngredient::new(meal_id, 1, 100.0))?;
+     *     Ok(meal_id)
+     * })?;
+     * # Ok(())
+     * # }
+     * ```
+     */
+    pub fn transaction<T, F>(&self, f: F) -> Result<T, NutriplanError>
+        where F: FnOnce(&ConnMgrPool) -> Result<T, NutriplanError>
+    {
+        use diesel::Connection;
+
+        let conn_mgr = self.pool.get()?;
+        conn_mgr.transaction(|| f(&conn_mgr))
+    }
+}
+
+pub struct NutriplanSqliteDbGoal {
+    pool: DbPool
+}
+
+impl NutriplanSqliteDbGoal {
+    #[deprecated(note = "use `NutriplanDb::goals`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.goals())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
+    }
+}
+
+impl INutriplanDbGoal for NutriplanSqliteDbGoal {
+    fn create(&self, item: NewGoal) -> Result<i32, NutriplanError> {
+        CRUDGoal::create(&self.conn_mgr()?, &item)
+    }
+    fn read(&self, id: i32) -> Result<Goal, NutriplanError> {
+        CRUDGoal::read(&self.conn_mgr()?, id)
+    }
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Goal>, NutriplanError> {
+        CRUDGoal::list(&self.conn_mgr()?, limit, offset)
+    }
+    fn update(&self, item: Goal) -> Result<(), NutriplanError> {
+        match item.id {
+            Some(id) => CRUDGoal::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
+        }
+    }
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDGoal::delete(&self.conn_mgr()?, id)
+    }
+    fn for_date(&self, date: &NaiveDate, profile: Option<i32>)
+        -> Result<Option<Goal>, NutriplanError>
+    {
+        CRUDGoal::for_date(&self.conn_mgr()?, date, profile)
+    }
+    fn progress(&self, start: &NaiveDate, end: &NaiveDate, profile: Option<i32>)
+        -> Result<Vec<DayProgress>, NutriplanError>
+    {
+        Progress::daily(&self.conn_mgr()?, start, end, profile)
+    }
+}
+
+pub struct NutriplanSqliteDbDump {
+    pool: DbPool
+}
+
+impl NutriplanSqliteDbDump {
+    #[deprecated(note = "use `NutriplanDb::dumps`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.dumps())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
+    }
+}
+
+impl INutriplanDbDump for NutriplanSqliteDbDump {
+    fn dump<W: Write>(&self, writer: W) -> Result<(), NutriplanError> {
+        dump(&self.conn_mgr()?, writer)
+    }
+
+    fn restore<R: Read>(&self, reader: R) -> Result<Vec<(&'static str, usize)>, NutriplanError> {
+        restore(&self.conn_mgr()?, reader)
+    }
+}
+
+pub struct NutriplanSqliteDbImport {
+    pool: DbPool
+}
+
+impl NutriplanSqliteDbImport {
+    #[deprecated(note = "use `NutriplanDb::imports`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.imports())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
+    }
+}
+
+impl INutriplanDbImport for NutriplanSqliteDbImport {
+    fn csv<R: Read>(&self, reader: R, format: &CsvFormat, dry_run: bool)
+        -> Result<ImportReport, NutriplanError>
+    {
+        import_csv(&self.conn_mgr()?, reader, format, dry_run)
+    }
+
+    fn fdc_json<R: Read>(&self, reader: R, dry_run: bool) -> Result<ImportReport, NutriplanError> {
+        import_fdc_json(&self.conn_mgr()?, reader, dry_run)
+    }
+
+    fn fdc_csv(&self, dir: &Path, dry_run: bool) -> Result<ImportReport, NutriplanError> {
+        import_fdc_csv(&self.conn_mgr()?, dir, dry_run)
+    }
+
+    fn off_csv<R: Read>(&self, reader: R, filter: &OffFilter, dry_run: bool)
+        -> Result<ImportReport, NutriplanError>
+    {
+        import_off_csv(&self.conn_mgr()?, reader, filter, dry_run)
+    }
+
+    fn off_jsonl<R: Read>(&self, reader: R, filter: &OffFilter, dry_run: bool)
+        -> Result<ImportReport, NutriplanError>
+    {
+        import_off_jsonl(&self.conn_mgr()?, reader, filter, dry_run)
+    }
 }
 
 pub struct NutriplanSqliteDbIngredient {
-    conn_mgr: ConnMgrPool
+    pool: DbPool
 }
 
 impl NutriplanSqliteDbIngredient {
-    pub fn new(database_path: &str) -> Self {
-        let conn_mgr = setup_conn_mgr(database_path);
-        NutriplanSqliteDbIngredient{ conn_mgr }
+    #[deprecated(note = "use `NutriplanDb::ingredients`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.ingredients())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
     }
 }
 
 impl INutriplanDbIngredient for NutriplanSqliteDbIngredient {
-    fn create(&self, item: NewIngredient) -> bool {
-        CRUDIngredient::create(&self.conn_mgr, &item)
+    fn create(&self, item: NewIngredient) -> Result<i32, NutriplanError> {
+        CRUDIngredient::create(&self.conn_mgr()?, &item)
     }
 
-    fn read(&self, id: i32) -> Option<Ingredient> {
-        CRUDIngredient::read(&self.conn_mgr, id)
+    fn read(&self, id: i32) -> Result<Ingredient, NutriplanError> {
+        CRUDIngredient::read(&self.conn_mgr()?, id)
+    }
+    fn find_by_barcode(&self, code: &str) -> Result<Option<Ingredient>, NutriplanError> {
+        CRUDIngredient::find_by_barcode(&self.conn_mgr()?, code)
+    }
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Ingredient>, NutriplanError> {
+        CRUDIngredient::list(&self.conn_mgr()?, limit, offset)
+    }
+    fn list_by_profile(&self, profile_id: i32, limit: i64, offset: i64)
+        -> Result<Vec<Ingredient>, NutriplanError>
+    {
+        CRUDIngredient::list_by_profile(&self.conn_mgr()?, profile_id, limit, offset)
     }
-    fn update(&self, item: Ingredient) -> bool {
+    fn search(&self, query: &str, mode: SearchMode, limit: usize, profile: Option<i32>)
+        -> Result<Vec<Ingredient>, NutriplanError>
+    {
+        CRUDIngredient::search(&self.conn_mgr()?, query, mode, limit, profile)
+    }
+    fn update(&self, item: Ingredient) -> Result<(), NutriplanError> {
         match item.id {
-            Some(id) => CRUDIngredient::update(&self.conn_mgr, id, item),
-            None => false
+            Some(id) => CRUDIngredient::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
         }
     }
-    fn delete(&self, id: i32) -> bool {
-        CRUDIngredient::delete(&self.conn_mgr, id)
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDIngredient::delete(&self.conn_mgr()?, id)
     }
 }
 
 pub struct NutriplanSqliteDbIngredientMacro {
-    conn_mgr: ConnMgrPool
+    pool: DbPool
 }
 
 impl NutriplanSqliteDbIngredientMacro {
-    pub fn new(database_path: &str) -> Self {
-        let conn_mgr = setup_conn_mgr(database_path);
-        NutriplanSqliteDbIngredientMacro{ conn_mgr }
+    #[deprecated(note = "use `NutriplanDb::ingredient_macros`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.ingredient_macros())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
     }
 }
 
 impl INutriplanDbIngredientMacro for NutriplanSqliteDbIngredientMacro {
-    fn create(&self, item: NewIngredientMacro) -> bool {
-        CRUDIngredientMacro::create(&self.conn_mgr, &item)
+    fn create(&self, item: NewIngredientMacro) -> Result<i32, NutriplanError> {
+        CRUDIngredientMacro::create(&self.conn_mgr()?, &item)
+    }
+    fn read(&self, id: i32) -> Result<IngredientMacro, NutriplanError> {
+        CRUDIngredientMacro::read(&self.conn_mgr()?, id)
+    }
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<IngredientMacro>, NutriplanError> {
+        CRUDIngredientMacro::list(&self.conn_mgr()?, limit, offset)
+    }
+    fn recompute_calories(&self, model: &EnergyModel) -> Result<usize, NutriplanError> {
+        CRUDIngredientMacro::recompute_calories(&self.conn_mgr()?, model)
+    }
+    fn update(&self, item: IngredientMacro) -> Result<(), NutriplanError> {
+        match item.id {
+            Some(id) => CRUDIngredientMacro::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
+        }
+    }
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDIngredientMacro::delete(&self.conn_mgr()?, id)
+    }
+}
+
+pub struct NutriplanSqliteDbIngredientMicro {
+    pool: DbPool
+}
+
+impl NutriplanSqliteDbIngredientMicro {
+    #[deprecated(note = "use `NutriplanDb::ingredient_micros`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.ingredient_micros())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
+    }
+}
+
+impl INutriplanDbIngredientMicro for NutriplanSqliteDbIngredientMicro {
+    fn create(&self, item: NewIngredientMicro) -> Result<i32, NutriplanError> {
+        CRUDIngredientMicro::create(&self.conn_mgr()?, &item)
+    }
+    fn read(&self, id: i32) -> Result<IngredientMicro, NutriplanError> {
+        CRUDIngredientMicro::read(&self.conn_mgr()?, id)
+    }
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<IngredientMicro>, NutriplanError> {
+        CRUDIngredientMicro::list(&self.conn_mgr()?, limit, offset)
     }
-    fn read(&self, id: i32) -> Option<IngredientMacro> {
-        CRUDIngredientMacro::read(&self.conn_mgr, id)
+    fn list_by_ingredient(&self, ingredient_id: i32)
+        -> Result<Vec<IngredientMicro>, NutriplanError>
+    {
+        CRUDIngredientMicro::list_by_ingredient_id(&self.conn_mgr()?, ingredient_id)
     }
-    fn update(&self, item: IngredientMacro) -> bool {
+    fn update(&self, item: IngredientMicro) -> Result<(), NutriplanError> {
         match item.id {
-            Some(id) => CRUDIngredientMacro::update(&self.conn_mgr, id, item),
-            None => false
+            Some(id) => CRUDIngredientMicro::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
         }
     }
-    fn delete(&self, id: i32) -> bool {
-        CRUDIngredientMacro::delete(&self.conn_mgr, id)
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDIngredientMicro::delete(&self.conn_mgr()?, id)
+    }
+}
+
+pub struct NutriplanSqliteDbIngredientPortion {
+    pool: DbPool
+}
+
+impl NutriplanSqliteDbIngredientPortion {
+    #[deprecated(note = "use `NutriplanDb::ingredient_portions`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.ingredient_portions())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
+    }
+}
+
+impl INutriplanDbIngredientPortion for NutriplanSqliteDbIngredientPortion {
+    fn create(&self, item: NewIngredientPortion) -> Result<i32, NutriplanError> {
+        CRUDIngredientPortion::create(&self.conn_mgr()?, &item)
+    }
+    fn read(&self, id: i32) -> Result<IngredientPortion, NutriplanError> {
+        CRUDIngredientPortion::read(&self.conn_mgr()?, id)
+    }
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<IngredientPortion>, NutriplanError> {
+        CRUDIngredientPortion::list(&self.conn_mgr()?, limit, offset)
+    }
+    fn list_by_ingredient(&self, ingredient_id: i32)
+        -> Result<Vec<IngredientPortion>, NutriplanError>
+    {
+        CRUDIngredientPortion::list_by_ingredient_id(&self.conn_mgr()?, ingredient_id)
+    }
+    fn to_grams(&self, ingredient_id: i32, quantity: &Quantity) -> Result<f32, NutriplanError> {
+        CRUDIngredientPortion::to_grams(&self.conn_mgr()?, ingredient_id, quantity)
+    }
+    fn update(&self, item: IngredientPortion) -> Result<(), NutriplanError> {
+        match item.id {
+            Some(id) => CRUDIngredientPortion::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
+        }
+    }
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDIngredientPortion::delete(&self.conn_mgr()?, id)
+    }
+}
+
+pub struct NutriplanSqliteDbMicronutrient {
+    pool: DbPool
+}
+
+impl NutriplanSqliteDbMicronutrient {
+    #[deprecated(note = "use `NutriplanDb::micronutrients`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.micronutrients())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
+    }
+}
+
+impl INutriplanDbMicronutrient for NutriplanSqliteDbMicronutrient {
+    fn create(&self, item: NewMicronutrient) -> Result<i32, NutriplanError> {
+        CRUDMicronutrient::create(&self.conn_mgr()?, &item)
+    }
+    fn read(&self, id: i32) -> Result<Micronutrient, NutriplanError> {
+        CRUDMicronutrient::read(&self.conn_mgr()?, id)
+    }
+    fn find_by_code(&self, code: &str) -> Result<Option<Micronutrient>, NutriplanError> {
+        CRUDMicronutrient::find_by_code(&self.conn_mgr()?, code)
+    }
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Micronutrient>, NutriplanError> {
+        CRUDMicronutrient::list(&self.conn_mgr()?, limit, offset)
+    }
+    fn update(&self, item: Micronutrient) -> Result<(), NutriplanError> {
+        match item.id {
+            Some(id) => CRUDMicronutrient::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
+        }
+    }
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDMicronutrient::delete(&self.conn_mgr()?, id)
     }
 }
 
 pub struct NutriplanSqliteDbMeal {
-    conn_mgr: ConnMgrPool
+    pool: DbPool
 }
 
 impl NutriplanSqliteDbMeal {
-    pub fn new(database_path: &str) -> Self {
-        let conn_mgr = setup_conn_mgr(database_path);
-        NutriplanSqliteDbMeal{ conn_mgr }
+    #[deprecated(note = "use `NutriplanDb::meals`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.meals())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
     }
 }
 
 impl INutriplanDbMeal for NutriplanSqliteDbMeal {
-    fn create(&self, item: NewMeal) -> bool {
-        CRUDMeal::create(&self.conn_mgr, &item)
+    fn create(&self, item: NewMeal) -> Result<i32, NutriplanError> {
+        CRUDMeal::create(&self.conn_mgr()?, &item)
+    }
+    fn read(&self, id: i32) -> Result<Meal, NutriplanError> {
+        CRUDMeal::read(&self.conn_mgr()?, id)
+    }
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Meal>, NutriplanError> {
+        CRUDMeal::list(&self.conn_mgr()?, limit, offset)
+    }
+    fn list_by_date(&self, start: &NaiveDate, end: &NaiveDate, profile: Option<i32>)
+        -> Result<Vec<Meal>, NutriplanError>
+    {
+        CRUDMeal::list_by_date(&self.conn_mgr()?, start, end, profile)
+    }
+    fn list_by_profile(&self, profile_id: i32, limit: i64, offset: i64)
+        -> Result<Vec<Meal>, NutriplanError>
+    {
+        CRUDMeal::list_by_profile(&self.conn_mgr()?, profile_id, limit, offset)
+    }
+    fn nutrients(&self, id: i32) -> Result<MealNutrients, NutriplanError> {
+        CRUDMeal::nutrients(&self.conn_mgr()?, id)
     }
-    fn read(&self, id: i32) -> Option<Meal> {
-        CRUDMeal::read(&self.conn_mgr, id)
+    fn add_recipe(&self, meal_id: i32, recipe_id: i32, portion: RecipePortion)
+        -> Result<Vec<i32>, NutriplanError>
+    {
+        CRUDMeal::add_recipe(&self.conn_mgr()?, meal_id, recipe_id, portion)
     }
-    fn update(&self, item: Meal) -> bool {
+    fn update(&self, item: Meal) -> Result<(), NutriplanError> {
         match item.id {
-            Some(id) => CRUDMeal::update(&self.conn_mgr, id, item),
-            None => false
+            Some(id) => CRUDMeal::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
         }
     }
-    fn delete(&self, id: i32) -> bool {
-        CRUDMeal::delete(&self.conn_mgr, id)
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDMeal::delete(&self.conn_mgr()?, id)
     }
 }
 
 pub struct NutriplanSqliteDbMealIngredient {
-    conn_mgr: ConnMgrPool
+    pool: DbPool
 }
 
 impl NutriplanSqliteDbMealIngredient {
-    pub fn new(database_path: &str) -> Self {
-        let conn_mgr = setup_conn_mgr(database_path);
-        NutriplanSqliteDbMealIngredient{ conn_mgr }
+    #[deprecated(note = "use `NutriplanDb::meal_ingredients`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.meal_ingredients())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
     }
 }
 
 impl INutriplanDbMealIngredient for NutriplanSqliteDbMealIngredient {
-    fn create(&self, item: NewMealIngredient) -> bool {
-        CRUDMealIngredient::create(&self.conn_mgr, &item)
+    fn create(&self, item: NewMealIngredient) -> Result<i32, NutriplanError> {
+        CRUDMealIngredient::create(&self.conn_mgr()?, &item)
+    }
+
+    fn create_from_quantity(&self, meal_id: i32, ingredient_id: i32, quantity: &Quantity)
+        -> Result<i32, NutriplanError>
+    {
+        CRUDMealIngredient::create_from_quantity(&self.conn_mgr()?, meal_id, ingredient_id,
+            quantity)
     }
 
-    fn read(&self, id: i32) -> Option<MealIngredient> {
-        CRUDMealIngredient::read(&self.conn_mgr, id)
+    fn read(&self, id: i32) -> Result<MealIngredient, NutriplanError> {
+        CRUDMealIngredient::read(&self.conn_mgr()?, id)
     }
 
-    fn update(&self, item: MealIngredient) -> bool {
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<MealIngredient>, NutriplanError> {
+        CRUDMealIngredient::list(&self.conn_mgr()?, limit, offset)
+    }
+
+    fn update(&self, item: MealIngredient) -> Result<(), NutriplanError> {
         match item.id {
-            Some(id) => CRUDMealIngredient::update(&self.conn_mgr, id, item),
-            None => false
+            Some(id) => CRUDMealIngredient::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
         }
     }
 
-    fn delete(&self, id: i32) -> bool {
-        CRUDMealIngredient::delete(&self.conn_mgr, id)
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDMealIngredient::delete(&self.conn_mgr()?, id)
+    }
+}
+
+pub struct NutriplanSqliteDbProfile {
+    pool: DbPool
+}
+
+impl NutriplanSqliteDbProfile {
+    #[deprecated(note = "use `NutriplanDb::profiles`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.profiles())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
+    }
+}
+
+impl INutriplanDbProfile for NutriplanSqliteDbProfile {
+    fn create(&self, item: NewProfile) -> Result<i32, NutriplanError> {
+        CRUDProfile::create(&self.conn_mgr()?, &item)
+    }
+    fn read(&self, id: i32) -> Result<Profile, NutriplanError> {
+        CRUDProfile::read(&self.conn_mgr()?, id)
+    }
+    fn find_by_name(&self, name: &str) -> Result<Option<Profile>, NutriplanError> {
+        CRUDProfile::find_by_name(&self.conn_mgr()?, name)
+    }
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Profile>, NutriplanError> {
+        CRUDProfile::list(&self.conn_mgr()?, limit, offset)
+    }
+    fn update(&self, item: Profile) -> Result<(), NutriplanError> {
+        match item.id {
+            Some(id) => CRUDProfile::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
+        }
+    }
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDProfile::delete(&self.conn_mgr()?, id)
     }
 }
 
 pub struct NutriplanSqliteDbRecipe {
-    conn_mgr: ConnMgrPool
+    pool: DbPool
 }
 
 impl NutriplanSqliteDbRecipe {
-    pub fn new(database_path: &str) -> Self {
-        let conn_mgr = setup_conn_mgr(database_path);
-        NutriplanSqliteDbRecipe{ conn_mgr }
+    #[deprecated(note = "use `NutriplanDb::recipes`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.recipes())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
     }
 }
 
 impl INutriplanDbRecipe for NutriplanSqliteDbRecipe {
-    fn create(&self, item: NewRecipe) -> bool {
-        CRUDRecipe::create(&self.conn_mgr, &item)
+    fn create(&self, item: NewRecipe) -> Result<i32, NutriplanError> {
+        CRUDRecipe::create(&self.conn_mgr()?, &item)
+    }
+
+    fn read(&self, id: i32) -> Result<Recipe, NutriplanError> {
+        CRUDRecipe::read(&self.conn_mgr()?, id)
     }
 
-    fn read(&self, id: i32) -> Option<Recipe> {
-        CRUDRecipe::read(&self.conn_mgr, id)
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Recipe>, NutriplanError> {
+        CRUDRecipe::list(&self.conn_mgr()?, limit, offset)
     }
 
-    fn update(&self, item: Recipe) -> bool {
+    fn list_by_profile(&self, profile_id: i32, limit: i64, offset: i64)
+        -> Result<Vec<Recipe>, NutriplanError>
+    {
+        CRUDRecipe::list_by_profile(&self.conn_mgr()?, profile_id, limit, offset)
+    }
+
+    fn update(&self, item: Recipe) -> Result<(), NutriplanError> {
         match item.id {
-            Some(id) => CRUDRecipe::update(&self.conn_mgr, id, item),
-            None => false
+            Some(id) => CRUDRecipe::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
         }
     }
 
-    fn delete(&self, id: i32) -> bool {
-        CRUDRecipe::delete(&self.conn_mgr, id)
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDRecipe::delete(&self.conn_mgr()?, id)
+    }
+
+    fn nutrients(&self, id: i32) -> Result<RecipeNutrients, NutriplanError> {
+        CRUDRecipe::nutrients(&self.conn_mgr()?, id)
+    }
+}
+
+pub struct NutriplanSqliteDbRecipeComponent {
+    pool: DbPool
+}
+
+impl NutriplanSqliteDbRecipeComponent {
+    #[deprecated(note = "use `NutriplanDb::recipe_components`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.recipe_components())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
     }
 }
 
 pub struct NutriplanSqliteDbRecipeIngredient {
-    conn_mgr: ConnMgrPool
+    pool: DbPool
 }
 
 impl NutriplanSqliteDbRecipeIngredient {
-    pub fn new(database_path: &str) -> Self {
-        let conn_mgr = setup_conn_mgr(database_path);
-        NutriplanSqliteDbRecipeIngredient{ conn_mgr }
+    #[deprecated(note = "use `NutriplanDb::recipe_ingredients`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.recipe_ingredients())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
     }
 }
 
 use controller::database::{
     CRUDController,
+    goal::CRUDGoal,
     ingredient::CRUDIngredient,
     ingredient_macro::CRUDIngredientMacro,
+    ingredient_micro::CRUDIngredientMicro,
+    ingredient_portion::CRUDIngredientPortion,
     meal::CRUDMeal,
     meal_ingredient::CRUDMealIngredient,
+    micronutrient::CRUDMicronutrient,
+    profile::CRUDProfile,
     recipe::CRUDRecipe,
+    recipe_component::CRUDRecipeComponent,
     recipe_ingredient::CRUDRecipeIngredient,
 };
 
+impl INutriplanDbRecipeComponent for NutriplanSqliteDbRecipeComponent {
+    fn create(&self, item: NewRecipeComponent) -> Result<i32, NutriplanError> {
+        CRUDRecipeComponent::create(&self.conn_mgr()?, &item)
+    }
+    fn read(&self, id: i32) -> Result<RecipeComponent, NutriplanError> {
+        CRUDRecipeComponent::read(&self.conn_mgr()?, id)
+    }
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<RecipeComponent>, NutriplanError> {
+        CRUDRecipeComponent::list(&self.conn_mgr()?, limit, offset)
+    }
+    fn update(&self, item: RecipeComponent) -> Result<(), NutriplanError> {
+        match item.id {
+            Some(id) => CRUDRecipeComponent::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
+        }
+    }
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDRecipeComponent::delete(&self.conn_mgr()?, id)
+    }
+}
+
 impl INutriplanDbRecipeIngredient for NutriplanSqliteDbRecipeIngredient {
-    fn create(&self, item: NewRecipeIngredient) -> bool {
-        CRUDRecipeIngredient::create(&self.conn_mgr, &item)
+    fn create(&self, item: NewRecipeIngredient) -> Result<i32, NutriplanError> {
+        CRUDRecipeIngredient::create(&self.conn_mgr()?, &item)
+    }
+    fn create_from_quantity(&self, recipe_id: i32, ingredient_id: i32, quantity: &Quantity)
+        -> Result<i32, NutriplanError>
+    {
+        CRUDRecipeIngredient::create_from_quantity(&self.conn_mgr()?, recipe_id, ingredient_id,
+            quantity)
     }
-    fn read(&self, id: i32) -> Option<RecipeIngredient> {
-        CRUDRecipeIngredient::read(&self.conn_mgr, id)
+    fn read(&self, id: i32) -> Result<RecipeIngredient, NutriplanError> {
+        CRUDRecipeIngredient::read(&self.conn_mgr()?, id)
     }
-    fn update(&self, item: RecipeIngredient) -> bool {
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<RecipeIngredient>, NutriplanError> {
+        CRUDRecipeIngredient::list(&self.conn_mgr()?, limit, offset)
+    }
+    fn update(&self, item: RecipeIngredient) -> Result<(), NutriplanError> {
         match item.id {
-            Some(id) => CRUDRecipeIngredient::update(&self.conn_mgr, id, item),
-            None => false
+            Some(id) => CRUDRecipeIngredient::update(&self.conn_mgr()?, id, item),
+            None => Err(NutriplanError::InvalidInput(String::from("item has no id")))
         }
     }
-    fn delete(&self, id: i32) -> bool {
-        CRUDRecipeIngredient::delete(&self.conn_mgr, id)
+    fn delete(&self, id: i32) -> Result<(), NutriplanError> {
+        CRUDRecipeIngredient::delete(&self.conn_mgr()?, id)
+    }
+}
+
+pub struct NutriplanSqliteDbReport {
+    pool: DbPool
+}
+
+impl NutriplanSqliteDbReport {
+    #[deprecated(note = "use `NutriplanDb::reports`, which shares one connection pool")]
+    pub fn new(database_path: &str) -> Result<Self, NutriplanError> {
+        Ok(NutriplanDb::new(database_path)?.reports())
+    }
+
+    fn conn_mgr(&self) -> Result<ConnMgrPool, NutriplanError> {
+        Ok(self.pool.get()?)
+    }
+}
+
+impl INutriplanDbReport for NutriplanSqliteDbReport {
+    fn daily(&self, start: &NaiveDate, end: &NaiveDate, profile: Option<i32>)
+        -> Result<Vec<DaySummary>, NutriplanError>
+    {
+        Report::daily(&self.conn_mgr()?, start, end, profile)
+    }
+
+    fn summarize(&self, start: &NaiveDate, end: &NaiveDate, period: Period,
+        profile: Option<i32>) -> Result<Vec<PeriodSummary>, NutriplanError>
+    {
+        Report::summarize(&self.conn_mgr()?, start, end, period, profile)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::util::test::run_db_test;
+
+    #[test]
+    fn entity_handles_operate_on_shared_pool() {
+        run_db_test(|| {
+            let db = NutriplanDb::new("test.db").unwrap();
+            let id = db.ingredients().create(NewIngredient::new("shared")).unwrap();
+            let item = NewIngredientMacro::new(id, 1.0, 1.0, 1.0, 1.0);
+            assert!(db.ingredient_macros().create(item).is_ok());
+            assert_eq!(db.ingredients().read(id).unwrap().name, "shared");
+        })
+    }
+
+    #[test]
+    fn update_of_read_ingredient_keeps_source() {
+        run_db_test(|| {
+            use std::process::Command;
+            use std::str;
+            let db = NutriplanDb::new("test.db").unwrap();
+            let imported = NewIngredient {
+                source: Some(String::from("fdc")),
+                source_id: Some(String::from("173904")),
+                ..NewIngredient::new("oats")
+            };
+            let id = db.ingredients().create(imported).unwrap();
+            let existing = db.ingredients().read(id).unwrap();
+            let item = Ingredient {
+                name: String::from("rolled oats"),
+                density: Some(0.4),
+                ..existing
+            };
+            db.ingredients().update(item).unwrap();
+            let output = Command::new("sqlite3")
+                .arg("test.db")
+                .arg("SELECT name, source, source_id FROM ingredients WHERE id = 3;")
+                .output()
+                .expect("Failed to execute process");
+            assert_eq!("rolled oats|fdc|173904\n", str::from_utf8(&output.stdout).unwrap());
+        })
+    }
+
+    #[test]
+    fn entity_handle_outlives_database_handle() {
+        run_db_test(|| {
+            let meals = NutriplanDb::new("test.db").unwrap().meals();
+            assert_eq!(meals.read(1).unwrap().name, "testmeal1");
+        })
+    }
+
+    #[test]
+    fn transaction_commits_all_operations() {
+        run_db_test(|| {
+            use crate::controller::database::CRUDController;
+            let db = NutriplanDb::new("test.db").unwrap();
+            let date = chrono::NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+            let time = chrono::NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+            let meal_id = db.transaction(|tx| {
+                let meal_id = CRUDMeal::create(tx, &NewMeal::new("atomic", &date, &time))?;
+                CRUDMealIngredient::create(tx, &NewMealIngredient::new(meal_id, 1, 100.0))?;
+                Ok(meal_id)
+            }).unwrap();
+            assert_eq!(db.meals().read(meal_id).unwrap().name, "atomic");
+            assert_eq!(db.meal_ingredients().read(3).unwrap().meal_id, meal_id);
+        })
+    }
+
+    #[test]
+    fn transaction_rolls_back_on_error() {
+        run_db_test(|| {
+            use crate::controller::database::CRUDController;
+            let db = NutriplanDb::new("test.db").unwrap();
+            let date = chrono::NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+            let time = chrono::NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+            let ret_val = db.transaction(|tx| {
+                let meal_id = CRUDMeal::create(tx, &NewMeal::new("half", &date, &time))?;
+                CRUDMealIngredient::create(tx, &NewMealIngredient::new(meal_id, 3, 100.0))
+            });
+            assert!(matches!(ret_val, Err(NutriplanError::ForeignKeyViolation(_))));
+            assert!(matches!(db.meals().read(3), Err(NutriplanError::NotFound { .. })));
+        })
     }
 }
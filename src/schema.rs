@@ -1,3 +1,21 @@
+table! {
+    goals (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+        weekday -> Nullable<Integer>,
+        start_date -> Nullable<Date>,
+        end_date -> Nullable<Date>,
+        calories -> Nullable<Float>,
+        proteins -> Nullable<Float>,
+        carbs -> Nullable<Float>,
+        fats -> Nullable<Float>,
+        alcohols -> Nullable<Float>,
+        fiber -> Nullable<Float>,
+        polyols -> Nullable<Float>,
+        profile_id -> Nullable<Integer>,
+    }
+}
+
 table! {
     ingredient_macros (id) {
         id -> Nullable<Integer>,
@@ -7,6 +25,26 @@ table! {
         fats -> Float,
         alcohols -> Float,
         calories -> Float,
+        fiber -> Float,
+        polyols -> Float,
+    }
+}
+
+table! {
+    ingredient_micros (id) {
+        id -> Nullable<Integer>,
+        ingredient_id -> Integer,
+        micronutrient_id -> Integer,
+        amount -> Float,
+    }
+}
+
+table! {
+    ingredient_portions (id) {
+        id -> Nullable<Integer>,
+        ingredient_id -> Integer,
+        name -> Text,
+        mass -> Float,
     }
 }
 
@@ -14,6 +52,11 @@ table! {
     ingredients (id) {
         id -> Nullable<Integer>,
         name -> Text,
+        density -> Nullable<Float>,
+        profile_id -> Nullable<Integer>,
+        barcode -> Nullable<Text>,
+        source -> Nullable<Text>,
+        source_id -> Nullable<Text>,
     }
 }
 
@@ -22,7 +65,9 @@ table! {
         id -> Nullable<Integer>,
         meal_id -> Integer,
         ingredient_id -> Integer,
-        mass -> Integer,
+        mass -> Float,
+        quantity -> Nullable<Float>,
+        unit -> Nullable<Text>,
     }
 }
 
@@ -32,6 +77,25 @@ table! {
         name -> Text,
         date -> Date,
         time -> Time,
+        profile_id -> Nullable<Integer>,
+    }
+}
+
+table! {
+    micronutrients (id) {
+        id -> Nullable<Integer>,
+        code -> Text,
+        name -> Text,
+        unit -> Text,
+    }
+}
+
+table! {
+    recipe_components (id) {
+        id -> Nullable<Integer>,
+        recipe_id -> Integer,
+        component_id -> Integer,
+        mass -> Float,
     }
 }
 
@@ -40,7 +104,9 @@ table! {
         id -> Nullable<Integer>,
         recipe_id -> Integer,
         ingredient_id -> Integer,
-        mass -> Integer,
+        mass -> Float,
+        quantity -> Nullable<Float>,
+        unit -> Nullable<Text>,
     }
 }
 
@@ -49,14 +115,44 @@ table! {
         id -> Nullable<Integer>,
         name -> Text,
         description -> Text,
+        servings -> Integer,
+        yield_mass -> Nullable<Float>,
+        profile_id -> Nullable<Integer>,
     }
 }
 
+table! {
+    profiles (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+    }
+}
+
+joinable!(goals -> profiles (profile_id));
+joinable!(ingredient_macros -> ingredients (ingredient_id));
+joinable!(ingredient_micros -> ingredients (ingredient_id));
+joinable!(ingredient_micros -> micronutrients (micronutrient_id));
+joinable!(ingredient_portions -> ingredients (ingredient_id));
+joinable!(ingredients -> profiles (profile_id));
+joinable!(meal_ingredients -> ingredients (ingredient_id));
+joinable!(meal_ingredients -> meals (meal_id));
+joinable!(meals -> profiles (profile_id));
+joinable!(recipe_components -> recipes (recipe_id));
+joinable!(recipe_ingredients -> ingredients (ingredient_id));
+joinable!(recipe_ingredients -> recipes (recipe_id));
+joinable!(recipes -> profiles (profile_id));
+
 allow_tables_to_appear_in_same_query!(
+    goals,
     ingredient_macros,
+    ingredient_micros,
+    ingredient_portions,
     ingredients,
     meal_ingredients,
     meals,
+    micronutrients,
+    profiles,
+    recipe_components,
     recipe_ingredients,
     recipes,
 );
@@ -1,107 +1,115 @@
-timeout=0
+# Schema of the test database, the rows of the tests are restored from fixtures.json
 
 sqlite3 test.db <<EOF
-CREATE TABLE IF NOT EXISTS ingredients (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL);
-EOF
-
-sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO ingredients (name) VALUES ("test1");
-EOF
-
-sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO ingredients (name) VALUES ("test2");
-EOF
-
-sqlite3 test.db <<EOF
-CREATE TABLE IF NOT EXISTS ingredient_macros (id INTEGER PRIMARY KEY AUTOINCREMENT, ingredient_id INTEGER NOT NULL, proteins FLOAT NOT NULL, carbs FLOAT NOT NULL, fats FLOAT NOT NULL, alcohols FLOAT NOT NULL, calories FLOAT NOT NULL);
+CREATE TABLE profiles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE
+);
 EOF
 
 sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO ingredient_macros (ingredient_id, proteins, carbs, fats, alcohols, calories) VALUES (1, 1.0, 1.0, 1.0, 1.0, 1.0);
+CREATE TABLE IF NOT EXISTS ingredients (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, density FLOAT, profile_id INTEGER REFERENCES profiles (id) ON DELETE SET NULL, barcode TEXT UNIQUE, source TEXT, source_id TEXT, UNIQUE (source, source_id));
 EOF
 
 sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO ingredient_macros (ingredient_id, proteins, carbs, fats, alcohols, calories) VALUES (2, 2.0, 2.0, 2.0, 2.0, 2.0);
+CREATE TABLE IF NOT EXISTS ingredient_macros (id INTEGER PRIMARY KEY AUTOINCREMENT, ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE, proteins FLOAT NOT NULL, carbs FLOAT NOT NULL, fats FLOAT NOT NULL, alcohols FLOAT NOT NULL, calories FLOAT NOT NULL, fiber FLOAT NOT NULL DEFAULT 0, polyols FLOAT NOT NULL DEFAULT 0);
 EOF
 
 sqlite3 test.db <<EOF
 CREATE TABLE IF NOT EXISTS meals (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, date
-DATE NOT NULL, time TIME NOT NULL);
-EOF
-
-sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO meals (name, date, time) VALUES ("testmeal1", "2000-01-01", "08:00:00");
-EOF
-
-sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO meals (name, date, time) VALUES ("testmeal2", "2000-02-02", "20:00:00");
+DATE NOT NULL, time TIME NOT NULL, profile_id INTEGER REFERENCES profiles (id) ON DELETE CASCADE);
 EOF
 
 sqlite3 test.db <<EOF
 CREATE TABLE meal_ingredients ( \
     id INTEGER PRIMARY KEY AUTOINCREMENT, \
-    meal_id INTEGER NOT NULL, \
-    ingredient_id INTEGER NOT NULL, \
-    mass INTEGER NOT NULL \
+    meal_id INTEGER NOT NULL REFERENCES meals (id) ON DELETE CASCADE, \
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE, \
+    mass FLOAT NOT NULL CHECK (mass >= 0), \
+    quantity FLOAT, \
+    unit TEXT \
 )
 EOF
 
-sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO meal_ingredients (meal_id, ingredient_id, mass)\
-    VALUES (1, 1, 111);
-EOF
-
-sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO meal_ingredients (meal_id, ingredient_id, mass)\
-    VALUES (2, 2, 222);
-EOF
-
 sqlite3 test.db <<EOF
 CREATE TABLE recipes (\
     id INTEGER PRIMARY KEY AUTOINCREMENT,\
     name TEXT NOT NULL,\
-    description TEXT NOT NULL\
+    description TEXT NOT NULL,\
+    servings INTEGER NOT NULL DEFAULT 1,\
+    yield_mass FLOAT,\
+    profile_id INTEGER REFERENCES profiles (id) ON DELETE SET NULL\
 )
 EOF
 
 sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO recipes (name, description)\
-    VALUES ("testitem1", "testdescription1");
+CREATE TABLE recipe_ingredients (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipe_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    mass FLOAT NOT NULL CHECK (mass >= 0),
+    quantity FLOAT,
+    unit TEXT
+)
 EOF
 
 sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO recipes (name, description)\
-    VALUES ("testitem2", "testdescription2");
+CREATE TABLE recipe_components (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipe_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    component_id INTEGER NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+    mass FLOAT NOT NULL CHECK (mass >= 0)
+)
 EOF
 
 sqlite3 test.db <<EOF
-CREATE TABLE recipe_ingredients (
+CREATE TABLE micronutrients (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
-    recipe_id INTEGER NOT NULL,
-    ingredient_id INTEGER NOT NULL,
-    mass INTEGER NOT NULL
-)
+    code TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    unit TEXT NOT NULL
+);
+INSERT INTO micronutrients (code, name, unit) VALUES
+    ('sugars', 'Sugars', 'g'),
+    ('saturates', 'Saturated fat', 'g'),
+    ('salt', 'Salt', 'g'),
+    ('sodium', 'Sodium', 'mg');
 EOF
 
 sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO recipe_ingredients (recipe_id, ingredient_id, mass)\
-    VALUES (1, 1, 111);
+CREATE TABLE ingredient_micros (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    micronutrient_id INTEGER NOT NULL REFERENCES micronutrients (id) ON DELETE CASCADE,
+    amount FLOAT NOT NULL,
+    UNIQUE (ingredient_id, micronutrient_id)
+);
 EOF
 
 sqlite3 test.db <<EOF
-.timeout $timeout
-INSERT INTO recipe_ingredients (recipe_id, ingredient_id, mass)\
-    VALUES (2, 2, 222);
+CREATE TABLE ingredient_portions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    mass FLOAT NOT NULL,
+    UNIQUE (ingredient_id, name)
+);
 EOF
 
+sqlite3 test.db <<EOF
+CREATE TABLE goals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    weekday INTEGER CHECK (weekday BETWEEN 0 AND 6),
+    start_date DATE,
+    end_date DATE,
+    calories FLOAT,
+    proteins FLOAT,
+    carbs FLOAT,
+    fats FLOAT,
+    alcohols FLOAT,
+    fiber FLOAT,
+    polyols FLOAT,
+    profile_id INTEGER REFERENCES profiles (id) ON DELETE CASCADE
+);
+EOF